@@ -0,0 +1,44 @@
+use crate::output;
+use colored::*;
+use std::io::{self, Write};
+
+/// ファイルの書き込みやコマンドの実行を許可するかを決める
+pub trait Approver: Send + Sync {
+    /// `action` を実行してよければ true を返す（`details` は差分やコマンドなどの補足）
+    fn approve(&self, action: &str, details: &str) -> bool;
+}
+
+/// 端末でユーザーに確認する
+#[derive(Debug, Default)]
+pub struct PromptApprover;
+
+impl Approver for PromptApprover {
+    fn approve(&self, action: &str, details: &str) -> bool {
+        output::on_stderr(|| {
+            eprintln!();
+            eprintln!("{} {}", "🔐".yellow(), action.bold());
+            if !details.is_empty() {
+                eprintln!("{}", details);
+            }
+            eprint!("{}", "❓ Allow? [y/N]: ".yellow());
+        });
+        io::stderr().flush().ok();
+
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            // 入力が閉じている場合は許可しない
+            Ok(0) | Err(_) => false,
+            Ok(_) => matches!(input.trim().to_lowercase().as_str(), "y" | "yes"),
+        }
+    }
+}
+
+/// 確認せずに常に同じ答えを返す（`--yes` やテスト用）
+#[derive(Debug, Clone, Copy)]
+pub struct AutoApprover(pub bool);
+
+impl Approver for AutoApprover {
+    fn approve(&self, _action: &str, _details: &str) -> bool {
+        self.0
+    }
+}
@@ -0,0 +1,9 @@
+pub mod approval;
+pub mod sandbox;
+pub mod session;
+pub mod tools;
+
+pub use approval::*;
+pub use sandbox::*;
+pub use session::*;
+pub use tools::*;
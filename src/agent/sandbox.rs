@@ -0,0 +1,172 @@
+use crate::error::{AskError, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// エージェントのツールがアクセスできるディレクトリ
+///
+/// パスはすべてルートからの相対パスとして解釈し、シンボリックリンクを
+/// たどった先も含めてルートの外を指すパスは拒否する。
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref();
+        let root = root.canonicalize().map_err(|e| {
+            AskError::ToolError(format!("Cannot open sandbox {}: {}", root.display(), e))
+        })?;
+        if !root.is_dir() {
+            return Err(AskError::ToolError(format!(
+                "Sandbox root {} is not a directory",
+                root.display()
+            )));
+        }
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 既存のファイルやディレクトリのパスを解決する
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let joined = self.join(path)?;
+        let resolved = joined
+            .canonicalize()
+            .map_err(|e| AskError::ToolError(format!("{}: {}", path, e)))?;
+        self.check_inside(path, &resolved)?;
+        Ok(resolved)
+    }
+
+    /// 書き込み先のパスを解決する（ファイルはまだ存在しなくてもよい）
+    ///
+    /// 存在しない親ディレクトリは、書き込み時に作成される前提でルートの中にあるかだけを確認する。
+    pub fn resolve_for_write(&self, path: &str) -> Result<PathBuf> {
+        let joined = self.join(path)?;
+        // リンク切れのシンボリックリンクも「存在する」とみなし、リンク先を確認させる
+        if joined.symlink_metadata().is_ok() {
+            let resolved = self.resolve(path)?;
+            if resolved.is_dir() {
+                return Err(AskError::ToolError(format!("{} is a directory", path)));
+            }
+            return Ok(resolved);
+        }
+
+        // 存在する最も近い祖先をたどり、それがルートの中にあることを確認する
+        let mut existing = joined.as_path();
+        let mut missing = Vec::new();
+        while existing.symlink_metadata().is_err() {
+            missing.push(existing.file_name().ok_or_else(|| invalid_path(path))?);
+            existing = existing.parent().ok_or_else(|| invalid_path(path))?;
+        }
+        let mut resolved = existing
+            .canonicalize()
+            .map_err(|e| AskError::ToolError(format!("{}: {}", path, e)))?;
+        self.check_inside(path, &resolved)?;
+        for name in missing.into_iter().rev() {
+            resolved.push(name);
+        }
+        Ok(resolved)
+    }
+
+    /// 表示用にルートからの相対パスを返す
+    pub fn display(&self, path: &Path) -> String {
+        match path.strip_prefix(&self.root) {
+            Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+            Ok(relative) => relative.display().to_string(),
+            Err(_) => path.display().to_string(),
+        }
+    }
+
+    /// `..` を字句的に取り除いてルートと結合する（ルートより上には出られない）
+    fn join(&self, path: &str) -> Result<PathBuf> {
+        let mut joined = self.root.clone();
+        let mut depth = 0usize;
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(name) => {
+                    joined.push(name);
+                    depth += 1;
+                }
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if depth == 0 {
+                        return Err(outside(path));
+                    }
+                    joined.pop();
+                    depth -= 1;
+                }
+                Component::RootDir | Component::Prefix(_) => {
+                    // 絶対パスはルートの中を指している場合だけ受け付ける
+                    let absolute = Path::new(path);
+                    let relative = absolute
+                        .strip_prefix(&self.root)
+                        .map_err(|_| outside(path))?;
+                    return self.join(&relative.to_string_lossy());
+                }
+            }
+        }
+        Ok(joined)
+    }
+
+    fn check_inside(&self, path: &str, resolved: &Path) -> Result<()> {
+        if resolved.starts_with(&self.root) {
+            Ok(())
+        } else {
+            Err(outside(path))
+        }
+    }
+}
+
+fn outside(path: &str) -> AskError {
+    AskError::ToolError(format!("{} is outside the sandbox", path))
+}
+
+fn invalid_path(path: &str) -> AskError {
+    AskError::ToolError(format!("Invalid path: {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_stays_inside_root() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        let sandbox = Sandbox::new(dir.path()).unwrap();
+
+        let resolved = sandbox.resolve("src/../src/main.rs").unwrap();
+        assert_eq!(sandbox.display(&resolved), "src/main.rs");
+        assert_eq!(sandbox.display(&sandbox.resolve(".").unwrap()), ".");
+
+        assert!(sandbox.resolve("../etc/passwd").is_err());
+        assert!(sandbox.resolve("/etc/passwd").is_err());
+        assert!(sandbox.resolve_for_write("src/../../escape.txt").is_err());
+
+        let new_file = sandbox.resolve_for_write("docs/notes/todo.md").unwrap();
+        assert_eq!(sandbox.display(&new_file), "docs/notes/todo.md");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_cannot_escape() {
+        let outside_dir = tempfile::TempDir::new().unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside_dir.path(), dir.path().join("link")).unwrap();
+        let sandbox = Sandbox::new(dir.path()).unwrap();
+
+        assert!(sandbox.resolve("link").is_err());
+        assert!(sandbox.resolve_for_write("link/file.txt").is_err());
+
+        // リンク切れのシンボリックリンクを経由して外に書き込むこともできない
+        std::os::unix::fs::symlink(
+            outside_dir.path().join("missing.txt"),
+            dir.path().join("dangling"),
+        )
+        .unwrap();
+        assert!(sandbox.resolve_for_write("dangling").is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::client::models::{ContentBlock, Conversation, Message, MessageContent};
+use crate::client::tools::ToolLoopEvent;
+use crate::conversation::ConversationStore;
+use crate::error::Result;
+use colored::*;
+
+/// エージェントに渡すシステムプロンプト
+pub const AGENT_SYSTEM_PROMPT: &str = "You are a coding agent working in a local workspace. \
+Use the tools to inspect files before changing them, keep changes minimal, and explain what you did when you are finished. \
+All paths are relative to the workspace root. \
+Writes and commands need the user's approval; if the user declines, do not retry the same action.";
+
+/// 再生時に表示するツール結果の最大行数
+const REPLAY_RESULT_LINES: usize = 20;
+
+/// ツールループの経過を会話として記録し、そのつど履歴に保存する
+///
+/// 途中でエラーになっても、それまでのツール呼び出しは履歴に残る。
+pub struct SessionLog {
+    conversation: Conversation,
+    pending_results: Vec<ContentBlock>,
+    store: Option<ConversationStore>,
+}
+
+impl SessionLog {
+    /// `store` が None の場合は記録だけを行い、保存はしない
+    pub fn new(conversation: Conversation, store: Option<ConversationStore>) -> Result<Self> {
+        let log = Self {
+            conversation,
+            pending_results: Vec::new(),
+            store,
+        };
+        log.save()?;
+        Ok(log)
+    }
+
+    pub fn conversation(&self) -> &Conversation {
+        &self.conversation
+    }
+
+    /// イベントを1つ記録する
+    pub fn record(&mut self, event: ToolLoopEvent<'_>) -> Result<()> {
+        match event {
+            ToolLoopEvent::Response(response) => {
+                self.flush_results();
+                self.conversation
+                    .add_message(Message::assistant(response.content.clone()));
+            }
+            ToolLoopEvent::ToolCall(call) => self.pending_results.push(call.result_block()),
+        }
+        self.save()
+    }
+
+    /// 返し終えていないツールの結果を記録して保存する
+    pub fn finish(&mut self) -> Result<()> {
+        self.flush_results();
+        self.save()
+    }
+
+    fn flush_results(&mut self) {
+        if !self.pending_results.is_empty() {
+            let results = std::mem::take(&mut self.pending_results);
+            self.conversation.add_message(Message::user(results));
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        match self.store {
+            Some(ref store) => store.save(&self.conversation),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 保存されたエージェントのセッションを、ツール呼び出しの順に表示する
+pub fn print_replay(conversation: &Conversation) {
+    println!(
+        "{} {}",
+        "🤖 Agent session:".cyan().bold(),
+        conversation.title.bold()
+    );
+    println!("{} {}", "ID:".cyan(), conversation.id);
+    println!(
+        "{} {}",
+        "Created:".cyan(),
+        conversation.created_at.format("%Y-%m-%d %H:%M:%S")
+    );
+
+    let mut step = 0;
+    for message in &conversation.messages {
+        let blocks = match message.content {
+            MessageContent::Text(ref text) => vec![ContentBlock::text(text.clone())],
+            MessageContent::Blocks(ref blocks) => blocks.clone(),
+        };
+        for block in blocks {
+            match block {
+                ContentBlock::Text { text } if !text.trim().is_empty() => {
+                    let label = if message.role == "user" {
+                        "You:".blue().bold()
+                    } else {
+                        "Claude:".green().bold()
+                    };
+                    println!("\n{}\n{}", label, text.trim_end());
+                }
+                ContentBlock::ToolUse { name, input, .. } => {
+                    step += 1;
+                    println!(
+                        "\n{} {} {}",
+                        format!("🔧 [{}]", step).yellow(),
+                        name.bold(),
+                        input
+                    );
+                }
+                ContentBlock::ToolResult {
+                    content, is_error, ..
+                } => {
+                    let text = content.to_string();
+                    let mut lines: Vec<&str> = text.lines().collect();
+                    let hidden = lines.len().saturating_sub(REPLAY_RESULT_LINES);
+                    lines.truncate(REPLAY_RESULT_LINES);
+                    let marker = if is_error { "✗".red() } else { "✓".green() };
+                    for line in lines {
+                        println!("  {} {}", marker, line.dimmed());
+                    }
+                    if hidden > 0 {
+                        println!("  {}", format!("… {} more lines", hidden).dimmed());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    println!("\n{} {}", "🔢 Tool calls:".cyan(), step);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::models::{ChatResponse, Usage};
+    use crate::client::tools::{ToolCall, ToolOutput};
+    use serde_json::json;
+
+    fn response(content: Vec<ContentBlock>, stop_reason: &str) -> ChatResponse {
+        ChatResponse {
+            id: "msg".to_string(),
+            r#type: "message".to_string(),
+            role: "assistant".to_string(),
+            content,
+            model: "claude-test".to_string(),
+            stop_reason: Some(stop_reason.to_string()),
+            stop_sequence: None,
+            usage: Usage::default(),
+        }
+    }
+
+    #[test]
+    fn test_session_log_records_tool_calls() {
+        let store = ConversationStore::open_in_memory().unwrap();
+        let mut conversation = Conversation::new("Agent".to_string());
+        conversation.add_message(Message::user("List the files"));
+        let id = conversation.id.clone();
+
+        let mut log = SessionLog::new(conversation, Some(store)).unwrap();
+        let tool_use = ContentBlock::ToolUse {
+            id: "toolu_1".to_string(),
+            name: "list_directory".to_string(),
+            input: json!({"path": "."}),
+        };
+        log.record(ToolLoopEvent::Response(&response(
+            vec![tool_use],
+            "tool_use",
+        )))
+        .unwrap();
+        let call = ToolCall {
+            id: "toolu_1".to_string(),
+            name: "list_directory".to_string(),
+            input: json!({"path": "."}),
+            output: ToolOutput::text("Cargo.toml\nsrc/"),
+        };
+        log.record(ToolLoopEvent::ToolCall(&call)).unwrap();
+
+        // 結果は次の応答の前にまとめて記録される
+        assert_eq!(log.conversation().messages.len(), 2);
+        log.record(ToolLoopEvent::Response(&response(
+            vec![ContentBlock::text("There are two entries.")],
+            "end_turn",
+        )))
+        .unwrap();
+        log.finish().unwrap();
+
+        let saved = log.store.as_ref().unwrap().load(&id).unwrap().unwrap();
+        assert_eq!(saved.messages.len(), 4);
+        assert_eq!(
+            saved.messages[2].content,
+            MessageContent::Blocks(vec![ContentBlock::tool_result(
+                "toolu_1",
+                "Cargo.toml\nsrc/",
+                false
+            )])
+        );
+        assert_eq!(saved.messages[3].text(), "There are two entries.");
+    }
+}
@@ -0,0 +1,741 @@
+use crate::agent::approval::Approver;
+use crate::agent::sandbox::Sandbox;
+use crate::client::tools::{Tool, ToolOutput, ToolSet};
+use crate::config::AgentConfig;
+use crate::error::{AskError, Result};
+use crate::output;
+use colored::*;
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// grep で返すマッチの最大件数
+const MAX_GREP_MATCHES: usize = 200;
+
+/// grep で読み飛ばすファイルの大きさ
+const MAX_GREP_FILE_BYTES: u64 = 1024 * 1024;
+
+/// サンドボックスの外を操作できるため、run_command では受け付けない引数
+const FORBIDDEN_ARGS: [&str; 9] = [
+    "-exec",
+    "-execdir",
+    "-ok",
+    "-okdir",
+    "-delete",
+    "-C",
+    "--git-dir",
+    "--work-tree",
+    "--exec-path",
+];
+
+/// シェルでは特別な意味を持つ記号（クォートの外では受け付けない）
+const SHELL_SYNTAX: [char; 9] = ['|', '&', ';', '<', '>', '`', '$', '(', ')'];
+
+/// ツールが共有する設定
+pub struct ToolContext {
+    pub sandbox: Sandbox,
+    pub config: AgentConfig,
+    /// ファイルの書き込みを許可するか決める
+    pub approver: Arc<dyn Approver>,
+    /// コマンドの実行を許可するか決める（`--yes` でも毎回確認する）
+    pub command_approver: Arc<dyn Approver>,
+}
+
+/// サンドボックス内で動くローカルツール一式を作成する
+pub fn local_tools(context: Arc<ToolContext>) -> ToolSet {
+    ToolSet::new()
+        .with(ReadFile(context.clone()))
+        .with(ListDirectory(context.clone()))
+        .with(Grep(context.clone()))
+        .with(WriteFile(context.clone()))
+        .with(RunCommand(context))
+}
+
+/// ファイルを読み込む
+pub struct ReadFile(pub Arc<ToolContext>);
+
+impl Tool for ReadFile {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "Read a UTF-8 text file in the workspace. Optionally read only `limit` lines starting at line `offset` (1-based)."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "Path relative to the workspace root"},
+                "offset": {"type": "integer", "minimum": 1},
+                "limit": {"type": "integer", "minimum": 1}
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn call(&self, input: Value) -> BoxFuture<'_, Result<ToolOutput>> {
+        Box::pin(async move {
+            let path = required_str(&input, "path")?;
+            let resolved = self.0.sandbox.resolve(path)?;
+            if resolved.is_dir() {
+                return Err(AskError::ToolError(format!(
+                    "{} is a directory; use list_directory",
+                    path
+                )));
+            }
+
+            let offset = input["offset"].as_u64().unwrap_or(1).max(1) as usize;
+            let limit = input["limit"].as_u64().map(|limit| limit as usize);
+            let content = read_lines(
+                &resolved,
+                path,
+                offset,
+                limit,
+                self.0.config.max_output_bytes,
+            )?;
+            Ok(ToolOutput::text(content))
+        })
+    }
+}
+
+/// ディレクトリの中身を一覧する
+pub struct ListDirectory(pub Arc<ToolContext>);
+
+impl Tool for ListDirectory {
+    fn name(&self) -> &str {
+        "list_directory"
+    }
+
+    fn description(&self) -> &str {
+        "List the entries of a directory in the workspace. Directories end with `/`."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "Directory relative to the workspace root (default: .)"}
+            }
+        })
+    }
+
+    fn call(&self, input: Value) -> BoxFuture<'_, Result<ToolOutput>> {
+        Box::pin(async move {
+            let path = input["path"].as_str().unwrap_or(".");
+            let resolved = self.0.sandbox.resolve(path)?;
+            if !resolved.is_dir() {
+                return Err(AskError::ToolError(format!("{} is not a directory", path)));
+            }
+
+            let mut entries = std::fs::read_dir(&resolved)?
+                .map(|entry| {
+                    let entry = entry?;
+                    let mut name = entry.file_name().to_string_lossy().into_owned();
+                    if entry.file_type()?.is_dir() {
+                        name.push('/');
+                    }
+                    Ok(name)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            entries.sort();
+
+            if entries.is_empty() {
+                return Ok(ToolOutput::text("(empty directory)"));
+            }
+            Ok(ToolOutput::text(truncate(
+                entries.join("\n"),
+                self.0.config.max_output_bytes,
+            )))
+        })
+    }
+}
+
+/// 正規表現でファイルの中身を検索する
+pub struct Grep(pub Arc<ToolContext>);
+
+impl Tool for Grep {
+    fn name(&self) -> &str {
+        "grep"
+    }
+
+    fn description(&self) -> &str {
+        "Search text files in the workspace for a regular expression. Returns `path:line: text` for each match. Hidden directories and `target` are skipped."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "pattern": {"type": "string", "description": "Regular expression (Rust regex syntax)"},
+                "path": {"type": "string", "description": "File or directory to search (default: .)"},
+                "case_insensitive": {"type": "boolean"}
+            },
+            "required": ["pattern"]
+        })
+    }
+
+    fn call(&self, input: Value) -> BoxFuture<'_, Result<ToolOutput>> {
+        Box::pin(async move {
+            let pattern = required_str(&input, "pattern")?;
+            let regex = regex::RegexBuilder::new(pattern)
+                .case_insensitive(input["case_insensitive"].as_bool().unwrap_or(false))
+                .build()
+                .map_err(|e| AskError::ToolError(format!("Invalid pattern: {}", e)))?;
+            let root = self
+                .0
+                .sandbox
+                .resolve(input["path"].as_str().unwrap_or("."))?;
+
+            let mut matches = Vec::new();
+            let walker = walkdir::WalkDir::new(&root)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_entry(|entry| entry.depth() == 0 || !is_skipped_dir(entry));
+            'files: for entry in walker.filter_map(|entry| entry.ok()) {
+                if !entry.file_type().is_file()
+                    || entry
+                        .metadata()
+                        .map_or(true, |m| m.len() > MAX_GREP_FILE_BYTES)
+                {
+                    continue;
+                }
+                let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                    continue;
+                };
+                let display = self.0.sandbox.display(entry.path());
+                for (number, line) in content.lines().enumerate() {
+                    if regex.is_match(line) {
+                        matches.push(format!("{}:{}: {}", display, number + 1, line.trim_end()));
+                        if matches.len() >= MAX_GREP_MATCHES {
+                            matches.push(format!("(stopped after {} matches)", MAX_GREP_MATCHES));
+                            break 'files;
+                        }
+                    }
+                }
+            }
+
+            if matches.is_empty() {
+                return Ok(ToolOutput::text("No matches"));
+            }
+            Ok(ToolOutput::text(truncate(
+                matches.join("\n"),
+                self.0.config.max_output_bytes,
+            )))
+        })
+    }
+}
+
+/// 差分を表示し、確認を取ってからファイルを書き込む
+pub struct WriteFile(pub Arc<ToolContext>);
+
+impl Tool for WriteFile {
+    fn name(&self) -> &str {
+        "write_file"
+    }
+
+    fn description(&self) -> &str {
+        "Create or overwrite a text file in the workspace with the given content. The user reviews a diff and must approve each write."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "Path relative to the workspace root"},
+                "content": {"type": "string", "description": "The complete new content of the file"}
+            },
+            "required": ["path", "content"]
+        })
+    }
+
+    fn call(&self, input: Value) -> BoxFuture<'_, Result<ToolOutput>> {
+        Box::pin(async move {
+            let path = required_str(&input, "path")?;
+            let content = required_str(&input, "content")?;
+            let resolved = self.0.sandbox.resolve_for_write(path)?;
+            let display = self.0.sandbox.display(&resolved);
+
+            let existing = resolved.exists();
+            let current = if existing {
+                read_text(&resolved, path)?
+            } else {
+                String::new()
+            };
+            if existing && current == content {
+                return Ok(ToolOutput::text(format!("{} is unchanged", display)));
+            }
+
+            let action = if existing {
+                format!("Write {}", display)
+            } else {
+                format!("Create {}", display)
+            };
+            if !self
+                .0
+                .approver
+                .approve(&action, &diff_preview(&display, &current, content))
+            {
+                return Ok(ToolOutput::error(format!(
+                    "The user declined to write {}",
+                    display
+                )));
+            }
+
+            if let Some(parent) = resolved.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&resolved, content)?;
+            Ok(ToolOutput::text(format!(
+                "Wrote {} bytes to {}",
+                content.len(),
+                display
+            )))
+        })
+    }
+}
+
+/// 許可されたコマンドを、確認を取ってから実行する
+pub struct RunCommand(pub Arc<ToolContext>);
+
+impl Tool for RunCommand {
+    fn name(&self) -> &str {
+        "run_command"
+    }
+
+    fn description(&self) -> &str {
+        "Run a command in the workspace root and return its exit code and output. The command is run without a shell (no pipes, redirects or variables), only allowlisted programs can be used, path arguments must stay inside the workspace, and the user must approve each command."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": {"type": "string", "description": "Program and arguments, e.g. `cargo test --lib`"}
+            },
+            "required": ["command"]
+        })
+    }
+
+    fn call(&self, input: Value) -> BoxFuture<'_, Result<ToolOutput>> {
+        Box::pin(async move {
+            let command = required_str(&input, "command")?;
+            let args = split_command(command)?;
+            let program = &args[0];
+            if !self
+                .0
+                .config
+                .allowed_commands
+                .iter()
+                .any(|allowed| allowed == program)
+            {
+                return Err(AskError::ToolError(format!(
+                    "`{}` is not in the allowed commands ({})",
+                    program,
+                    self.0.config.allowed_commands.join(", ")
+                )));
+            }
+
+            check_args(&self.0.sandbox, &args[1..])?;
+
+            let details = format!("  in {}", self.0.sandbox.root().display());
+            if !self
+                .0
+                .command_approver
+                .approve(&format!("Run `{}`", command), &details)
+            {
+                return Ok(ToolOutput::error(format!(
+                    "The user declined to run `{}`",
+                    command
+                )));
+            }
+
+            let mut child = tokio::process::Command::new(program)
+                .args(&args[1..])
+                .current_dir(self.0.sandbox.root())
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| AskError::ToolError(format!("Failed to run {}: {}", program, e)))?;
+            let max_bytes = self.0.config.max_output_bytes;
+            let stdout = child.stdout.take().expect("stdout is piped");
+            let stderr = child.stderr.take().expect("stderr is piped");
+            let run = async move {
+                let (stdout, stderr) = tokio::try_join!(
+                    read_capped(stdout, max_bytes),
+                    read_capped(stderr, max_bytes)
+                )?;
+                let status = child.wait().await?;
+                Ok::<_, std::io::Error>((status, stdout, stderr))
+            };
+            let timeout = Duration::from_secs(self.0.config.command_timeout);
+            let (status, stdout, stderr) =
+                tokio::time::timeout(timeout, run).await.map_err(|_| {
+                    AskError::ToolError(format!(
+                        "`{}` timed out after {} seconds",
+                        command, self.0.config.command_timeout
+                    ))
+                })??;
+
+            let code = status
+                .code()
+                .map_or_else(|| "killed by signal".to_string(), |code| code.to_string());
+            let text = format!(
+                "exit code: {}\n--- stdout ---\n{}\n--- stderr ---\n{}",
+                code, stdout, stderr
+            );
+            let text = truncate(text, max_bytes);
+            Ok(if status.success() {
+                ToolOutput::text(text)
+            } else {
+                ToolOutput::error(text)
+            })
+        })
+    }
+}
+
+/// 引数がサンドボックスの外を指していないか確認する
+///
+/// パスらしい引数（`/` を含むもの、`.` や `~` で始まるもの、ルートに同名のファイルがあるもの）は
+/// サンドボックスで解決し、`--opt=value` や `-Ivalue` の値も同じように確認する。
+pub fn check_args(sandbox: &Sandbox, args: &[String]) -> Result<()> {
+    for arg in args {
+        let name = arg.split_once('=').map_or(arg.as_str(), |(name, _)| name);
+        if FORBIDDEN_ARGS.contains(&name) {
+            return Err(AskError::ToolError(format!(
+                "`{}` is not allowed in run_command",
+                name
+            )));
+        }
+
+        let value = if let Some((_, value)) = arg.split_once('=').filter(|_| arg.starts_with('-')) {
+            value
+        } else if arg.starts_with("--") {
+            continue;
+        } else if let Some(flag) = arg.strip_prefix('-') {
+            // `-Ivalue` のように値が続く短いオプション
+            flag.get(1..).unwrap_or("")
+        } else {
+            arg.as_str()
+        };
+        if value.is_empty() {
+            continue;
+        }
+
+        let path_like = value.contains('/')
+            || value.starts_with('.')
+            || value.starts_with('~')
+            || sandbox.root().join(value).symlink_metadata().is_ok();
+        if !path_like {
+            continue;
+        }
+        if value.starts_with('~') {
+            return Err(AskError::ToolError(format!(
+                "{} is outside the sandbox",
+                value
+            )));
+        }
+        // 存在するパスはリンク先まで、存在しないパスは最も近い祖先まで確認する
+        if sandbox.resolve(value).is_err() {
+            sandbox.resolve_for_write(value)?;
+        }
+    }
+    Ok(())
+}
+
+/// コマンド文字列をプログラムと引数に分割する
+///
+/// シェルを経由しないため、パイプやリダイレクトなどの記号はクォートの外では受け付けない。
+pub fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(unbalanced(command)),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => current.push(c),
+                            None => return Err(unbalanced(command)),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(unbalanced(command)),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    // `\;` などでシェルの記号を引数に紛れ込ませない
+                    Some(c) if SHELL_SYNTAX.contains(&c) => return Err(shell_syntax(c)),
+                    Some(c) => current.push(c),
+                    None => {}
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c if SHELL_SYNTAX.contains(&c) => return Err(shell_syntax(c)),
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        args.push(current);
+    }
+
+    match args.first() {
+        None => Err(AskError::ToolError("Empty command".to_string())),
+        Some(program) if program.contains('/') => Err(AskError::ToolError(format!(
+            "`{}`: use the program name, not a path",
+            program
+        ))),
+        Some(_) => Ok(args),
+    }
+}
+
+/// 書き込み前に表示する差分
+///
+/// 確認と一緒に標準エラー出力に表示するため、色は標準エラー出力の設定で付ける。
+pub fn diff_preview(path: &str, old: &str, new: &str) -> String {
+    let diff = similar::TextDiff::from_lines(old, new);
+    let unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string();
+
+    output::on_stderr(|| color_diff(&unified))
+}
+
+fn color_diff(unified: &str) -> String {
+    unified
+        .lines()
+        .map(|line| {
+            if line.starts_with("+++") || line.starts_with("---") {
+                line.bold().to_string()
+            } else if line.starts_with('+') {
+                line.green().to_string()
+            } else if line.starts_with('-') {
+                line.red().to_string()
+            } else if line.starts_with("@@") {
+                line.cyan().to_string()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn required_str<'a>(input: &'a Value, key: &str) -> Result<&'a str> {
+    input[key]
+        .as_str()
+        .ok_or_else(|| AskError::ToolError(format!("Missing required string field `{}`", key)))
+}
+
+fn shell_syntax(c: char) -> AskError {
+    AskError::ToolError(format!(
+        "Shell syntax (`{}`) is not supported; run a single program without pipes or redirects",
+        c
+    ))
+}
+
+fn read_text(path: &Path, display: &str) -> Result<String> {
+    let bytes = std::fs::read(path)?;
+    String::from_utf8(bytes).map_err(|_| not_text(display))
+}
+
+/// `offset` 行目（1 から数える）から最大 `limit` 行を読む
+///
+/// `max_bytes` を超える分はファイルから読まずに切り詰める。
+fn read_lines(
+    path: &Path,
+    display: &str,
+    offset: usize,
+    limit: Option<usize>,
+    max_bytes: usize,
+) -> Result<String> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    for _ in 1..offset {
+        if !skip_line(&mut reader)? {
+            break;
+        }
+    }
+
+    let mut bytes = Vec::new();
+    let mut lines = 0;
+    let mut truncated = false;
+    while limit.map_or(true, |limit| lines < limit) {
+        let remaining = (max_bytes + 1 - bytes.len()) as u64;
+        if (&mut reader)
+            .take(remaining)
+            .read_until(b'\n', &mut bytes)?
+            == 0
+        {
+            break;
+        }
+        lines += 1;
+        if bytes.len() > max_bytes {
+            bytes.truncate(max_bytes);
+            truncated = true;
+            break;
+        }
+    }
+
+    let mut text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        // 切り詰めた位置で文字が途切れている場合は、その文字の手前まで
+        Err(e) if truncated && e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid);
+            String::from_utf8(bytes).map_err(|_| not_text(display))?
+        }
+        Err(_) => return Err(not_text(display)),
+    };
+    if truncated {
+        text.push_str(&format!("\n… (truncated after {} bytes)", text.len()));
+    } else if offset > 1 || limit.is_some() {
+        // 行を取り出した場合は最後の改行を含めない
+        if text.ends_with('\n') {
+            text.pop();
+        }
+    }
+    Ok(text)
+}
+
+/// 1行を読み飛ばす（行の中身は溜めない）。ファイルの終わりに達していれば false
+fn skip_line(reader: &mut impl BufRead) -> std::io::Result<bool> {
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(false);
+        }
+        match buffer.iter().position(|&byte| byte == b'\n') {
+            Some(end) => {
+                reader.consume(end + 1);
+                return Ok(true);
+            }
+            None => {
+                let len = buffer.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+/// 最大 `max_bytes` までを読み、残りは読み捨てる（子プロセスが書き込みで止まらないように）
+async fn read_capped(
+    mut reader: impl AsyncRead + Unpin,
+    max_bytes: usize,
+) -> std::io::Result<String> {
+    let mut bytes = Vec::new();
+    (&mut reader)
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut bytes)
+        .await?;
+    let truncated = bytes.len() > max_bytes;
+    if truncated {
+        bytes.truncate(max_bytes);
+        tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    }
+
+    let mut text = String::from_utf8_lossy(&bytes).trim_end().to_string();
+    if truncated {
+        text.push_str(&format!("\n… (truncated after {} bytes)", max_bytes));
+    }
+    Ok(text)
+}
+
+fn not_text(display: &str) -> AskError {
+    AskError::ToolError(format!("{} is not a UTF-8 text file", display))
+}
+
+fn is_skipped_dir(entry: &walkdir::DirEntry) -> bool {
+    entry.file_type().is_dir()
+        && entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.starts_with('.') || name == "target" || name == "node_modules")
+}
+
+/// 出力が大きすぎる場合は文字の境界で切り詰める
+fn truncate(mut text: String, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let total = text.len();
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str(&format!(
+        "\n… (truncated, {} of {} bytes shown)",
+        end, total
+    ));
+    text
+}
+
+fn unbalanced(command: &str) -> AskError {
+    AskError::ToolError(format!("Unbalanced quotes in `{}`", command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            split_command(r#"git log --format="%h %s" -n 3"#).unwrap(),
+            ["git", "log", "--format=%h %s", "-n", "3"]
+        );
+        assert_eq!(
+            split_command("grep 'a|b' src").unwrap(),
+            ["grep", "a|b", "src"]
+        );
+        assert!(split_command("ls | sh").is_err());
+        assert!(split_command("cat x > y").is_err());
+        assert!(split_command("echo $(id)").is_err());
+        assert!(split_command("/bin/rm -rf x").is_err());
+        assert!(split_command("echo 'oops").is_err());
+        assert!(split_command("   ").is_err());
+    }
+
+    #[test]
+    fn test_truncate_at_char_boundary() {
+        let text = truncate("あいう".to_string(), 4);
+        assert!(text.starts_with("あ\n"));
+        assert!(text.contains("3 of 9 bytes"));
+    }
+}
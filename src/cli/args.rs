@@ -54,10 +54,47 @@ pub struct Cli {
     #[arg(long)]
     pub temperature: Option<f32>,
 
-    /// Conversation ID to continue
+    /// Nucleus sampling threshold (0.0-1.0)
+    #[arg(long)]
+    pub top_p: Option<f32>,
+
+    /// Only sample from the top K tokens
+    #[arg(long)]
+    pub top_k: Option<u32>,
+
+    /// Stop generating when this text is produced (can be repeated)
+    #[arg(long = "stop", value_name = "TEXT")]
+    pub stop_sequences: Vec<String>,
+
+    /// Opaque user ID sent as metadata.user_id
+    #[arg(long)]
+    pub user_id: Option<String>,
+
+    /// Attach an image, PDF or text file (path or URL; can be repeated)
+    #[arg(long = "attach", value_name = "FILE", conflicts_with = "interactive")]
+    pub attachments: Vec<String>,
+
+    /// System prompt (overrides the configured default)
+    #[arg(long, value_name = "TEXT", conflicts_with = "system_file")]
+    pub system: Option<String>,
+
+    /// Read the system prompt from a file
+    #[arg(long, value_name = "FILE")]
+    pub system_file: Option<String>,
+
+    /// Conversation ID (or unique prefix) to continue
     #[arg(short = 'c', long)]
     pub conversation: Option<String>,
 
+    /// Print only the fenced code blocks from the reply (optionally only LANG, e.g. --extract-code rust).
+    /// Without LANG, put the message before the flag or after `--`
+    #[arg(long, value_name = "LANG", num_args = 0..=1, default_missing_value = "")]
+    pub extract_code: Option<String>,
+
+    /// Write each fenced code block from the reply to a file in DIR
+    #[arg(long, value_name = "DIR")]
+    pub extract_code_to: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -79,6 +116,27 @@ pub enum Commands {
         #[command(subcommand)]
         action: TemplateAction,
     },
+    /// Run an agent that can read, search, edit files and run commands in a directory
+    Agent {
+        /// Task for the agent
+        #[arg(required_unless_present = "replay")]
+        task: Option<String>,
+        /// Directory the agent's tools can access (default: current directory)
+        #[arg(long, value_name = "DIR")]
+        root: Option<String>,
+        /// Also allow this program in run_command (can be repeated)
+        #[arg(long = "allow", value_name = "PROGRAM")]
+        allow: Vec<String>,
+        /// Approve every file write without asking (commands are still confirmed one by one)
+        #[arg(short, long)]
+        yes: bool,
+        /// Maximum number of model calls
+        #[arg(long)]
+        max_iterations: Option<usize>,
+        /// Show the tool calls of a saved agent session instead of running one
+        #[arg(long, value_name = "ID", conflicts_with = "task")]
+        replay: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -105,6 +163,11 @@ pub enum ConfigAction {
         /// Temperature value (0.0-1.0)
         temp: f32,
     },
+    /// Set default system prompt (omit the prompt to clear it)
+    SetSystem {
+        /// System prompt text
+        prompt: Option<String>,
+    },
     /// Set output format
     SetFormat {
         /// Output format
@@ -167,8 +230,8 @@ pub enum TemplateAction {
     Create {
         /// Template name
         name: String,
-        /// Template content
-        content: String,
+        /// Template content (opens $EDITOR when omitted)
+        content: Option<String>,
     },
     /// Edit existing template
     Edit {
@@ -184,9 +247,31 @@ pub enum TemplateAction {
     Use {
         /// Template name
         name: String,
-        /// Variables in key=value format
+        /// Variables in key=value format (value may be @file, - for stdin, or $(command))
         #[arg(short, long)]
         var: Vec<String>,
+        /// Allow $(command) variable values to run shell commands
+        #[arg(long)]
+        allow_exec: bool,
+    },
+    /// Export templates to a shareable pack (.tar or .json)
+    Export {
+        /// Template names (all templates when omitted)
+        names: Vec<String>,
+        /// Output file path (.tar or .json)
+        #[arg(short, long)]
+        output: String,
+    },
+    /// Import templates from a pack (.tar or .json)
+    Import {
+        /// Pack file path
+        pack: String,
+        /// What to do when a template with the same name already exists
+        #[arg(long, value_enum, default_value = "skip")]
+        on_conflict: ConflictStrategy,
+        /// Import into this directory instead of the default template directory
+        #[arg(long)]
+        to: Option<String>,
     },
 }
 
@@ -210,6 +295,16 @@ pub enum ExportFormat {
     Text,
 }
 
+#[derive(ValueEnum, Clone, Debug)]
+pub enum ConflictStrategy {
+    /// Keep the existing template
+    Skip,
+    /// Replace the existing template
+    Overwrite,
+    /// Import under a new name (name-1, name-2, ...)
+    Rename,
+}
+
 impl std::fmt::Display for OutputFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,6 +1,15 @@
-use crate::cli::args::{ConfigAction, HistoryAction, OutputFormat, TemplateAction};
+use crate::cli::args::{
+    ConfigAction, ConflictStrategy, ExportFormat, HistoryAction, OutputFormat, TemplateAction,
+};
+use crate::cli::editor;
+use crate::cli::input::read_piped_stdin;
 use crate::config::{ConfigManager, Settings};
+use crate::conversation::{export_conversation, ConversationStore};
 use crate::error::{AskError, Result};
+use crate::output;
+use crate::template::{
+    self, ImportOutcome, SourceOptions, Template, TemplatePack, TemplateStore, VariableSource,
+};
 use colored::*;
 use std::io::{self, Write};
 
@@ -36,6 +45,27 @@ impl CommandHandler {
                 println!("  Model: {}", settings.api.model);
                 println!("  Max Tokens: {}", settings.api.max_tokens);
                 println!("  Temperature: {}", settings.api.temperature);
+                if let Some(top_p) = settings.api.top_p {
+                    println!("  Top P: {}", top_p);
+                }
+                if let Some(top_k) = settings.api.top_k {
+                    println!("  Top K: {}", top_k);
+                }
+                if !settings.api.stop_sequences.is_empty() {
+                    println!("  Stop Sequences: {:?}", settings.api.stop_sequences);
+                }
+                if let Some(ref user_id) = settings.api.user_id {
+                    println!("  User ID: {}", user_id);
+                }
+                println!(
+                    "  System: {}",
+                    settings
+                        .api
+                        .system
+                        .as_deref()
+                        .and_then(|system| system.lines().next())
+                        .unwrap_or("(none)")
+                );
                 println!(
                     "  Streaming: {}",
                     if settings.api.stream {
@@ -44,6 +74,12 @@ impl CommandHandler {
                         "disabled"
                     }
                 );
+                println!(
+                    "  Retries: {} attempts (base delay {}ms, jitter {}%)",
+                    settings.api.retry.max_attempts,
+                    settings.api.retry.base_delay_ms,
+                    (settings.api.retry.jitter * 100.0).round()
+                );
 
                 println!("\n{}:", "Output".yellow().bold());
                 println!("  Format: {}", settings.output.format);
@@ -63,6 +99,14 @@ impl CommandHandler {
                         "disabled"
                     }
                 );
+                println!(
+                    "  Pager: {}",
+                    if settings.output.pager {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    }
+                );
 
                 println!("\n{}:", "Conversation".yellow().bold());
                 println!(
@@ -85,6 +129,14 @@ impl CommandHandler {
                         "disabled"
                     }
                 );
+
+                println!("\n{}:", "Agent".yellow().bold());
+                println!(
+                    "  Allowed Commands: {}",
+                    settings.agent.allowed_commands.join(", ")
+                );
+                println!("  Max Iterations: {}", settings.agent.max_iterations);
+                println!("  Command Timeout: {}s", settings.agent.command_timeout);
             }
 
             ConfigAction::SetModel { model } => {
@@ -121,6 +173,18 @@ impl CommandHandler {
                 );
             }
 
+            ConfigAction::SetSystem { prompt } => {
+                let mut settings = self.config_manager.load_settings()?;
+                settings.api.system = prompt.filter(|prompt| !prompt.trim().is_empty());
+                self.config_manager.save_settings(&settings)?;
+                match settings.api.system {
+                    Some(ref system) => {
+                        println!("{} {}", "✅ System prompt set to:".green(), system.cyan())
+                    }
+                    None => println!("{}", "✅ System prompt cleared".green()),
+                }
+            }
+
             ConfigAction::SetFormat { format } => {
                 let mut settings = self.config_manager.load_settings()?;
                 settings.output.format = format.clone().into();
@@ -159,22 +223,74 @@ impl CommandHandler {
 
     pub async fn handle_history_command(&self, action: HistoryAction) -> Result<()> {
         match action {
-            HistoryAction::List { limit: _limit } => {
+            HistoryAction::List { limit } => {
+                let store = ConversationStore::open_default()?;
+                let conversations = store.list(limit)?;
+
                 println!("{}", "📚 Conversation History".cyan().bold());
-                println!("(History management will be implemented with database)");
-                // TODO: 実際のデータベース実装
+
+                if conversations.is_empty() {
+                    println!("{}", "No conversations saved yet.".yellow());
+                    return Ok(());
+                }
+
+                for summary in conversations {
+                    println!(
+                        "{}  {}  {} {}",
+                        summary.id[..8.min(summary.id.len())].yellow(),
+                        summary
+                            .updated_at
+                            .with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M"),
+                        summary.title.bold(),
+                        format!("({} messages)", summary.message_count).dimmed()
+                    );
+                }
             }
 
             HistoryAction::Show { id } => {
-                println!("{} {}", "📖 Showing conversation:".cyan(), id.yellow());
-                println!("(History viewing will be implemented with database)");
-                // TODO: 実際のデータベース実装
+                let store = ConversationStore::open_default()?;
+                let conversation = store.load(&id)?.ok_or_else(|| {
+                    AskError::InvalidInput(format!("Conversation not found: {}", id))
+                })?;
+
+                let settings = self.config_manager.load_settings()?;
+                let mut output = format!(
+                    "{} {}\n",
+                    "📖 Conversation:".cyan().bold(),
+                    conversation.title.bold()
+                );
+                output.push_str(&format!("  ID: {}\n", conversation.id.yellow()));
+                output.push_str(&format!(
+                    "  Created: {}\n\n",
+                    conversation
+                        .created_at
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                ));
+
+                for message in &conversation.messages {
+                    let label = match message.role.as_str() {
+                        "user" => "You:".green().bold(),
+                        "assistant" => "Claude:".blue().bold(),
+                        other => other.normal().bold(),
+                    };
+                    output.push_str(&format!("{}\n{}\n\n", label, message.content));
+                }
+
+                output::page(&output, settings.output.pager)?;
             }
 
             HistoryAction::Delete { id } => {
-                println!("{} {}", "🗑️  Deleting conversation:".yellow(), id.yellow());
-                println!("(History deletion will be implemented with database)");
-                // TODO: 実際のデータベース実装
+                let store = ConversationStore::open_default()?;
+                if store.delete(&id)? {
+                    println!("{} {}", "🗑️  Deleted conversation:".yellow(), id.yellow());
+                } else {
+                    return Err(AskError::InvalidInput(format!(
+                        "Conversation not found: {}",
+                        id
+                    )));
+                }
             }
 
             HistoryAction::Clear { yes } => {
@@ -191,23 +307,34 @@ impl CommandHandler {
                     }
                 }
 
-                println!("{}", "🗑️ Clearing all conversation history...".yellow());
-                println!("(History clearing will be implemented with database)");
-                // TODO: 実際のデータベース実装
+                let store = ConversationStore::open_default()?;
+                let deleted = store.clear()?;
+                println!(
+                    "{} {}",
+                    "✅ Cleared conversations:".green(),
+                    deleted.to_string().cyan()
+                );
             }
 
             HistoryAction::Export { id, output, format } => {
-                let output_file =
-                    output.unwrap_or_else(|| format!("conversation_{}.{}", id, format));
+                let store = ConversationStore::open_default()?;
+                let conversation = store.load(&id)?.ok_or_else(|| {
+                    AskError::InvalidInput(format!("Conversation not found: {}", id))
+                })?;
+
+                let format: crate::conversation::ExportFormat = format.into();
+                let output_file = output.unwrap_or_else(|| {
+                    format!("conversation_{}.{}", conversation.id, format.extension())
+                });
+                std::fs::write(&output_file, export_conversation(&conversation, format)?)?;
+
                 println!(
                     "{} {} {} {}",
-                    "📤 Exporting conversation".cyan(),
-                    id.yellow(),
+                    "📤 Exported conversation".cyan(),
+                    conversation.id.yellow(),
                     "to".cyan(),
                     output_file.green()
                 );
-                println!("(History export will be implemented with database)");
-                // TODO: 実際のデータベース実装
             }
         }
 
@@ -215,50 +342,346 @@ impl CommandHandler {
     }
 
     pub async fn handle_template_command(&self, action: TemplateAction) -> Result<()> {
+        let settings = self.config_manager.load_settings()?;
+        let store = TemplateStore::from_config(&settings.template);
+
         match action {
             TemplateAction::List => {
                 println!("{}", "📝 Available Templates".cyan().bold());
-                println!("(Template management will be implemented)");
-                // TODO: テンプレート実装
+
+                let entries = store.list_entries()?;
+                if entries.is_empty() {
+                    println!("{}", "No templates found in:".yellow());
+                    for dir in store.search_paths() {
+                        println!("  {}", dir.display());
+                    }
+                    return Ok(());
+                }
+
+                for entry in entries {
+                    // 壊れたテンプレートがあっても一覧は最後まで表示する
+                    let summary = match store.load(&entry.name) {
+                        Ok(template) => template.summary().dimmed(),
+                        Err(e) => format!("❌ {}", e).red(),
+                    };
+                    print!("  {}  {}", entry.name.yellow(), summary);
+                    // デフォルト以外のディレクトリにあるテンプレートは場所も表示する
+                    match entry.path.parent() {
+                        Some(dir) if dir != store.dir() => {
+                            println!("  {}", format!("({})", dir.display()).dimmed());
+                        }
+                        _ => println!(),
+                    }
+                }
             }
 
             TemplateAction::Show { name } => {
-                println!("{} {}", "📋 Template:".cyan(), name.yellow());
-                println!("(Template viewing will be implemented)");
-                // TODO: テンプレート実装
+                let template = store.load(&name)?;
+                print_template_schema(&template)?;
+                println!();
+                println!("{}", template.content);
             }
 
             TemplateAction::Create { name, content } => {
-                println!("{} {}", "✨ Creating template:".green(), name.yellow());
-                println!("Content: {}", content);
-                println!("(Template creation will be implemented)");
-                // TODO: テンプレート実装
+                if store.exists(&name)? {
+                    return Err(AskError::TemplateError(format!(
+                        "Template already exists: {} (use 'ask template edit')",
+                        name
+                    )));
+                }
+
+                let content = match content {
+                    // 保存前にフロントマターと構文を確認する
+                    Some(content) => {
+                        template::validate(&name, &content)?;
+                        content
+                    }
+                    None => match self.edit_template_in_editor(&name, NEW_TEMPLATE_SKELETON)? {
+                        Some(content) => content,
+                        None => {
+                            println!("{}", "❌ Template creation cancelled".yellow());
+                            return Ok(());
+                        }
+                    },
+                };
+
+                let path = store.save(&name, &content)?;
+                println!(
+                    "{} {} ({})",
+                    "✨ Created template:".green(),
+                    name.yellow(),
+                    path.display()
+                );
             }
 
             TemplateAction::Edit { name } => {
-                println!("{} {}", "✏️  Editing template:".yellow(), name.yellow());
-                println!("(Template editing will be implemented)");
-                // TODO: テンプレート実装
+                // 壊れたテンプレートも直せるよう、解析せずにそのまま開く
+                let path = store.locate(&name)?.ok_or_else(|| {
+                    AskError::TemplateError(format!("Template not found: {}", name))
+                })?;
+                let current = std::fs::read_to_string(path)?;
+
+                match self.edit_template_in_editor(&name, &current)? {
+                    Some(content) if content == current => {
+                        println!("{}", "No changes made".yellow());
+                    }
+                    Some(content) => {
+                        store.save(&name, &content)?;
+                        println!("{} {}", "✅ Updated template:".green(), name.yellow());
+                    }
+                    None => {
+                        println!(
+                            "{}",
+                            "❌ Edit cancelled, the stored template was not changed".yellow()
+                        );
+                    }
+                }
             }
 
             TemplateAction::Delete { name } => {
-                println!("{} {}", "🗑️  Deleting template:".red(), name.yellow());
-                println!("(Template deletion will be implemented)");
-                // TODO: テンプレート実装
+                store.delete(&name)?;
+                println!("{} {}", "🗑️  Deleted template:".red(), name.yellow());
             }
 
-            TemplateAction::Use { name, var } => {
-                println!("{} {}", "🚀 Using template:".green(), name.yellow());
-                for v in var {
-                    println!("  Variable: {}", v.cyan());
+            // 展開したテンプレートの送信は run_app が行い、ここには来ない
+            TemplateAction::Use { name, .. } => {
+                return Err(AskError::TemplateError(format!(
+                    "'template use {}' sends a message and is handled by run_app",
+                    name
+                )));
+            }
+
+            TemplateAction::Export { names, output } => {
+                let pack = TemplatePack::from_store(&store, &names)?;
+                if pack.templates.is_empty() {
+                    return Err(AskError::TemplateError(
+                        "No templates to export".to_string(),
+                    ));
+                }
+
+                pack.write_to(std::path::Path::new(&output))?;
+                println!(
+                    "{} {} {} {}",
+                    "📤 Exported".green(),
+                    pack.templates.len().to_string().yellow(),
+                    "template(s) to".green(),
+                    output.cyan()
+                );
+            }
+
+            TemplateAction::Import {
+                pack,
+                on_conflict,
+                to,
+            } => {
+                // パック全体を検証してから書き込むため、不正な場合は何も取り込まれない
+                let templates = TemplatePack::read_from(std::path::Path::new(&pack))?;
+                let target = match to {
+                    Some(dir) => TemplateStore::new(dir),
+                    None => store,
+                };
+
+                let outcomes = template::import_pack(&templates, &target, on_conflict.into())?;
+                for outcome in &outcomes {
+                    match outcome {
+                        ImportOutcome::Created { name, .. } => {
+                            println!("  {} {}", "✨ Imported".green(), name.yellow());
+                        }
+                        ImportOutcome::Overwritten { name, .. } => {
+                            println!("  {} {}", "♻️  Overwrote".cyan(), name.yellow());
+                        }
+                        ImportOutcome::Renamed { from, to, .. } => {
+                            println!(
+                                "  {} {} {} {}",
+                                "✨ Imported".green(),
+                                from.yellow(),
+                                "as".dimmed(),
+                                to.yellow()
+                            );
+                        }
+                        ImportOutcome::Skipped { name } => {
+                            println!(
+                                "  {} {} {}",
+                                "⏭️  Skipped".yellow(),
+                                name.yellow(),
+                                "(already exists)".dimmed()
+                            );
+                        }
+                    }
                 }
-                println!("(Template usage will be implemented)");
-                // TODO: テンプレート実装
+
+                let skipped = outcomes
+                    .iter()
+                    .filter(|outcome| matches!(outcome, ImportOutcome::Skipped { .. }))
+                    .count();
+                println!(
+                    "{} {} {}",
+                    "📥 Imported".green(),
+                    (outcomes.len() - skipped).to_string().yellow(),
+                    format!("template(s) into {}", target.dir().display()).green()
+                );
             }
         }
 
         Ok(())
     }
+
+    /// テンプレートをエディタで編集し、検証に通った内容を返す
+    ///
+    /// 内容が壊れている場合はエディタを開き直すか確認し、やめた場合や
+    /// 本文が空の場合は None を返す。
+    fn edit_template_in_editor(&self, name: &str, initial: &str) -> Result<Option<String>> {
+        let path = std::env::temp_dir().join(format!(
+            "ask-template-{}-{}.{}",
+            name,
+            uuid::Uuid::new_v4(),
+            template::TEMPLATE_EXTENSION
+        ));
+        std::fs::write(&path, initial)?;
+
+        let result = loop {
+            if let Err(e) = editor::open_in_editor(&path) {
+                break Err(e);
+            }
+
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) => break Err(e.into()),
+            };
+            match template::validate(name, &content) {
+                Ok(parsed) if parsed.body.trim().is_empty() => break Ok(None),
+                Ok(_) => break Ok(Some(content)),
+                Err(e) => {
+                    output::on_stderr(|| eprintln!("{} {}", "❌".red(), e));
+                    match confirm("❓ Reopen the editor to fix it? [Y/n]: ", true) {
+                        Ok(true) => {}
+                        Ok(false) => break Ok(None),
+                        Err(e) => break Err(e),
+                    }
+                }
+            }
+        };
+
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    /// テンプレートを読み込み、`key=value` 形式の変数で展開する
+    ///
+    /// 変数の値は `@file`、`-`（標準入力）、`$(command)`（`allow_exec` が必要）でも指定できる。
+    pub fn render_template(
+        &self,
+        name: &str,
+        vars: &[String],
+        allow_exec: bool,
+    ) -> Result<(Template, String)> {
+        let settings = self.config_manager.load_settings()?;
+        let store = TemplateStore::from_config(&settings.template);
+        let template = store.load(name)?;
+
+        let variables = template::parse_variables(vars)?;
+        let reads_stdin = variables
+            .values()
+            .any(|value| VariableSource::parse(value) == VariableSource::Stdin);
+        let options = SourceOptions {
+            allow_exec,
+            stdin: if reads_stdin {
+                read_piped_stdin()?
+            } else {
+                None
+            },
+        };
+
+        let variables = template::resolve_sources(variables, &options)?;
+        let prompt = template.render(&variables, &store)?;
+        Ok((template, prompt))
+    }
+}
+
+/// 新しいテンプレートをエディタで作成するときの雛形
+const NEW_TEMPLATE_SKELETON: &str = "+++
+# description = \"What this template does\"
+# model = \"claude-3-5-sonnet-20241022\"
+# temperature = 0.7
+# system = \"You are a helpful assistant.\"
+#
+# [variables.input]
+# required = true
+# description = \"The text to work on\"
++++
+
+";
+
+/// y/n で確認する（空の入力は `default` として扱う）
+fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        // 入力が閉じている場合は続行しない
+        return Ok(false);
+    }
+
+    Ok(match input.trim().to_lowercase().as_str() {
+        "" => default,
+        answer => answer == "y" || answer == "yes",
+    })
+}
+
+/// テンプレートの説明・パラメータ・変数の定義を表示する
+fn print_template_schema(template: &Template) -> Result<()> {
+    let metadata = &template.metadata;
+
+    println!("{} {}", "📋 Template:".cyan(), template.name.yellow());
+    if let Some(ref description) = metadata.description {
+        println!("  {}", description);
+    }
+
+    if let Some(ref model) = metadata.model {
+        println!("  Model: {}", model.cyan());
+    }
+    if let Some(max_tokens) = metadata.max_tokens {
+        println!("  Max Tokens: {}", max_tokens);
+    }
+    if let Some(temperature) = metadata.temperature {
+        println!("  Temperature: {}", temperature);
+    }
+    if let Some(ref system) = metadata.system {
+        println!("  System: {}", system.lines().next().unwrap_or("").dimmed());
+    }
+
+    // 本文で使われているが定義の無い変数も必須の文字列として表示する
+    let undeclared: Vec<String> = template::variables(&template.body)?
+        .into_iter()
+        .filter(|name| !metadata.variables.contains_key(name))
+        .collect();
+
+    if metadata.variables.is_empty() && undeclared.is_empty() {
+        return Ok(());
+    }
+
+    println!("\n{}:", "Variables".yellow().bold());
+    for (name, spec) in &metadata.variables {
+        let requirement = if spec.required {
+            "required".red()
+        } else {
+            "optional".green()
+        };
+        let mut line = format!("  {} ({}, {})", name.cyan(), spec.kind, requirement);
+        if let Some(ref default) = spec.default {
+            line.push_str(&format!(" [default: {}]", default));
+        }
+        if let Some(ref description) = spec.description {
+            line.push_str(&format!(" - {}", description));
+        }
+        println!("{}", line);
+    }
+    for name in undeclared {
+        println!("  {} (string, {})", name.cyan(), "required".red());
+    }
+
+    Ok(())
 }
 
 impl Default for CommandHandler {
@@ -267,6 +690,28 @@ impl Default for CommandHandler {
     }
 }
 
+// ExportFormat の変換実装
+impl From<ExportFormat> for crate::conversation::ExportFormat {
+    fn from(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Json => crate::conversation::ExportFormat::Json,
+            ExportFormat::Markdown => crate::conversation::ExportFormat::Markdown,
+            ExportFormat::Text => crate::conversation::ExportFormat::Text,
+        }
+    }
+}
+
+// ConflictStrategy の変換実装
+impl From<ConflictStrategy> for crate::template::ConflictStrategy {
+    fn from(strategy: ConflictStrategy) -> Self {
+        match strategy {
+            ConflictStrategy::Skip => crate::template::ConflictStrategy::Skip,
+            ConflictStrategy::Overwrite => crate::template::ConflictStrategy::Overwrite,
+            ConflictStrategy::Rename => crate::template::ConflictStrategy::Rename,
+        }
+    }
+}
+
 // OutputFormat の変換実装
 impl From<OutputFormat> for crate::config::OutputFormat {
     fn from(format: OutputFormat) -> Self {
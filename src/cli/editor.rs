@@ -0,0 +1,42 @@
+use crate::error::{AskError, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// 使用するエディタのコマンド（$VISUAL > $EDITOR > vi）
+pub fn editor_command() -> String {
+    std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// ファイルをエディタで開き、エディタが終了するまで待つ
+pub fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = editor_command();
+
+    // "code --wait" のような引数付きの指定にも対応する
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+
+    let status = Command::new(program).args(parts).arg(path).status()?;
+    if !status.success() {
+        return Err(AskError::InvalidInput(format!(
+            "Editor '{}' exited with {}",
+            editor, status
+        )));
+    }
+
+    Ok(())
+}
+
+/// 一時ファイルにテキストを書き出してエディタで編集し、編集後の内容を返す
+pub fn edit_text(initial: &str, extension: &str) -> Result<String> {
+    let path = std::env::temp_dir().join(format!("ask-{}.{}", uuid::Uuid::new_v4(), extension));
+    std::fs::write(&path, initial)?;
+
+    let result = open_in_editor(&path).and_then(|_| Ok(std::fs::read_to_string(&path)?));
+    let _ = std::fs::remove_file(&path);
+
+    result
+}
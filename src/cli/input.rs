@@ -0,0 +1,13 @@
+use crate::error::Result;
+use std::io::{self, Read};
+
+/// 標準入力がパイプされている場合はその内容を読み込む
+pub fn read_piped_stdin() -> Result<Option<String>> {
+    if atty::is(atty::Stream::Stdin) {
+        return Ok(None);
+    }
+
+    let mut buffer = String::new();
+    io::stdin().read_to_string(&mut buffer)?;
+    Ok(Some(buffer))
+}
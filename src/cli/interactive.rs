@@ -0,0 +1,538 @@
+use crate::cli::editor;
+use crate::cli::slash_commands::{estimate_tokens, SlashCommand, SystemAction, SLASH_COMMAND_HELP};
+use crate::client::{ClaudeClient, Conversation, Message, StreamEvent};
+use crate::config::{ApiConfig, Settings};
+use crate::conversation::{self, export_conversation, ConversationStore, ExportFormat};
+use crate::error::{AskError, Result};
+use crate::output::{self, MarkdownStream};
+use colored::*;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use tokio_stream::StreamExt;
+
+/// 複数行入力ブロックの区切り
+const MULTILINE_DELIMITER: &str = "\"\"\"";
+
+/// 対話モードのセッション
+pub struct InteractiveSession {
+    client: ClaudeClient,
+    settings: Settings,
+    /// セッション中だけ有効なAPI設定（保存済みの設定は変更しない）
+    api: ApiConfig,
+    conversation: Conversation,
+    verbose: bool,
+}
+
+/// 1行の入力を解釈した結果
+enum Input {
+    Message(String),
+    Command(Result<SlashCommand>),
+    Help,
+    Exit,
+    Empty,
+}
+
+impl InteractiveSession {
+    pub fn new(
+        client: ClaudeClient,
+        settings: Settings,
+        api: ApiConfig,
+        conversation: Conversation,
+        verbose: bool,
+    ) -> Self {
+        Self {
+            client,
+            settings,
+            api,
+            conversation,
+            verbose,
+        }
+    }
+
+    /// 現在の会話
+    pub fn conversation(&self) -> &Conversation {
+        &self.conversation
+    }
+
+    /// REPLを実行する（終了時に会話を履歴へ保存する）
+    pub async fn run(&mut self, initial_message: Option<String>) -> Result<()> {
+        let mut editor = DefaultEditor::new()?;
+        let history_path = input_history_path();
+        if let Some(ref path) = history_path {
+            // 初回起動時は履歴ファイルが無いので失敗は無視する
+            let _ = editor.load_history(path);
+        }
+
+        self.print_banner();
+
+        if let Some(message) = initial_message.filter(|m| !m.trim().is_empty()) {
+            self.send(&message).await;
+        }
+
+        loop {
+            let line = match editor.readline(&"> ".green().bold().to_string()) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(e.into()),
+            };
+
+            let line = match read_continuation(&mut editor, line)? {
+                Some(line) => line,
+                None => continue,
+            };
+
+            editor.add_history_entry(line.as_str())?;
+
+            match parse_input(&line) {
+                Input::Exit => break,
+                Input::Help => print_help(),
+                Input::Empty => {}
+                Input::Message(message) => self.send(&message).await,
+                Input::Command(Ok(command)) => {
+                    if let Err(e) = self.execute(command).await {
+                        output::on_stderr(|| eprintln!("{} {}", "❌ Error:".red().bold(), e));
+                    }
+                }
+                Input::Command(Err(e)) => output::on_stderr(|| eprintln!("{} {}", "❌".red(), e)),
+            }
+        }
+
+        if let Some(ref path) = history_path {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            editor.save_history(path)?;
+        }
+
+        self.save()?;
+        println!("{}", "👋 Goodbye!".green());
+        Ok(())
+    }
+
+    fn print_banner(&self) {
+        println!("{}", "🎯 Interactive mode starting...".green().bold());
+        println!(
+            "{}",
+            "Type 'exit' or 'quit' to end the conversation.".yellow()
+        );
+        println!("{}", "Type 'help' for available commands.".yellow());
+        if !self.conversation.messages.is_empty() {
+            println!(
+                "{} {} ({} messages)",
+                "📖 Continuing conversation:".cyan(),
+                self.conversation.title.bold(),
+                self.conversation.messages.len()
+            );
+        }
+        println!();
+    }
+
+    /// メッセージを送信して応答を表示する
+    ///
+    /// エラーが発生した場合はユーザーの発言を取り消し、セッションは継続する。
+    async fn send(&mut self, message: &str) {
+        if self.conversation.messages.is_empty() && self.settings.conversation.auto_title {
+            self.conversation.title = conversation::generate_title(message);
+        }
+        self.conversation
+            .add_message(Message::user(message.to_string()));
+
+        match self.request_reply().await {
+            Ok(Some(reply)) => {
+                self.conversation.add_message(Message::assistant(reply));
+            }
+            Ok(None) => {
+                self.conversation.messages.pop();
+            }
+            Err(e) => {
+                self.conversation.messages.pop();
+                output::on_stderr(|| eprintln!("{} {}", "❌ Error:".red().bold(), e));
+            }
+        }
+        println!();
+    }
+
+    /// 会話全体を送信し、応答テキストを返す（中断された場合は None）
+    async fn request_reply(&self) -> Result<Option<String>> {
+        let request = self
+            .client
+            .messages()
+            .config(&self.api)
+            .messages(self.conversation.messages.clone())
+            .system_opt(self.conversation.system.as_deref());
+
+        if !self.api.stream {
+            if self.verbose {
+                println!("{}", "🤔 Processing request...".yellow());
+            }
+            let response = request.send().await?;
+            let text = response.text();
+            println!("{}", output::render_markdown(&text, self.markdown()));
+            warn_if_truncated(response.stop_reason.as_deref());
+            return Ok(Some(text));
+        }
+
+        let stream = request.stream().await?;
+        let mut pinned_stream = Box::pin(stream);
+        let mut full_text = String::new();
+        let mut markdown = MarkdownStream::new(self.markdown());
+        let mut stop_reason = None;
+
+        loop {
+            tokio::select! {
+                event = pinned_stream.next() => match event {
+                    Some(Ok(event)) => {
+                        if let Some(e) = event.to_error() {
+                            println!("{}", markdown.finish());
+                            return Err(e);
+                        }
+                        if let StreamEvent::MessageDelta { stop_reason: Some(ref reason), .. } = event {
+                            stop_reason = Some(reason.clone());
+                        }
+                        if let Some(text) = event.text_delta() {
+                            print!("{}", markdown.push(text));
+                            io::stdout().flush()?;
+                            full_text.push_str(text);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        println!("{}", markdown.finish());
+                        return Err(e);
+                    }
+                    None => break,
+                },
+                _ = tokio::signal::ctrl_c() => {
+                    println!("{}\n{}", markdown.finish(), "⏹  Response cancelled".yellow());
+                    return Ok(None);
+                }
+            }
+        }
+        println!("{}", markdown.finish());
+        warn_if_truncated(stop_reason.as_deref());
+
+        Ok(Some(full_text))
+    }
+
+    /// 応答を Markdown として整形して表示するか
+    fn markdown(&self) -> bool {
+        output::use_terminal_markdown()
+    }
+
+    /// スラッシュコマンドを実行する
+    async fn execute(&mut self, command: SlashCommand) -> Result<()> {
+        match command {
+            SlashCommand::Model(None) => print_setting("Model", &self.api.model),
+            SlashCommand::Model(Some(model)) => {
+                self.api.model = model;
+                print_updated("Model", &self.api.model);
+            }
+
+            SlashCommand::Temperature(None) => {
+                print_setting("Temperature", &self.api.temperature.to_string())
+            }
+            SlashCommand::Temperature(Some(temperature)) => {
+                self.api.temperature = temperature;
+                print_updated("Temperature", &temperature.to_string());
+            }
+
+            SlashCommand::MaxTokens(None) => {
+                print_setting("Max tokens", &self.api.max_tokens.to_string())
+            }
+            SlashCommand::MaxTokens(Some(max_tokens)) => {
+                self.api.max_tokens = max_tokens;
+                print_updated("Max tokens", &max_tokens.to_string());
+            }
+
+            SlashCommand::System(SystemAction::Show) => match self.conversation.system {
+                Some(ref system) => print_setting("System prompt", system),
+                None => println!("{}", "No system prompt set".yellow()),
+            },
+            SlashCommand::System(SystemAction::Set(system)) => {
+                self.conversation.system = Some(system);
+                println!("{}", "✅ System prompt updated".green());
+            }
+            SlashCommand::System(SystemAction::Clear) => {
+                self.conversation.system = None;
+                println!("{}", "✅ System prompt cleared".green());
+            }
+
+            SlashCommand::Save => {
+                if self.conversation.messages.is_empty() {
+                    println!("{}", "Nothing to save yet".yellow());
+                } else {
+                    conversation::save_to_history(&self.conversation, &self.settings.conversation)?;
+                    println!(
+                        "{} {}",
+                        "💾 Saved as conversation:".cyan(),
+                        self.conversation.id.yellow()
+                    );
+                }
+            }
+
+            SlashCommand::Load(id) => {
+                let loaded = ConversationStore::open_default()?
+                    .load(&id)?
+                    .ok_or_else(|| {
+                        AskError::InvalidInput(format!("Conversation not found: {}", id))
+                    })?;
+                self.save()?;
+                self.conversation = loaded;
+                println!(
+                    "{} {} ({} messages)",
+                    "📖 Loaded conversation:".cyan(),
+                    self.conversation.title.bold(),
+                    self.conversation.messages.len()
+                );
+            }
+
+            SlashCommand::Clear => {
+                self.save()?;
+                let mut fresh = Conversation::new("Interactive session".to_string());
+                fresh.system = self.conversation.system.take();
+                self.conversation = fresh;
+                println!("{}", "🧹 Started a new conversation".green());
+            }
+
+            SlashCommand::Retry => {
+                let prompt = self.pop_last_exchange().ok_or_else(|| {
+                    AskError::InvalidInput("There is no response to retry".to_string())
+                })?;
+                self.send(&prompt).await;
+            }
+
+            SlashCommand::Undo => {
+                if self.pop_last_exchange().is_some() {
+                    println!("{}", "↩️  Removed the last exchange".green());
+                } else {
+                    println!("{}", "Nothing to undo".yellow());
+                }
+            }
+
+            SlashCommand::Edit => {
+                let previous = self.last_prompt().unwrap_or_default();
+                let edited = editor::edit_text(&previous, "md")?;
+                let edited = edited.trim_end();
+
+                if edited.trim().is_empty() {
+                    println!("{}", "Edit cancelled (empty prompt)".yellow());
+                } else {
+                    if !previous.is_empty() {
+                        self.pop_last_exchange();
+                    }
+                    println!("{}", edited.dimmed());
+                    self.send(edited).await;
+                }
+            }
+
+            SlashCommand::Tokens => {
+                let system_tokens = self
+                    .conversation
+                    .system
+                    .as_deref()
+                    .map(estimate_tokens)
+                    .unwrap_or(0);
+                let message_tokens: usize = self
+                    .conversation
+                    .messages
+                    .iter()
+                    .map(|message| estimate_tokens(&message.text()))
+                    .sum();
+
+                println!("{}", "🔢 Conversation size (estimated)".cyan().bold());
+                println!("  Messages: {}", self.conversation.messages.len());
+                println!("  System prompt: ~{} tokens", system_tokens);
+                println!("  Messages: ~{} tokens", message_tokens);
+                println!("  Total input: ~{} tokens", system_tokens + message_tokens);
+                println!("  Max response: {} tokens", self.api.max_tokens);
+            }
+
+            SlashCommand::Export(path) => {
+                let path =
+                    path.unwrap_or_else(|| format!("conversation_{}.md", self.conversation.id));
+                let format = std::path::Path::new(&path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .and_then(ExportFormat::from_extension)
+                    .ok_or_else(|| {
+                        AskError::InvalidInput(
+                            "Export file must end in .md, .json or .txt".to_string(),
+                        )
+                    })?;
+
+                std::fs::write(&path, export_conversation(&self.conversation, format)?)?;
+                println!("{} {}", "📤 Exported conversation to".cyan(), path.green());
+            }
+
+            SlashCommand::Help => print_help(),
+        }
+
+        Ok(())
+    }
+
+    /// 最後のプロンプト（ユーザーの発言）
+    fn last_prompt(&self) -> Option<String> {
+        self.conversation
+            .messages
+            .iter()
+            .rev()
+            .find(|message| message.role == "user")
+            .map(|message| message.text())
+    }
+
+    /// 最後のやり取り（ユーザーの発言とその応答）を取り除き、そのプロンプトを返す
+    fn pop_last_exchange(&mut self) -> Option<String> {
+        let messages = &mut self.conversation.messages;
+        if messages.last().map(|m| m.role.as_str()) == Some("assistant") {
+            messages.pop();
+        }
+        if messages.last().map(|m| m.role.as_str()) == Some("user") {
+            messages.pop().map(|message| message.text())
+        } else {
+            None
+        }
+    }
+
+    /// 会話を履歴に保存する
+    fn save(&self) -> Result<()> {
+        if !self.settings.conversation.save_history || self.conversation.messages.is_empty() {
+            return Ok(());
+        }
+
+        conversation::save_to_history(&self.conversation, &self.settings.conversation)?;
+        println!(
+            "{} {}",
+            "💾 Saved as conversation:".cyan(),
+            self.conversation.id.yellow()
+        );
+        Ok(())
+    }
+}
+
+/// 最大トークン数に達して応答が途中で切れた場合に知らせる
+fn warn_if_truncated(stop_reason: Option<&str>) {
+    if stop_reason == Some("max_tokens") {
+        println!(
+            "{}",
+            "⚠️  The reply was cut off at the max token limit (raise it with /max-tokens)".yellow()
+        );
+    }
+}
+
+/// 行末の `\` や `"""` ブロックによる複数行入力を読み取る
+///
+/// 入力途中で Ctrl-C が押された場合は None を返す。
+fn read_continuation(editor: &mut DefaultEditor, first_line: String) -> Result<Option<String>> {
+    let continuation_prompt = "… ".dimmed().to_string();
+
+    if first_line.trim() == MULTILINE_DELIMITER {
+        let mut lines = Vec::new();
+        loop {
+            match editor.readline(&continuation_prompt) {
+                Ok(line) if line.trim() == MULTILINE_DELIMITER => break,
+                Ok(line) => lines.push(line),
+                Err(ReadlineError::Interrupted) => return Ok(None),
+                Err(ReadlineError::Eof) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        return Ok(Some(lines.join("\n")));
+    }
+
+    let mut buffer = first_line;
+    while let Some(stripped) = buffer.strip_suffix('\\') {
+        buffer = stripped.to_string();
+        buffer.push('\n');
+        match editor.readline(&continuation_prompt) {
+            Ok(line) => buffer.push_str(&line),
+            Err(ReadlineError::Interrupted) => return Ok(None),
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(Some(buffer))
+}
+
+fn parse_input(line: &str) -> Input {
+    match line.trim() {
+        "" => Input::Empty,
+        command if command.starts_with('/') => Input::Command(SlashCommand::parse(command)),
+        "exit" | "quit" => Input::Exit,
+        "help" => Input::Help,
+        _ => Input::Message(line.to_string()),
+    }
+}
+
+fn print_help() {
+    println!("{}", "Available commands:".cyan().bold());
+    println!("  {}      Show this help", "help".yellow());
+    println!(
+        "  {} End the session and save it to history",
+        "exit, quit".yellow()
+    );
+    for (usage, description) in SLASH_COMMAND_HELP {
+        println!("  {:<22} {}", usage.yellow(), description);
+    }
+    println!();
+    println!("{}", "Input:".cyan().bold());
+    println!(
+        "  End a line with {} to continue on the next line",
+        "\\".yellow()
+    );
+    println!(
+        "  Type {} on its own line to start and end a multi-line block",
+        MULTILINE_DELIMITER.yellow()
+    );
+    println!(
+        "  Press {} to cancel a response, {} to exit",
+        "Ctrl-C".yellow(),
+        "Ctrl-D".yellow()
+    );
+}
+
+fn print_setting(name: &str, value: &str) {
+    println!("{}: {}", name.cyan(), value);
+}
+
+fn print_updated(name: &str, value: &str) {
+    println!(
+        "{} {}",
+        format!("✅ {} set to:", name).green(),
+        value.cyan()
+    );
+}
+
+/// 入力履歴ファイルのパス
+fn input_history_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("ltd", "engineers-hub", "ask")
+        .map(|dirs| dirs.data_dir().join("interactive_history.txt"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_input_commands() {
+        assert!(matches!(parse_input("  "), Input::Empty));
+        assert!(matches!(parse_input("exit"), Input::Exit));
+        assert!(matches!(parse_input(" quit "), Input::Exit));
+        assert!(matches!(parse_input("help"), Input::Help));
+        assert!(matches!(
+            parse_input("/model claude-3-5-haiku-20241022"),
+            Input::Command(Ok(SlashCommand::Model(Some(_))))
+        ));
+        assert!(matches!(parse_input("/nope"), Input::Command(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_input_message_keeps_text() {
+        match parse_input("  What is Rust?") {
+            Input::Message(text) => assert_eq!(text, "  What is Rust?"),
+            _ => panic!("expected a message"),
+        }
+    }
+}
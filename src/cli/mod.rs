@@ -1,5 +1,12 @@
 pub mod args;
 pub mod commands;
+pub mod editor;
+pub mod input;
+pub mod interactive;
+pub mod slash_commands;
 
 pub use args::*;
 pub use commands::*;
+pub use input::*;
+pub use interactive::*;
+pub use slash_commands::*;
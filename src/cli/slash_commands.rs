@@ -0,0 +1,214 @@
+use crate::error::{AskError, Result};
+
+/// 対話モードで使えるスラッシュコマンド
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlashCommand {
+    /// モデルを表示・変更する
+    Model(Option<String>),
+    /// temperature を表示・変更する
+    Temperature(Option<f32>),
+    /// 最大トークン数を表示・変更する
+    MaxTokens(Option<u32>),
+    /// システムプロンプトを表示・変更する
+    System(SystemAction),
+    /// 会話を履歴に保存する
+    Save,
+    /// 履歴から会話を読み込む
+    Load(String),
+    /// 会話をリセットする
+    Clear,
+    /// 最後の応答を再生成する
+    Retry,
+    /// 最後のやり取りを取り消す
+    Undo,
+    /// 最後のプロンプトをエディタで編集して再送信する
+    Edit,
+    /// 会話のトークン数の目安を表示する
+    Tokens,
+    /// 会話をファイルにエクスポートする
+    Export(Option<String>),
+    /// コマンドの一覧を表示する
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SystemAction {
+    Show,
+    Set(String),
+    Clear,
+}
+
+impl SlashCommand {
+    /// `/` で始まる入力をコマンドとして解釈する
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let body = input
+            .strip_prefix('/')
+            .ok_or_else(|| AskError::InvalidInput(format!("Not a command: {}", input)))?;
+
+        let (name, argument) = match body.split_once(char::is_whitespace) {
+            Some((name, rest)) => (name, Some(rest.trim()).filter(|rest| !rest.is_empty())),
+            None => (body, None),
+        };
+
+        match name {
+            "model" => Ok(SlashCommand::Model(argument.map(str::to_string))),
+            "temperature" | "temp" => argument
+                .map(parse_temperature)
+                .transpose()
+                .map(SlashCommand::Temperature),
+            "max-tokens" => argument
+                .map(|value| {
+                    value.parse::<u32>().ok().filter(|&n| n > 0).ok_or_else(|| {
+                        AskError::InvalidInput(format!(
+                            "Max tokens must be a positive integer: {}",
+                            value
+                        ))
+                    })
+                })
+                .transpose()
+                .map(SlashCommand::MaxTokens),
+            "system" => Ok(SlashCommand::System(match argument {
+                None => SystemAction::Show,
+                Some("clear") => SystemAction::Clear,
+                Some(text) => SystemAction::Set(text.to_string()),
+            })),
+            "save" => Ok(SlashCommand::Save),
+            "load" => argument
+                .map(|id| SlashCommand::Load(id.to_string()))
+                .ok_or_else(|| {
+                    AskError::InvalidInput("Usage: /load <conversation-id>".to_string())
+                }),
+            "clear" => Ok(SlashCommand::Clear),
+            "retry" => Ok(SlashCommand::Retry),
+            "undo" => Ok(SlashCommand::Undo),
+            "edit" => Ok(SlashCommand::Edit),
+            "tokens" => Ok(SlashCommand::Tokens),
+            "export" => Ok(SlashCommand::Export(argument.map(str::to_string))),
+            "help" => Ok(SlashCommand::Help),
+            _ => Err(AskError::InvalidInput(format!(
+                "Unknown command: /{} (type /help for a list of commands)",
+                name
+            ))),
+        }
+    }
+}
+
+fn parse_temperature(value: &str) -> Result<f32> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|temp| (0.0..=1.0).contains(temp))
+        .ok_or_else(|| {
+            AskError::InvalidInput("Temperature must be between 0.0 and 1.0".to_string())
+        })
+}
+
+/// コマンドの一覧（ヘルプ表示用）
+pub const SLASH_COMMAND_HELP: &[(&str, &str)] = &[
+    ("/model [name]", "Show or change the model for this session"),
+    ("/temperature [value]", "Show or change the temperature"),
+    (
+        "/max-tokens [n]",
+        "Show or change the maximum response tokens",
+    ),
+    (
+        "/system [text|clear]",
+        "Show, set or clear the system prompt",
+    ),
+    ("/save", "Save the conversation to history"),
+    ("/load <id>", "Load a conversation from history"),
+    ("/clear", "Start a new conversation"),
+    ("/retry", "Regenerate the last response"),
+    ("/undo", "Remove the last exchange"),
+    ("/edit", "Edit the last prompt in $EDITOR and resend it"),
+    ("/tokens", "Show an estimate of the conversation size"),
+    (
+        "/export [file]",
+        "Export the conversation (.md, .json or .txt)",
+    ),
+    ("/help", "Show this help"),
+];
+
+/// トークン数の簡易的な見積もり
+///
+/// ASCII は約4文字で1トークン、それ以外（日本語など）は1文字1トークンとして数える。
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), c| {
+        if c.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    (ascii + 3) / 4 + other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands_with_arguments() {
+        assert_eq!(
+            SlashCommand::parse("/model claude-3-5-haiku-20241022").unwrap(),
+            SlashCommand::Model(Some("claude-3-5-haiku-20241022".to_string()))
+        );
+        assert_eq!(
+            SlashCommand::parse("/temperature 0.2").unwrap(),
+            SlashCommand::Temperature(Some(0.2))
+        );
+        assert_eq!(
+            SlashCommand::parse("/max-tokens 512").unwrap(),
+            SlashCommand::MaxTokens(Some(512))
+        );
+        assert_eq!(
+            SlashCommand::parse("/system You are terse.").unwrap(),
+            SlashCommand::System(SystemAction::Set("You are terse.".to_string()))
+        );
+        assert_eq!(
+            SlashCommand::parse("/load abc123").unwrap(),
+            SlashCommand::Load("abc123".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_commands_without_arguments() {
+        assert_eq!(
+            SlashCommand::parse("/model").unwrap(),
+            SlashCommand::Model(None)
+        );
+        assert_eq!(
+            SlashCommand::parse("/system").unwrap(),
+            SlashCommand::System(SystemAction::Show)
+        );
+        assert_eq!(
+            SlashCommand::parse("/system clear").unwrap(),
+            SlashCommand::System(SystemAction::Clear)
+        );
+        assert_eq!(
+            SlashCommand::parse(" /retry ").unwrap(),
+            SlashCommand::Retry
+        );
+        assert_eq!(
+            SlashCommand::parse("/export").unwrap(),
+            SlashCommand::Export(None)
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_commands() {
+        assert!(SlashCommand::parse("/unknown").is_err());
+        assert!(SlashCommand::parse("/load").is_err());
+        assert!(SlashCommand::parse("/temperature 1.5").is_err());
+        assert!(SlashCommand::parse("/max-tokens zero").is_err());
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("こんにちは"), 5);
+    }
+}
@@ -1,13 +1,27 @@
-use crate::client::models::{ChatRequest, ChatResponse, ErrorResponse, Message};
+use crate::client::models::{
+    ChatRequest, ChatResponse, ContentBlock, ErrorDetails, ErrorResponse, Message, StreamEvent,
+    Usage,
+};
+use crate::client::request::MessageRequest;
+use crate::client::retry::{
+    is_retryable_error, is_retryable_error_type, is_retryable_status, retry_after, RetryNotice,
+    RetryPolicy,
+};
+use crate::client::streaming::create_event_stream;
+use crate::client::tools::{ToolLoopEvent, ToolRun, ToolSet};
 use crate::error::{AskError, Result};
-use reqwest::{Client, Response};
+use reqwest::{Client, Response, StatusCode};
 use std::time::Duration;
-use tokio_stream::Stream;
+use tokio_stream::{Stream, StreamExt};
+
+type RetryCallback = Box<dyn Fn(&RetryNotice<'_>) + Send + Sync>;
 
 pub struct ClaudeClient {
     client: Client,
     api_key: String,
     base_url: String,
+    retry_policy: RetryPolicy,
+    on_retry: Option<RetryCallback>,
 }
 
 impl ClaudeClient {
@@ -18,6 +32,8 @@ impl ClaudeClient {
             client,
             api_key,
             base_url: "https://api.anthropic.com/v1".to_string(),
+            retry_policy: RetryPolicy::default(),
+            on_retry: None,
         })
     }
 
@@ -30,9 +46,38 @@ impl ClaudeClient {
             client,
             api_key,
             base_url: "https://api.anthropic.com/v1".to_string(),
+            retry_policy: RetryPolicy::default(),
+            on_retry: None,
         })
     }
 
+    /// API のベース URL を変更する（プロキシやテスト用のサーバーを使う場合）
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// 失敗したリクエストを再試行する方針を変更する
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// 再試行を待つ間、1秒ごとに呼ばれる関数を設定する（残り時間の表示用）
+    pub fn on_retry(mut self, callback: impl Fn(&RetryNotice<'_>) + Send + Sync + 'static) -> Self {
+        self.on_retry = Some(Box::new(callback));
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// リクエストを組み立てるビルダーを作成する
+    pub fn messages(&self) -> MessageRequest<'_> {
+        MessageRequest::new(self)
+    }
+
     /// 単一メッセージを送信する
     pub async fn send_message(
         &self,
@@ -41,13 +86,17 @@ impl ClaudeClient {
         max_tokens: u32,
         temperature: Option<f32>,
     ) -> Result<String> {
-        let messages = vec![Message::user(message.to_string())];
         let response = self
-            .send_messages(messages, model, max_tokens, temperature, false)
+            .messages()
+            .model(model)
+            .max_tokens(max_tokens)
+            .temperature(temperature)
+            .user(message)
+            .send()
             .await?;
 
-        if let Some(content_block) = response.content.first() {
-            Ok(content_block.text.clone())
+        if let Some(text) = response.content.first().and_then(ContentBlock::as_text) {
+            Ok(text.to_string())
         } else {
             Err(AskError::InvalidInput(
                 "Empty response from API".to_string(),
@@ -56,35 +105,108 @@ impl ClaudeClient {
     }
 
     /// 複数メッセージ（会話履歴）を送信する
+    ///
+    /// `stream` は互換性のために残しているだけで、無視する（`true` を渡しても応答は
+    /// まとめて受け取る）。ストリーミングには `messages().stream()` か `stream_messages` を使う。
+    /// システムプロンプトを指定する場合は [`ClaudeClient::messages`] のビルダーを使う。
     pub async fn send_messages(
         &self,
         messages: Vec<Message>,
         model: &str,
         max_tokens: u32,
         temperature: Option<f32>,
-        stream: bool,
+        _stream: bool,
     ) -> Result<ChatResponse> {
+        self.messages()
+            .messages(messages)
+            .model(model)
+            .max_tokens(max_tokens)
+            .temperature(temperature)
+            .send()
+            .await
+    }
+
+    /// リクエストを送信する（応答は常にまとめて受け取る）
+    pub async fn send_request(&self, request: &ChatRequest) -> Result<ChatResponse> {
         let request = ChatRequest {
-            model: model.to_string(),
-            max_tokens,
-            messages,
-            temperature,
-            stream: if stream { Some(true) } else { None },
+            stream: None,
+            ..request.clone()
         };
 
-        let response = self
-            .client
-            .post(format!("{}/messages", self.base_url))
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
-
+        let response = self.post(&request, &mut 0).await?;
         self.handle_response(response).await
     }
 
+    /// ツールを使いながら会話を進める
+    ///
+    /// モデルを呼び出し、`tool_use` があれば登録されたツールで実行して
+    /// `tool_result` を返す。`end_turn` などツール以外の理由で止まるか、
+    /// 呼び出し回数が `max_iterations` に達するまで繰り返す。
+    pub async fn run_tools(
+        &self,
+        request: &ChatRequest,
+        tools: &ToolSet,
+        max_iterations: usize,
+        on_event: &mut dyn FnMut(ToolLoopEvent<'_>),
+    ) -> Result<ToolRun> {
+        if max_iterations == 0 {
+            return Err(AskError::InvalidInput(
+                "max_iterations must be at least 1".to_string(),
+            ));
+        }
+
+        let mut request = request.clone();
+        if request.tools.is_none() && !tools.is_empty() {
+            request.tools = Some(tools.definitions());
+        }
+        let start = request.messages.len();
+        let mut calls = Vec::new();
+        let mut usage = Usage::default();
+
+        for iteration in 1..=max_iterations {
+            let response = self.send_request(&request).await?;
+            usage.input_tokens += response.usage.input_tokens;
+            usage.output_tokens += response.usage.output_tokens;
+            on_event(ToolLoopEvent::Response(&response));
+            request
+                .messages
+                .push(Message::assistant(response.content.clone()));
+
+            if !response.wants_tools() {
+                return Ok(ToolRun {
+                    messages: request.messages.split_off(start),
+                    response,
+                    calls,
+                    iterations: iteration,
+                    usage,
+                    limit_reached: false,
+                });
+            }
+
+            let mut results = Vec::new();
+            for (id, name, input) in response.tool_uses() {
+                let call = tools.dispatch(id, name, input).await;
+                on_event(ToolLoopEvent::ToolCall(&call));
+                results.push(call.result_block());
+                calls.push(call);
+            }
+            request.messages.push(Message::user(results));
+
+            if iteration == max_iterations {
+                return Ok(ToolRun {
+                    messages: request.messages.split_off(start),
+                    response,
+                    calls,
+                    iterations: iteration,
+                    usage,
+                    limit_reached: true,
+                });
+            }
+        }
+
+        unreachable!("the loop returns on its last iteration")
+    }
+
     /// ストリーミング形式でメッセージを送信する
     pub async fn stream_message(
         &self,
@@ -93,12 +215,18 @@ impl ClaudeClient {
         max_tokens: u32,
         temperature: Option<f32>,
     ) -> Result<impl Stream<Item = Result<String>>> {
-        let messages = vec![Message::user(message.to_string())];
-        self.stream_messages(messages, model, max_tokens, temperature)
+        self.messages()
+            .model(model)
+            .max_tokens(max_tokens)
+            .temperature(temperature)
+            .user(message)
+            .stream_text()
             .await
     }
 
     /// ストリーミング形式で複数メッセージを送信する
+    ///
+    /// システムプロンプトを指定する場合は [`ClaudeClient::messages`] のビルダーを使う。
     pub async fn stream_messages(
         &self,
         messages: Vec<Message>,
@@ -106,29 +234,114 @@ impl ClaudeClient {
         max_tokens: u32,
         temperature: Option<f32>,
     ) -> Result<impl Stream<Item = Result<String>>> {
+        self.messages()
+            .messages(messages)
+            .model(model)
+            .max_tokens(max_tokens)
+            .temperature(temperature)
+            .stream_text()
+            .await
+    }
+
+    /// ストリーミング形式で複数メッセージを送信し、SSE のイベントをそのまま返す
+    pub async fn stream_message_events(
+        &self,
+        messages: Vec<Message>,
+        model: &str,
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> Result<impl Stream<Item = Result<StreamEvent>>> {
+        self.messages()
+            .messages(messages)
+            .model(model)
+            .max_tokens(max_tokens)
+            .temperature(temperature)
+            .stream()
+            .await
+    }
+
+    /// リクエストをストリーミング形式で送信し、SSE のイベントを返す
+    ///
+    /// 内容が届く前にストリームが失敗した場合は、リクエストごとやり直す。
+    pub async fn stream_request(
+        &self,
+        request: &ChatRequest,
+    ) -> Result<impl Stream<Item = Result<StreamEvent>>> {
         let request = ChatRequest {
-            model: model.to_string(),
-            max_tokens,
-            messages,
-            temperature,
             stream: Some(true),
+            ..request.clone()
         };
 
-        let response = self
-            .client
-            .post(format!("{}/messages", self.base_url))
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+        let mut attempt = 0;
+        loop {
+            let response = self.post(&request, &mut attempt).await?;
+            if !response.status().is_success() {
+                return Err(self.handle_error_response(response).await);
+            }
 
-        if !response.status().is_success() {
-            return Err(self.handle_error_response(response).await);
+            let mut events = Box::pin(create_event_stream(response));
+            let (received, failure) = read_until_content(&mut events).await;
+            match failure {
+                Some(ref reason) if self.retry_policy.should_retry(attempt) => {
+                    self.wait_before_retry(attempt, reason, None).await;
+                }
+                _ => return Ok(tokio_stream::iter(received).chain(events)),
+            }
         }
+    }
+
+    /// リクエストを送信する（失敗した場合は方針に従って再試行する）
+    ///
+    /// `attempt` はこれまでの試行回数で、送信するたびに増える。
+    /// 再試行しきれなかったエラーのレスポンスはそのまま返す。
+    async fn post(&self, request: &ChatRequest, attempt: &mut u32) -> Result<Response> {
+        loop {
+            *attempt += 1;
+            let result = self
+                .client
+                .post(format!("{}/messages", self.base_url))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .header("Content-Type", "application/json")
+                .json(request)
+                .send()
+                .await;
 
-        Ok(crate::client::streaming::create_stream(response))
+            let retry = self.retry_policy.should_retry(*attempt);
+            let (reason, delay) = match result {
+                Ok(response) if retry && is_retryable_status(response.status()) => (
+                    format!("HTTP {}", response.status()),
+                    retry_after(response.headers()),
+                ),
+                Ok(response) => return Ok(response),
+                Err(e) if retry && is_retryable_error(&e) => {
+                    ("Connection failed".to_string(), None)
+                }
+                Err(e) => return Err(e.into()),
+            };
+            self.wait_before_retry(*attempt, &reason, delay).await;
+        }
+    }
+
+    /// 再試行の前に待つ（待っている間は1秒ごとに `on_retry` を呼ぶ）
+    async fn wait_before_retry(&self, attempt: u32, reason: &str, retry_after: Option<Duration>) {
+        let mut remaining = self.retry_policy.delay(attempt, retry_after);
+        loop {
+            if let Some(ref on_retry) = self.on_retry {
+                on_retry(&RetryNotice {
+                    attempt: attempt + 1,
+                    max_attempts: self.retry_policy.max_attempts,
+                    reason,
+                    remaining,
+                });
+            }
+            if remaining.is_zero() {
+                break;
+            }
+            let step = remaining.min(Duration::from_secs(1));
+            tokio::time::sleep(step).await;
+            remaining -= step;
+        }
     }
 
     async fn handle_response(&self, response: Response) -> Result<ChatResponse> {
@@ -142,22 +355,89 @@ impl ClaudeClient {
 
     async fn handle_error_response(&self, response: Response) -> AskError {
         let status = response.status();
+        let request_id = response
+            .headers()
+            .get("request-id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let details = response
+            .json::<ErrorResponse>()
+            .await
+            .ok()
+            .map(|error_response| error_response.error);
 
-        match status.as_u16() {
-            401 => AskError::AuthenticationFailed,
-            429 => AskError::RateLimitExceeded,
-            _ => {
-                if let Ok(error_response) = response.json::<ErrorResponse>().await {
-                    AskError::InvalidInput(format!(
-                        "API Error ({}): {}",
-                        status, error_response.error.message
-                    ))
-                } else {
-                    AskError::InvalidInput(format!("HTTP Error: {}", status))
-                }
-            }
+        api_error(status, details, request_id)
+    }
+}
+
+/// エラーのレスポンスを `AskError` に変換する
+///
+/// エラーの種類（`error.type`）で振り分け、本文が読めなかった場合はステータスコードで判断する。
+fn api_error(
+    status: StatusCode,
+    details: Option<ErrorDetails>,
+    request_id: Option<String>,
+) -> AskError {
+    match status.as_u16() {
+        401 => return AskError::AuthenticationFailed,
+        429 => return AskError::RateLimitExceeded,
+        _ => {}
+    }
+
+    match details {
+        Some(details) => details.to_error(request_id).unwrap_or_else(|| {
+            AskError::InvalidInput(format!("API Error ({}): {}", status, details.message))
+        }),
+        None => ErrorDetails {
+            r#type: status_error_type(status).to_string(),
+            message: status.to_string(),
+        }
+        .to_error(request_id)
+        .unwrap_or_else(|| AskError::InvalidInput(format!("HTTP Error: {}", status))),
+    }
+}
+
+/// 本文が読めなかったエラーのステータスコードに対応するエラーの種類
+fn status_error_type(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        400 => "invalid_request_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        529 => "overloaded_error",
+        500..=599 => "api_error",
+        _ => "",
+    }
+}
+
+/// 最初の内容（テキストやツール入力の差分）が届くまでイベントを読み進める
+///
+/// それまでに再試行できる失敗があった場合は、その理由も返す。
+async fn read_until_content<S>(events: &mut S) -> (Vec<Result<StreamEvent>>, Option<String>)
+where
+    S: Stream<Item = Result<StreamEvent>> + Unpin,
+{
+    let mut received = Vec::new();
+    while let Some(item) = events.next().await {
+        let failure = match item {
+            Ok(StreamEvent::Error {
+                ref r#type,
+                ref message,
+            }) if is_retryable_error_type(r#type) => Some(format!("{}: {}", r#type, message)),
+            Err(AskError::NetworkError(_)) => Some("Stream interrupted".to_string()),
+            _ => None,
+        };
+        let done = failure.is_some()
+            || matches!(
+                item,
+                Ok(StreamEvent::TextDelta { .. } | StreamEvent::InputJsonDelta { .. }) | Err(_)
+            );
+        received.push(item);
+        if done {
+            return (received, failure);
         }
     }
+    (received, None)
 }
 
 #[cfg(test)]
@@ -170,6 +450,85 @@ mod tests {
         assert!(client.is_ok());
     }
 
+    fn details(error_type: &str, message: &str) -> Option<ErrorDetails> {
+        Some(ErrorDetails {
+            r#type: error_type.to_string(),
+            message: message.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_api_error_from_error_type() {
+        let request_id = Some("req_123".to_string());
+        let error = api_error(
+            StatusCode::BAD_REQUEST,
+            details("invalid_request_error", "prompt is too long"),
+            request_id.clone(),
+        );
+        assert!(
+            matches!(error, AskError::InvalidRequest { ref message, .. } if message == "prompt is too long")
+        );
+        assert_eq!(error.request_id(), Some("req_123"));
+
+        let cases = [
+            (403, "permission_error"),
+            (404, "not_found_error"),
+            (413, "request_too_large"),
+            (529, "overloaded_error"),
+            (500, "api_error"),
+        ];
+        for (status, error_type) in cases {
+            let error = api_error(
+                StatusCode::from_u16(status).unwrap(),
+                details(error_type, "message"),
+                request_id.clone(),
+            );
+            let matched = match error_type {
+                "permission_error" => matches!(error, AskError::PermissionDenied { .. }),
+                "not_found_error" => matches!(error, AskError::NotFound { .. }),
+                "request_too_large" => matches!(error, AskError::RequestTooLarge { .. }),
+                "overloaded_error" => matches!(error, AskError::Overloaded { .. }),
+                _ => matches!(error, AskError::ApiServerError { .. }),
+            };
+            assert!(matched, "{} mapped to {:?}", error_type, error);
+            assert_eq!(error.request_id(), Some("req_123"));
+        }
+
+        assert!(matches!(
+            api_error(
+                StatusCode::UNAUTHORIZED,
+                details("authentication_error", "bad key"),
+                None
+            ),
+            AskError::AuthenticationFailed
+        ));
+        assert!(matches!(
+            api_error(StatusCode::TOO_MANY_REQUESTS, None, None),
+            AskError::RateLimitExceeded
+        ));
+    }
+
+    #[test]
+    fn test_api_error_without_body() {
+        let error = api_error(StatusCode::BAD_GATEWAY, None, None);
+        assert!(matches!(error, AskError::ApiServerError { .. }));
+        assert_eq!(error.to_string(), "API server error: 502 Bad Gateway");
+
+        let error = api_error(StatusCode::IM_A_TEAPOT, None, None);
+        assert!(matches!(error, AskError::InvalidInput(_)));
+
+        // 未知の種類はこれまでどおりステータスとメッセージを伝える
+        let error = api_error(
+            StatusCode::PAYMENT_REQUIRED,
+            details("billing_error", "no credit"),
+            None,
+        );
+        assert_eq!(
+            error.to_string(),
+            "Invalid input: API Error (402 Payment Required): no credit"
+        );
+    }
+
     #[test]
     fn test_client_with_timeout() {
         let client = ClaudeClient::with_timeout("test-key".to_string(), 60);
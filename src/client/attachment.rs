@@ -0,0 +1,262 @@
+use crate::client::models::{ContentBlock, MediaSource, Message, MessageContent};
+use crate::error::{AskError, Result};
+use base64::Engine;
+use std::path::Path;
+
+/// 画像1枚あたりの上限（エンコード前）
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// 文書1つあたりの上限（エンコード前）
+pub const MAX_DOCUMENT_BYTES: usize = 32 * 1024 * 1024;
+
+/// 1リクエストに含められる添付ファイルの合計（エンコード後）
+pub const MAX_REQUEST_BYTES: usize = 32 * 1024 * 1024;
+
+/// API が受け付ける画像のメディアタイプ
+pub const IMAGE_MEDIA_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// ファイルの内容（先頭のマジックナンバー）と拡張子からメディアタイプを判定する
+pub fn detect_media_type(path: &Path, bytes: &[u8]) -> Option<&'static str> {
+    let by_content = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else {
+        None
+    };
+
+    by_content.or_else(|| media_type_from_extension(path))
+}
+
+/// 拡張子からメディアタイプを推測する
+pub fn media_type_from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "pdf" => Some("application/pdf"),
+        "txt" | "md" | "markdown" | "csv" | "log" => Some("text/plain"),
+        _ => None,
+    }
+}
+
+/// ファイルまたは URL を読み込み、コンテンツブロックに変換する
+///
+/// `http://` と `https://` で始まる場合は URL のまま参照させる。
+/// ファイルはサイズを確認してから読み込むため、大きすぎるファイルは読み込まない。
+pub fn load_attachment(source: &str) -> Result<ContentBlock> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        return url_attachment(source);
+    }
+
+    let path = Path::new(source);
+    let metadata = std::fs::metadata(path)
+        .map_err(|e| AskError::AttachmentError(format!("{}: {}", source, e)))?;
+    if !metadata.is_file() {
+        return Err(AskError::AttachmentError(format!(
+            "{} is not a file",
+            source
+        )));
+    }
+    let size = metadata.len() as usize;
+    if size > MAX_DOCUMENT_BYTES {
+        return Err(too_large(source, size, MAX_DOCUMENT_BYTES));
+    }
+
+    let bytes = std::fs::read(path)?;
+    let media_type = detect_media_type(path, &bytes).ok_or_else(|| {
+        AskError::AttachmentError(format!(
+            "Unsupported file type: {} (supported: PNG, JPEG, GIF, WebP, PDF, plain text)",
+            source
+        ))
+    })?;
+    let title = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+
+    match media_type {
+        "text/plain" => {
+            let data = String::from_utf8(bytes).map_err(|_| {
+                AskError::AttachmentError(format!("{} is not valid UTF-8 text", source))
+            })?;
+            Ok(ContentBlock::Document {
+                source: MediaSource::Text {
+                    media_type: media_type.to_string(),
+                    data,
+                },
+                title,
+            })
+        }
+        "application/pdf" => Ok(ContentBlock::Document {
+            source: MediaSource::Base64 {
+                media_type: media_type.to_string(),
+                data: base64::engine::general_purpose::STANDARD.encode(&bytes),
+            },
+            title,
+        }),
+        _ => {
+            if size > MAX_IMAGE_BYTES {
+                return Err(too_large(source, size, MAX_IMAGE_BYTES));
+            }
+            Ok(ContentBlock::image_base64(
+                media_type,
+                base64::engine::general_purpose::STANDARD.encode(&bytes),
+            ))
+        }
+    }
+}
+
+/// 添付ファイルとテキストから1つのユーザーメッセージを作成する
+///
+/// 添付ファイルがなければ従来どおりテキストだけのメッセージになる。
+pub fn user_message_with_attachments(text: &str, attachments: &[String]) -> Result<Message> {
+    if attachments.is_empty() {
+        return Ok(Message::user(text));
+    }
+
+    let mut blocks = attachments
+        .iter()
+        .map(|source| load_attachment(source))
+        .collect::<Result<Vec<_>>>()?;
+    if !text.is_empty() {
+        blocks.push(ContentBlock::text(text));
+    }
+    validate_blocks(&blocks)?;
+    Ok(Message::user(blocks))
+}
+
+/// 送信前にブロックのサイズを確認する
+pub fn validate_blocks(blocks: &[ContentBlock]) -> Result<()> {
+    let mut total = 0;
+    for block in blocks {
+        let (source, limit) = match block {
+            ContentBlock::Image { source } => (source, MAX_IMAGE_BYTES),
+            ContentBlock::Document { source, .. } => (source, MAX_DOCUMENT_BYTES),
+            _ => continue,
+        };
+        let (encoded, decoded) = match source {
+            MediaSource::Base64 { data, .. } => (data.len(), data.len() / 4 * 3),
+            MediaSource::Text { data, .. } => (data.len(), data.len()),
+            MediaSource::Url { .. } => continue,
+        };
+        if decoded > limit {
+            return Err(too_large(source.describe(), decoded, limit));
+        }
+        total += encoded;
+    }
+
+    if total > MAX_REQUEST_BYTES {
+        return Err(AskError::AttachmentError(format!(
+            "Attachments total {} which exceeds the request limit of {}",
+            format_size(total),
+            format_size(MAX_REQUEST_BYTES)
+        )));
+    }
+    Ok(())
+}
+
+/// メッセージ全体の添付ファイルのサイズを確認する
+pub fn validate_messages(messages: &[Message]) -> Result<()> {
+    let blocks: Vec<ContentBlock> = messages
+        .iter()
+        .filter_map(|message| match message.content {
+            MessageContent::Blocks(ref blocks) => Some(blocks.iter().cloned()),
+            MessageContent::Text(_) => None,
+        })
+        .flatten()
+        .collect();
+    validate_blocks(&blocks)
+}
+
+fn url_attachment(url: &str) -> Result<ContentBlock> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    match media_type_from_extension(Path::new(path)) {
+        Some("application/pdf") => Ok(ContentBlock::Document {
+            source: MediaSource::Url {
+                url: url.to_string(),
+            },
+            title: None,
+        }),
+        Some(media_type) if IMAGE_MEDIA_TYPES.contains(&media_type) => {
+            Ok(ContentBlock::image_url(url))
+        }
+        _ => Err(AskError::AttachmentError(format!(
+            "Cannot tell the type of {} (URLs must end in .png, .jpg, .gif, .webp or .pdf)",
+            url
+        ))),
+    }
+}
+
+fn too_large(source: &str, size: usize, limit: usize) -> AskError {
+    AskError::AttachmentError(format!(
+        "{} is {} which exceeds the limit of {}",
+        source,
+        format_size(size),
+        format_size(limit)
+    ))
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_media_type_prefers_content() {
+        let path = Path::new("screenshot.jpg");
+        assert_eq!(
+            detect_media_type(path, b"\x89PNG\r\n\x1a\n...."),
+            Some("image/png")
+        );
+        assert_eq!(detect_media_type(path, b"unknown"), Some("image/jpeg"));
+        assert_eq!(
+            detect_media_type(Path::new("file"), b"%PDF-1.7"),
+            Some("application/pdf")
+        );
+        assert_eq!(
+            detect_media_type(Path::new("a.webp"), b"RIFF\0\0\0\0WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(detect_media_type(Path::new("file.bin"), b"\0\0"), None);
+    }
+
+    #[test]
+    fn test_url_attachment() {
+        assert_eq!(
+            load_attachment("https://example.com/cat.png?size=large").unwrap(),
+            ContentBlock::image_url("https://example.com/cat.png?size=large")
+        );
+        assert!(matches!(
+            load_attachment("https://example.com/paper.pdf").unwrap(),
+            ContentBlock::Document { .. }
+        ));
+        assert!(load_attachment("https://example.com/page").is_err());
+    }
+
+    #[test]
+    fn test_validate_blocks_rejects_large_images() {
+        let data = "A".repeat(MAX_IMAGE_BYTES / 3 * 4 + 8);
+        let blocks = vec![ContentBlock::image_base64("image/png", data)];
+        assert!(matches!(
+            validate_blocks(&blocks),
+            Err(AskError::AttachmentError(_))
+        ));
+    }
+}
@@ -1,7 +1,17 @@
 pub mod api;
+pub mod attachment;
 pub mod models;
+pub mod request;
+pub mod retry;
+pub mod sse;
 pub mod streaming;
+pub mod tools;
 
 pub use api::*;
+pub use attachment::*;
 pub use models::*;
+pub use request::*;
+pub use retry::*;
+pub use sse::*;
 pub use streaming::*;
+pub use tools::*;
@@ -1,3 +1,5 @@
+use crate::config::ApiConfig;
+use crate::error::AskError;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -5,21 +7,113 @@ use uuid::Uuid;
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Message {
     pub role: String,
-    pub content: String,
+    pub content: MessageContent,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// メッセージの本文
+///
+/// テキストだけのメッセージは従来どおり文字列として送受信し、
+/// 画像や文書を含む場合はコンテンツブロックの配列になる。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatRequest {
     pub model: String,
     pub max_tokens: u32,
     pub messages: Vec<Message>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RequestMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ToolDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// モデルに渡すツールの定義
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 入力の JSON Schema
+    pub input_schema: serde_json::Value,
+}
+
+/// モデルにツールをどう使わせるか
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// 使うかどうかをモデルに任せる
+    Auto,
+    /// いずれかのツールを必ず使わせる
+    Any,
+    /// 指定したツールを必ず使わせる
+    Tool { name: String },
+    /// ツールを使わせない
+    None,
+}
+
+/// リクエストに付けるメタデータ
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RequestMetadata {
+    /// 利用者を識別する不透明な ID（名前やメールアドレスなどは含めない）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+impl ChatRequest {
+    /// 必須の項目だけを指定してリクエストを作成する
+    pub fn new(model: &str, max_tokens: u32, messages: Vec<Message>) -> Self {
+        Self {
+            model: model.to_string(),
+            max_tokens,
+            messages,
+            system: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            stop_sequences: None,
+            metadata: None,
+            tools: None,
+            tool_choice: None,
+            stream: None,
+        }
+    }
+
+    /// 設定の値からリクエストを作成する
+    pub fn from_config(config: &ApiConfig, messages: Vec<Message>, system: Option<&str>) -> Self {
+        Self {
+            system: system.map(str::to_string),
+            temperature: Some(config.temperature),
+            top_p: config.top_p,
+            top_k: config.top_k,
+            stop_sequences: Some(config.stop_sequences.clone()).filter(|stop| !stop.is_empty()),
+            metadata: config.user_id.as_ref().map(|user_id| RequestMetadata {
+                user_id: Some(user_id.clone()),
+            }),
+            ..Self::new(&config.model, config.max_tokens, messages)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatResponse {
     pub id: String,
     pub r#type: String,
@@ -31,32 +125,109 @@ pub struct ChatResponse {
     pub usage: Usage,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ContentBlock {
-    pub r#type: String,
-    pub text: String,
+/// コンテンツブロック
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: MediaSource,
+    },
+    Document {
+        source: MediaSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    /// モデルからのツール呼び出し
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// ツールの実行結果（ユーザーのメッセージとして返す）
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: MessageContent,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
+    },
+    /// このクライアントが扱わない種類のブロック（受け取った JSON のまま）
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// 画像・文書の取得元
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    Base64 {
+        media_type: String,
+        data: String,
+    },
+    Url {
+        url: String,
+    },
+    /// プレーンテキストの文書
+    Text {
+        media_type: String,
+        data: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct Usage {
+    /// `message_delta` イベントには含まれないため省略可能
+    #[serde(default)]
     pub input_tokens: u32,
     pub output_tokens: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StreamEvent {
-    pub r#type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub message: Option<StreamMessage>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub content_block: Option<ContentBlock>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub delta: Option<Delta>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub usage: Option<Usage>,
+/// ストリーミングで届くイベント
+///
+/// JSON との変換は API のイベントの形式のまま行う（`content_block_delta` は
+/// デルタの種類ごとに `TextDelta` と `InputJsonDelta` に分かれる）。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(try_from = "serde_json::Value", into = "serde_json::Value")]
+pub enum StreamEvent {
+    MessageStart {
+        message: StreamMessage,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    TextDelta {
+        index: usize,
+        text: String,
+    },
+    /// ツール入力の JSON の断片
+    InputJsonDelta {
+        index: usize,
+        partial_json: String,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    /// 停止理由と出力トークン数（生成の終わりに届く）
+    MessageDelta {
+        stop_reason: Option<String>,
+        stop_sequence: Option<String>,
+        usage: Option<Usage>,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        r#type: String,
+        message: String,
+    },
+    /// このクライアントが扱わない種類のイベント（受け取った JSON のまま）
+    Unknown(serde_json::Value),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct StreamMessage {
     pub id: String,
     pub r#type: String,
@@ -68,10 +239,157 @@ pub struct StreamMessage {
     pub usage: Usage,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Delta {
-    pub r#type: String,
-    pub text: String,
+/// API のイベントの JSON の形（`StreamEvent` との変換にだけ使う）
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireEvent {
+    MessageStart {
+        message: StreamMessage,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: WireDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: WireMessageDelta,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: ErrorDetails,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireDelta {
+    TextDelta { text: String },
+    InputJsonDelta { partial_json: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct WireMessageDelta {
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    stop_sequence: Option<String>,
+}
+
+/// API が定義しているイベントの種類（これ以外は `Unknown` として扱う）
+const KNOWN_EVENT_TYPES: &[&str] = &[
+    "message_start",
+    "content_block_start",
+    "content_block_delta",
+    "content_block_stop",
+    "message_delta",
+    "message_stop",
+    "ping",
+    "error",
+];
+
+impl TryFrom<serde_json::Value> for StreamEvent {
+    type Error = String;
+
+    fn try_from(value: serde_json::Value) -> std::result::Result<Self, String> {
+        let Some(event_type) = value.get("type").and_then(|t| t.as_str()) else {
+            return Err("event has no type".to_string());
+        };
+        let event_type = event_type.to_string();
+        // 未知のイベントやデルタは、将来の API の拡張として読み飛ばせるようにする
+        let delta_type = value.pointer("/delta/type").and_then(|t| t.as_str());
+        let known = KNOWN_EVENT_TYPES.contains(&event_type.as_str())
+            && (event_type != "content_block_delta"
+                || matches!(delta_type, Some("text_delta" | "input_json_delta")));
+        if !known {
+            return Ok(StreamEvent::Unknown(value));
+        }
+
+        let wire: WireEvent = serde_json::from_value(value)
+            .map_err(|e| format!("invalid {} event: {}", event_type, e))?;
+        Ok(match wire {
+            WireEvent::MessageStart { message } => StreamEvent::MessageStart { message },
+            WireEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            },
+            WireEvent::ContentBlockDelta { index, delta } => match delta {
+                WireDelta::TextDelta { text } => StreamEvent::TextDelta { index, text },
+                WireDelta::InputJsonDelta { partial_json } => StreamEvent::InputJsonDelta {
+                    index,
+                    partial_json,
+                },
+            },
+            WireEvent::ContentBlockStop { index } => StreamEvent::ContentBlockStop { index },
+            WireEvent::MessageDelta { delta, usage } => StreamEvent::MessageDelta {
+                stop_reason: delta.stop_reason,
+                stop_sequence: delta.stop_sequence,
+                usage,
+            },
+            WireEvent::MessageStop => StreamEvent::MessageStop,
+            WireEvent::Ping => StreamEvent::Ping,
+            WireEvent::Error { error } => StreamEvent::Error {
+                r#type: error.r#type,
+                message: error.message,
+            },
+        })
+    }
+}
+
+impl From<StreamEvent> for serde_json::Value {
+    fn from(event: StreamEvent) -> Self {
+        let wire = match event {
+            StreamEvent::MessageStart { message } => WireEvent::MessageStart { message },
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => WireEvent::ContentBlockStart {
+                index,
+                content_block,
+            },
+            StreamEvent::TextDelta { index, text } => WireEvent::ContentBlockDelta {
+                index,
+                delta: WireDelta::TextDelta { text },
+            },
+            StreamEvent::InputJsonDelta {
+                index,
+                partial_json,
+            } => WireEvent::ContentBlockDelta {
+                index,
+                delta: WireDelta::InputJsonDelta { partial_json },
+            },
+            StreamEvent::ContentBlockStop { index } => WireEvent::ContentBlockStop { index },
+            StreamEvent::MessageDelta {
+                stop_reason,
+                stop_sequence,
+                usage,
+            } => WireEvent::MessageDelta {
+                delta: WireMessageDelta {
+                    stop_reason,
+                    stop_sequence,
+                },
+                usage,
+            },
+            StreamEvent::MessageStop => WireEvent::MessageStop,
+            StreamEvent::Ping => WireEvent::Ping,
+            StreamEvent::Error { r#type, message } => WireEvent::Error {
+                error: ErrorDetails { r#type, message },
+            },
+            StreamEvent::Unknown(value) => return value,
+        };
+        serde_json::to_value(wire).unwrap_or(serde_json::Value::Null)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -80,12 +398,50 @@ pub struct ErrorResponse {
     pub error: ErrorDetails,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ErrorDetails {
     pub r#type: String,
     pub message: String,
 }
 
+impl ErrorDetails {
+    /// エラーの種類（`type`）に対応する `AskError` に変換する（未知の種類は None）
+    ///
+    /// HTTP のエラーレスポンスとストリーム中の `error` イベントで共通に使う。
+    pub fn to_error(&self, request_id: Option<String>) -> Option<AskError> {
+        let message = self.message.clone();
+        Some(match self.r#type.as_str() {
+            "authentication_error" => AskError::AuthenticationFailed,
+            "rate_limit_error" => AskError::RateLimitExceeded,
+            "invalid_request_error" => AskError::InvalidRequest {
+                message,
+                request_id,
+            },
+            "permission_error" => AskError::PermissionDenied {
+                message,
+                request_id,
+            },
+            "not_found_error" => AskError::NotFound {
+                message,
+                request_id,
+            },
+            "request_too_large" => AskError::RequestTooLarge {
+                message,
+                request_id,
+            },
+            "overloaded_error" => AskError::Overloaded {
+                message,
+                request_id,
+            },
+            "api_error" => AskError::ApiServerError {
+                message,
+                request_id,
+            },
+            _ => return None,
+        })
+    }
+}
+
 // 会話履歴用のモデル
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Conversation {
@@ -93,6 +449,8 @@ pub struct Conversation {
     pub title: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
     pub messages: Vec<Message>,
 }
 
@@ -104,6 +462,7 @@ impl Conversation {
             title,
             created_at: now,
             updated_at: now,
+            system: None,
             messages: Vec::new(),
         }
     }
@@ -114,18 +473,244 @@ impl Conversation {
     }
 }
 
+impl ChatResponse {
+    /// レスポンス中のテキストブロックを連結して返す
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(ContentBlock::as_text)
+            .collect()
+    }
+
+    /// ツールの呼び出しを待って停止したかどうか
+    pub fn wants_tools(&self) -> bool {
+        self.stop_reason.as_deref() == Some("tool_use")
+    }
+
+    /// レスポンス中のツール呼び出し（ID・ツール名・入力）
+    pub fn tool_uses(&self) -> impl Iterator<Item = (&str, &str, &serde_json::Value)> {
+        self.content.iter().filter_map(|block| match block {
+            ContentBlock::ToolUse { id, name, input } => Some((id.as_str(), name.as_str(), input)),
+            _ => None,
+        })
+    }
+}
+
+impl StreamEvent {
+    /// テキストのデルタならその内容を返す
+    pub fn text_delta(&self) -> Option<&str> {
+        match self {
+            StreamEvent::TextDelta { text, .. } if !text.is_empty() => Some(text),
+            _ => None,
+        }
+    }
+
+    /// `error` イベントを `AskError` に変換する（それ以外のイベントは None）
+    ///
+    /// エラーの種類は HTTP のエラーレスポンスと同じ変種に振り分け、未知の種類は
+    /// `AskError::StreamError` にする。
+    pub fn to_error(&self) -> Option<AskError> {
+        match self {
+            StreamEvent::Error { r#type, message } => {
+                let details = ErrorDetails {
+                    r#type: r#type.clone(),
+                    message: message.clone(),
+                };
+                Some(
+                    details.to_error(None).unwrap_or_else(|| {
+                        AskError::StreamError(format!("{}: {}", r#type, message))
+                    }),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
 impl Message {
-    pub fn user(content: String) -> Self {
+    pub fn user(content: impl Into<MessageContent>) -> Self {
         Self {
             role: "user".to_string(),
-            content,
+            content: content.into(),
         }
     }
 
-    pub fn assistant(content: String) -> Self {
+    pub fn assistant(content: impl Into<MessageContent>) -> Self {
         Self {
             role: "assistant".to_string(),
-            content,
+            content: content.into(),
+        }
+    }
+
+    /// 本文のテキスト部分を返す
+    pub fn text(&self) -> String {
+        self.content.text()
+    }
+}
+
+impl MessageContent {
+    /// テキストブロックを連結して返す（画像や文書は含まない）
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Blocks(blocks) => {
+                blocks.iter().filter_map(ContentBlock::as_text).collect()
+            }
+        }
+    }
+
+    /// ブロックの一覧として返す
+    pub fn blocks(&self) -> Vec<ContentBlock> {
+        match self {
+            MessageContent::Text(text) => vec![ContentBlock::text(text.clone())],
+            MessageContent::Blocks(blocks) => blocks.clone(),
+        }
+    }
+
+    /// テキスト以外のブロックを含むかどうか
+    pub fn has_attachments(&self) -> bool {
+        match self {
+            MessageContent::Text(_) => false,
+            MessageContent::Blocks(blocks) => blocks.iter().any(|block| {
+                matches!(
+                    block,
+                    ContentBlock::Image { .. } | ContentBlock::Document { .. }
+                )
+            }),
+        }
+    }
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Text(String::new())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        MessageContent::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentBlock>> for MessageContent {
+    fn from(blocks: Vec<ContentBlock>) -> Self {
+        MessageContent::Blocks(blocks)
+    }
+}
+
+impl PartialEq<str> for MessageContent {
+    fn eq(&self, other: &str) -> bool {
+        matches!(self, MessageContent::Text(text) if text == other)
+    }
+}
+
+impl PartialEq<&str> for MessageContent {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+/// テキストはそのまま、添付ファイルは `[image: image/png]` のような目印として表示する
+impl std::fmt::Display for MessageContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageContent::Text(text) => f.write_str(text),
+            MessageContent::Blocks(blocks) => {
+                let mut first = true;
+                for block in blocks {
+                    let part = match block {
+                        ContentBlock::Text { text } => text.clone(),
+                        ContentBlock::Image { source } => format!("[image: {}]", source.describe()),
+                        ContentBlock::Document { source, title } => match title {
+                            Some(title) => format!("[document: {}]", title),
+                            None => format!("[document: {}]", source.describe()),
+                        },
+                        ContentBlock::ToolUse { name, input, .. } => {
+                            format!("[tool_use: {} {}]", name, input)
+                        }
+                        ContentBlock::ToolResult {
+                            content, is_error, ..
+                        } => {
+                            let label = if *is_error {
+                                "tool_error"
+                            } else {
+                                "tool_result"
+                            };
+                            format!("[{}: {}]", label, content)
+                        }
+                        ContentBlock::Unknown(_) => continue,
+                    };
+                    if !first {
+                        f.write_str("\n")?;
+                    }
+                    f.write_str(&part)?;
+                    first = false;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ContentBlock {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentBlock::Text { text: text.into() }
+    }
+
+    /// base64 でエンコード済みの画像
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        ContentBlock::Image {
+            source: MediaSource::Base64 {
+                media_type: media_type.into(),
+                data: data.into(),
+            },
+        }
+    }
+
+    /// URL で参照する画像
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentBlock::Image {
+            source: MediaSource::Url { url: url.into() },
+        }
+    }
+
+    /// ツールの実行結果
+    pub fn tool_result(
+        tool_use_id: impl Into<String>,
+        content: impl Into<MessageContent>,
+        is_error: bool,
+    ) -> Self {
+        ContentBlock::ToolResult {
+            tool_use_id: tool_use_id.into(),
+            content: content.into(),
+            is_error,
+        }
+    }
+
+    /// テキストブロックならその内容を返す
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            ContentBlock::Text { text } => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl MediaSource {
+    /// 表示用の短い説明（メディアタイプまたは URL）
+    pub fn describe(&self) -> &str {
+        match self {
+            MediaSource::Base64 { media_type, .. } | MediaSource::Text { media_type, .. } => {
+                media_type
+            }
+            MediaSource::Url { url } => url,
         }
     }
 }
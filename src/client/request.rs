@@ -0,0 +1,188 @@
+use crate::client::api::ClaudeClient;
+use crate::client::attachment;
+use crate::client::models::{
+    ChatRequest, ChatResponse, Message, RequestMetadata, StreamEvent, ToolChoice,
+};
+use crate::client::tools::{ToolLoopEvent, ToolRun, ToolSet, DEFAULT_MAX_TOOL_ITERATIONS};
+use crate::config::ApiConfig;
+use crate::error::{AskError, Result};
+use tokio_stream::{Stream, StreamExt};
+
+/// モデルを指定しなかった場合に使うモデル
+pub const DEFAULT_MODEL: &str = "claude-3-5-sonnet-20241022";
+
+/// 最大トークン数を指定しなかった場合の値
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Messages API へのリクエストを組み立てるビルダー
+///
+/// ```no_run
+/// # async fn example(client: &ask::ClaudeClient) -> ask::Result<()> {
+/// let response = client
+///     .messages()
+///     .model("claude-3-5-sonnet-20241022")
+///     .system("You are terse.")
+///     .max_tokens(1024)
+///     .temperature(0.2)
+///     .user("Hello, Claude!")
+///     .send()
+///     .await?;
+/// println!("{}", response.text());
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "a request does nothing until .send() or .stream() is called"]
+pub struct MessageRequest<'a> {
+    client: &'a ClaudeClient,
+    request: ChatRequest,
+}
+
+impl<'a> MessageRequest<'a> {
+    pub fn new(client: &'a ClaudeClient) -> Self {
+        Self {
+            client,
+            request: ChatRequest::new(DEFAULT_MODEL, DEFAULT_MAX_TOKENS, Vec::new()),
+        }
+    }
+
+    /// 設定の値（モデル・最大トークン数・サンプリングのパラメータなど）をまとめて反映する
+    pub fn config(mut self, config: &ApiConfig) -> Self {
+        let messages = std::mem::take(&mut self.request.messages);
+        let system = self.request.system.take();
+        self.request = ChatRequest::from_config(config, messages, system.as_deref());
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.request.model = model.into();
+        self
+    }
+
+    pub fn system(mut self, system: impl Into<String>) -> Self {
+        self.request.system = Some(system.into());
+        self
+    }
+
+    /// システムプロンプトを指定する（None の場合は指定しない）
+    pub fn system_opt(mut self, system: Option<&str>) -> Self {
+        self.request.system = system.map(str::to_string);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.request.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn temperature(mut self, temperature: impl Into<Option<f32>>) -> Self {
+        self.request.temperature = temperature.into();
+        self
+    }
+
+    pub fn top_p(mut self, top_p: f32) -> Self {
+        self.request.top_p = Some(top_p);
+        self
+    }
+
+    pub fn top_k(mut self, top_k: u32) -> Self {
+        self.request.top_k = Some(top_k);
+        self
+    }
+
+    /// 生成を止める文字列を追加する
+    pub fn stop_sequence(mut self, sequence: impl Into<String>) -> Self {
+        self.request
+            .stop_sequences
+            .get_or_insert_with(Vec::new)
+            .push(sequence.into());
+        self
+    }
+
+    pub fn user_id(mut self, user_id: impl Into<String>) -> Self {
+        self.request.metadata = Some(RequestMetadata {
+            user_id: Some(user_id.into()),
+        });
+        self
+    }
+
+    /// 会話のメッセージをまとめて指定する（それまでのメッセージは置き換える）
+    pub fn messages(mut self, messages: Vec<Message>) -> Self {
+        self.request.messages = messages;
+        self
+    }
+
+    /// メッセージを1つ追加する
+    pub fn message(mut self, message: Message) -> Self {
+        self.request.messages.push(message);
+        self
+    }
+
+    /// ユーザーのメッセージを1つ追加する
+    pub fn user(self, content: impl Into<String>) -> Self {
+        self.message(Message::user(content.into()))
+    }
+
+    /// 使えるツールを指定する（ツールの定義だけを送る）
+    pub fn tools(mut self, tools: &ToolSet) -> Self {
+        self.request.tools = Some(tools.definitions());
+        self
+    }
+
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.request.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// 組み立てたリクエスト
+    pub fn build(&self) -> &ChatRequest {
+        &self.request
+    }
+
+    /// リクエストを送信し、応答全体を返す
+    pub async fn send(self) -> Result<ChatResponse> {
+        self.validate()?;
+        self.client.send_request(&self.request).await
+    }
+
+    /// リクエストをストリーミング形式で送信し、SSE のイベントを返す
+    pub async fn stream(self) -> Result<impl Stream<Item = Result<StreamEvent>>> {
+        self.validate()?;
+        self.client.stream_request(&self.request).await
+    }
+
+    /// リクエストをストリーミング形式で送信し、テキストだけを返す
+    pub async fn stream_text(self) -> Result<impl Stream<Item = Result<String>>> {
+        Ok(self.stream().await?.filter_map(|event| match event {
+            Ok(event) => event.text_delta().map(|text| Ok(text.to_string())),
+            Err(e) => Some(Err(e)),
+        }))
+    }
+
+    /// ツールを実行しながら、モデルがツールを使わなくなるまで会話を進める
+    pub async fn run_tools(self, tools: &ToolSet) -> Result<ToolRun> {
+        self.run_tools_with(tools, DEFAULT_MAX_TOOL_ITERATIONS, |_| {})
+            .await
+    }
+
+    /// 繰り返しの上限と途中経過を受け取る関数を指定してツールループを実行する
+    pub async fn run_tools_with(
+        self,
+        tools: &ToolSet,
+        max_iterations: usize,
+        mut on_event: impl FnMut(ToolLoopEvent<'_>),
+    ) -> Result<ToolRun> {
+        self.validate()?;
+        self.client
+            .run_tools(&self.request, tools, max_iterations, &mut on_event)
+            .await
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.request.messages.is_empty() {
+            return Err(AskError::InvalidInput(
+                "A request needs at least one message".to_string(),
+            ));
+        }
+        attachment::validate_messages(&self.request.messages)
+    }
+}
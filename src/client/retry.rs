@@ -0,0 +1,182 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 待ち時間の上限（`retry-after` の指定にも適用する）
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// 再試行するステータスコード（529 は API が過負荷のとき）
+const RETRYABLE_STATUSES: [u16; 5] = [429, 500, 502, 503, 529];
+
+/// 再試行するストリーム中の `error` イベントの種類
+const RETRYABLE_ERROR_TYPES: [&str; 3] = ["rate_limit_error", "api_error", "overloaded_error"];
+
+/// 失敗したリクエストを再試行する方針
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 最初の1回を含めた試行回数の上限（1 なら再試行しない）
+    pub max_attempts: u32,
+    /// 最初の再試行までの待ち時間（以降は2倍ずつ増やす）
+    pub base_delay_ms: u64,
+    /// 待ち時間をばらつかせる割合（0.0〜1.0）
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 1000,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 再試行しない
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// `attempt` 回目の試行が失敗した後に、もう一度試行するかどうか
+    pub fn should_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// `attempt` 回目の試行が失敗した後の待ち時間
+    ///
+    /// サーバーが `retry-after` で指定した場合はその時間だけ待つ（[`MAX_RETRY_DELAY`] まで）。
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(MAX_RETRY_DELAY);
+        }
+
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = Duration::from_millis(self.base_delay_ms)
+            .saturating_mul(1 << exponent)
+            .min(MAX_RETRY_DELAY);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        delay.mul_f64(1.0 - jitter + 2.0 * jitter * fastrand::f64())
+    }
+}
+
+/// 再試行の前の待ち時間の経過（進行状況の表示に使う）
+#[derive(Debug, Clone, Copy)]
+pub struct RetryNotice<'a> {
+    /// 次の試行が何回目か
+    pub attempt: u32,
+    pub max_attempts: u32,
+    /// 再試行する理由
+    pub reason: &'a str,
+    /// 次の試行までの残り時間（0 なら待ち終わった）
+    pub remaining: Duration,
+}
+
+/// 再試行すべきステータスコードかどうか
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    RETRYABLE_STATUSES.contains(&status.as_u16())
+}
+
+/// 再試行すべき通信エラーかどうか
+pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_connect()
+}
+
+/// 再試行すべきストリーム中の `error` イベントかどうか
+pub fn is_retryable_error_type(error_type: &str) -> bool {
+    RETRYABLE_ERROR_TYPES.contains(&error_type)
+}
+
+/// `retry-after` ヘッダーの秒数を読む（日付形式には対応しない）
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    let seconds: f64 = value.trim().parse().ok()?;
+    // 負の値や表せないほど大きな値は指定がなかったものとして扱う
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay_ms: 500,
+            jitter: 0.0,
+        };
+        assert_eq!(policy.delay(1, None), Duration::from_millis(500));
+        assert_eq!(policy.delay(2, None), Duration::from_millis(1000));
+        assert_eq!(policy.delay(3, None), Duration::from_millis(2000));
+        assert_eq!(policy.delay(40, None), MAX_RETRY_DELAY);
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(30))),
+            Duration::from_secs(30)
+        );
+        // サーバーの指定も上限で切り詰める
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(86400))),
+            MAX_RETRY_DELAY
+        );
+
+        assert!(policy.should_retry(4));
+        assert!(!policy.should_retry(5));
+        assert!(!RetryPolicy::none().should_retry(1));
+    }
+
+    #[test]
+    fn test_jitter_stays_in_range() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 1000,
+            jitter: 0.5,
+        };
+        for _ in 0..100 {
+            let delay = policy.delay(1, None);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1500));
+        }
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        for status in [429, 500, 502, 503, 529] {
+            assert!(is_retryable_status(StatusCode::from_u16(status).unwrap()));
+        }
+        for status in [400, 401, 403, 404, 413] {
+            assert!(!is_retryable_status(StatusCode::from_u16(status).unwrap()));
+        }
+        assert!(is_retryable_error_type("overloaded_error"));
+        assert!(!is_retryable_error_type("invalid_request_error"));
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("0.5"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(500)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), None);
+
+        // Duration で表せない値でも panic しない
+        for value in ["1e300", "-1", "inf", "NaN"] {
+            headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+            assert_eq!(retry_after(&headers), None, "{}", value);
+        }
+    }
+}
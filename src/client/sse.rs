@@ -0,0 +1,291 @@
+use crate::error::{AskError, Result};
+use futures::stream;
+use std::time::Duration;
+use tokio_stream::{Stream, StreamExt};
+
+/// SSE（Server-Sent Events）の1つのイベント
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// `event:` フィールドの値（指定がなければ "message"）
+    pub event: String,
+    /// `data:` フィールドの値（複数行の場合は改行で連結したもの）
+    pub data: String,
+    /// このイベントの時点での最後の `id:` の値
+    pub id: Option<String>,
+}
+
+/// WHATWG の仕様（HTML Living Standard の event stream の解釈）に従う SSE のデコーダ
+///
+/// 受け取ったバイト列を行単位で解釈する。行の区切りは CRLF・LF・CR のいずれでもよく、
+/// チャンクの境界がどこにあっても同じ結果になる。プロバイダに依存する処理は持たない。
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    /// 直前の行が CR で終わっていた（続く LF は同じ区切りとして読み飛ばす）
+    after_cr: bool,
+    /// 先頭の BOM を確認済みか
+    started: bool,
+    event_type: String,
+    data: String,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// バイト列を追加し、揃ったイベントを返す
+    ///
+    /// 行の途中で終わっている部分は、次の呼び出しまで持ち越す。
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut position = 0;
+        while position < self.buffer.len() {
+            match self.buffer[position] {
+                b'\n' if self.after_cr && position == start => {
+                    // CRLF の LF
+                    self.after_cr = false;
+                    start = position + 1;
+                }
+                byte @ (b'\r' | b'\n') => {
+                    let line = String::from_utf8_lossy(&self.buffer[start..position]).into_owned();
+                    self.after_cr = byte == b'\r';
+                    start = position + 1;
+                    if let Some(event) = self.process_line(&line) {
+                        events.push(event);
+                    }
+                }
+                _ => self.after_cr = false,
+            }
+            position += 1;
+        }
+        self.buffer.drain(..start);
+        events
+    }
+
+    /// 最後に受け取った `id:` の値（再接続時の `Last-Event-ID` に使う）
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// サーバーが `retry:` で指定した再接続までの時間
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// 1行を解釈し、空行であればイベントを確定させる
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        let line = if self.started {
+            line
+        } else {
+            self.started = true;
+            line.strip_prefix('\u{FEFF}').unwrap_or(line)
+        };
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None; // コメント
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event_type = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {} // 未知のフィールドは無視する
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event_type = std::mem::take(&mut self.event_type);
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let mut data = std::mem::take(&mut self.data);
+        data.pop(); // 最後の改行
+        Some(SseEvent {
+            event: if event_type.is_empty() {
+                "message".to_string()
+            } else {
+                event_type
+            },
+            data,
+            id: self.last_event_id.clone(),
+        })
+    }
+}
+
+/// バイト列のストリームを SSE のイベントのストリームに変換する
+///
+/// ストリームが空行なしで終わった場合、最後の不完全なイベントは仕様どおり捨てる。
+pub fn decode_sse<S, B, E>(bytes: S) -> impl Stream<Item = Result<SseEvent>>
+where
+    S: Stream<Item = std::result::Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<AskError>,
+{
+    stream::unfold(
+        (bytes, SseDecoder::new(), Vec::<SseEvent>::new().into_iter()),
+        |(mut stream, mut decoder, mut pending)| async move {
+            loop {
+                if let Some(event) = pending.next() {
+                    return Some((Ok(event), (stream, decoder, pending)));
+                }
+                match stream.next().await {
+                    Some(Ok(chunk)) => pending = decoder.push(chunk.as_ref()).into_iter(),
+                    Some(Err(e)) => return Some((Err(e.into()), (stream, decoder, pending))),
+                    None => return None,
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: &str, data: &str, id: Option<&str>) -> SseEvent {
+        SseEvent {
+            event: event.to_string(),
+            data: data.to_string(),
+            id: id.map(str::to_string),
+        }
+    }
+
+    /// 一度に渡した場合と1バイトずつ渡した場合で、同じイベントになることを確認する
+    fn decode_all(input: &str) -> Vec<SseEvent> {
+        let events = SseDecoder::new().push(input.as_bytes());
+
+        let mut decoder = SseDecoder::new();
+        let bytewise: Vec<SseEvent> = input
+            .as_bytes()
+            .iter()
+            .flat_map(|byte| decoder.push(std::slice::from_ref(byte)))
+            .collect();
+        assert_eq!(events, bytewise);
+        events
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let expected = vec![event("message", "a", None), event("message", "b", None)];
+        assert_eq!(decode_all("data: a\n\ndata: b\n\n"), expected);
+        assert_eq!(decode_all("data: a\r\n\r\ndata: b\r\n\r\n"), expected);
+        assert_eq!(decode_all("data: a\r\rdata: b\r\r"), expected);
+        assert_eq!(decode_all("data: a\r\n\ndata: b\n\r\n"), expected);
+    }
+
+    #[test]
+    fn test_multiline_data() {
+        assert_eq!(
+            decode_all("data: first\ndata: second\ndata\ndata:third\n\n"),
+            vec![event("message", "first\nsecond\n\nthird", None)]
+        );
+    }
+
+    #[test]
+    fn test_field_value_spacing() {
+        // コロンの後の空白は1つだけ取り除く
+        assert_eq!(
+            decode_all("data:no space\n\ndata:  two spaces\n\n"),
+            vec![
+                event("message", "no space", None),
+                event("message", " two spaces", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_and_id_fields() {
+        let input = "event: message_start\nid: 1\ndata: {}\n\ndata: next\n\nid\ndata: reset\n\n";
+        assert_eq!(
+            decode_all(input),
+            vec![
+                event("message_start", "{}", Some("1")),
+                event("message", "next", Some("1")),
+                event("message", "reset", Some("")),
+            ]
+        );
+
+        // NUL を含む id は無視する
+        let mut decoder = SseDecoder::new();
+        decoder.push(b"id: 7\n\nid: a\0b\n\n");
+        assert_eq!(decoder.last_event_id(), Some("7"));
+    }
+
+    #[test]
+    fn test_comments_and_unknown_fields() {
+        assert_eq!(
+            decode_all(": keep-alive\nfoo: bar\ndata: x\n:another\n\n"),
+            vec![event("message", "x", None)]
+        );
+    }
+
+    #[test]
+    fn test_events_without_data_are_not_dispatched() {
+        // data のないイベントは送らず、event の値も持ち越さない
+        assert_eq!(
+            decode_all("event: ping\n\ndata: x\n\n"),
+            vec![event("message", "x", None)]
+        );
+    }
+
+    #[test]
+    fn test_retry_field() {
+        let mut decoder = SseDecoder::new();
+        decoder.push(b"retry: 1500\n\nretry: soon\n\n");
+        assert_eq!(decoder.retry(), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn test_bom_and_incomplete_event() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push("\u{FEFF}data: a\n\ndata: partial".as_bytes());
+        assert_eq!(events, vec![event("message", "a", None)]);
+        // 行が終わるまでは何も返さない
+        assert!(decoder.push(b" line\ndata: more").is_empty());
+        assert_eq!(
+            decoder.push(b"\n\n"),
+            vec![event("message", "partial line\nmore", None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decode_stream() {
+        let chunks = vec![
+            Ok::<_, AskError>("event: a\r".as_bytes().to_vec()),
+            Ok("\ndata: こん".as_bytes()[..12].to_vec()),
+            Ok("\ndata: こん".as_bytes()[12..].to_vec()),
+            Ok(b"\r\n\r\n".to_vec()),
+            Err(AskError::StreamError("connection reset".to_string())),
+        ];
+        let mut events = Box::pin(decode_sse(tokio_stream::iter(chunks)));
+        assert_eq!(
+            events.next().await.unwrap().unwrap(),
+            event("a", "こん", None)
+        );
+        assert!(events.next().await.unwrap().is_err());
+        assert!(events.next().await.is_none());
+    }
+}
@@ -1,106 +1,192 @@
-use crate::client::models::StreamEvent;
+use crate::client::models::{ChatResponse, ContentBlock, StreamEvent, Usage};
+use crate::client::sse::decode_sse;
 use crate::error::{AskError, Result};
-use futures::stream;
 use reqwest::Response;
+use std::collections::HashMap;
 use tokio_stream::{Stream, StreamExt};
 
+/// レスポンスのテキストだけを順に返すストリームを作成する
+///
+/// [`create_event_stream`] のアダプタで、`error` イベントはエラーとして返す。
 pub fn create_stream(response: Response) -> impl Stream<Item = Result<String>> {
-    let byte_stream = response.bytes_stream();
-
-    stream::unfold(
-        (byte_stream, String::new()),
-        move |(mut stream, mut buffer)| async move {
-            while let Some(chunk_result) = stream.next().await {
-                match chunk_result {
-                    Ok(chunk) => {
-                        buffer.push_str(&String::from_utf8_lossy(&chunk));
-
-                        // SSE形式のパースを行う
-                        loop {
-                            // 完全なSSEイベントを探す（空行まで）
-                            if let Some(event_end) = buffer.find("\n\n") {
-                                let event_data = buffer[..event_end].to_string();
-                                buffer.drain(..event_end + 2);
-
-                                // データ行を抽出
-                                for line in event_data.lines() {
-                                    if let Some(data) = line.strip_prefix("data: ") {
-                                        if data == "[DONE]" {
-                                            return None; // ストリーム終了
-                                        }
-
-                                        match parse_stream_event(data) {
-                                            Ok(Some(text)) => {
-                                                return Some((Ok(text), (stream, buffer)));
-                                            }
-                                            Ok(None) => {
-                                                // テキストがない場合は続行
-                                                continue;
-                                            }
-                                            Err(_) => {
-                                                // JSONパースエラーの場合はサイレントにスキップ
-                                                continue;
-                                            }
-                                        }
-                                    }
-                                }
-                            } else {
-                                // 完全なイベントがない場合は、次のチャンクを待つ
-                                break;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        return Some((Err(AskError::NetworkError(e)), (stream, buffer)));
+    text_stream(create_event_stream(response))
+}
+
+/// イベントのストリームをテキストだけのストリームに変換する
+pub fn text_stream(
+    events: impl Stream<Item = Result<StreamEvent>>,
+) -> impl Stream<Item = Result<String>> {
+    events.filter_map(|event| match event {
+        Ok(event) => match event.to_error() {
+            Some(error) => Some(Err(error)),
+            None => event.text_delta().map(|text| Ok(text.to_string())),
+        },
+        Err(e) => Some(Err(e)),
+    })
+}
+
+/// SSE のイベントを順に返すストリームを作成する
+///
+/// `error` イベントも `StreamEvent::Error` としてそのまま返す。
+pub fn create_event_stream(response: Response) -> impl Stream<Item = Result<StreamEvent>> {
+    event_stream(response.bytes_stream())
+}
+
+/// バイト列のストリームから SSE のイベントを順に返すストリームを作成する
+///
+/// SSE の解釈は [`decode_sse`] に任せ、各イベントの `data` を [`StreamEvent`] として読む。
+/// 読めないイベントは `AskError::StreamError` として返す。
+pub fn event_stream<S, B, E>(bytes: S) -> impl Stream<Item = Result<StreamEvent>>
+where
+    S: Stream<Item = std::result::Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Into<AskError>,
+{
+    decode_sse(bytes)
+        .take_while(|event| !matches!(event, Ok(event) if event.data == "[DONE]"))
+        .filter_map(|event| match event {
+            Ok(event) => parse_event(&event.data).transpose(),
+            Err(e) => Some(Err(e)),
+        })
+}
+
+/// ストリーミングのイベントから、非ストリーミングと同じ形のレスポンスを組み立てる
+#[derive(Debug, Clone)]
+pub struct ResponseAccumulator {
+    response: ChatResponse,
+    /// ブロックごとに受信途中のツール入力（JSON の断片）
+    tool_inputs: HashMap<usize, String>,
+}
+
+impl ResponseAccumulator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            response: ChatResponse {
+                id: String::new(),
+                r#type: "message".to_string(),
+                role: "assistant".to_string(),
+                content: Vec::new(),
+                model: model.to_string(),
+                stop_reason: None,
+                stop_sequence: None,
+                usage: Usage::default(),
+            },
+            tool_inputs: HashMap::new(),
+        }
+    }
+
+    /// イベントを1つ反映する
+    ///
+    /// ツール入力の JSON が読めない場合は `AskError::StreamError` を返す（空の入力で
+    /// ツールを実行しないため）。
+    pub fn push(&mut self, event: &StreamEvent) -> Result<()> {
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.response.id = message.id.clone();
+                self.response.model = message.model.clone();
+                self.response.usage = message.usage.clone();
+            }
+            StreamEvent::ContentBlockStart { content_block, .. } => {
+                self.response.content.push(content_block.clone());
+            }
+            StreamEvent::TextDelta { index, text } => {
+                if self.response.content.is_empty() {
+                    self.response.content.push(ContentBlock::text(""));
+                }
+                let index = (*index).min(self.response.content.len() - 1);
+                if let ContentBlock::Text {
+                    text: ref mut block,
+                } = self.response.content[index]
+                {
+                    block.push_str(text);
+                }
+            }
+            StreamEvent::InputJsonDelta {
+                index,
+                partial_json,
+            } => {
+                self.tool_inputs
+                    .entry(*index)
+                    .or_default()
+                    .push_str(partial_json);
+            }
+            StreamEvent::ContentBlockStop { index } => {
+                let Some(json) = self.tool_inputs.remove(index) else {
+                    return Ok(());
+                };
+                if let Some(ContentBlock::ToolUse { name, input, .. }) =
+                    self.response.content.get_mut(*index)
+                {
+                    // 引数のないツールでは断片が空のまま終わる
+                    if !json.trim().is_empty() {
+                        *input = serde_json::from_str(&json).map_err(|e| {
+                            AskError::StreamError(format!(
+                                "Invalid input for tool '{}': {}",
+                                name, e
+                            ))
+                        })?;
                     }
                 }
             }
+            StreamEvent::MessageDelta {
+                stop_reason,
+                stop_sequence,
+                usage,
+            } => {
+                self.response.stop_reason = stop_reason.clone();
+                self.response.stop_sequence = stop_sequence.clone();
+                if let Some(usage) = usage {
+                    self.response.usage.output_tokens = usage.output_tokens;
+                }
+            }
+            StreamEvent::MessageStop
+            | StreamEvent::Ping
+            | StreamEvent::Error { .. }
+            | StreamEvent::Unknown(_) => {}
+        }
+        Ok(())
+    }
 
-            None // ストリーム終了
-        },
-    )
-}
+    /// ここまでに受信したテキスト
+    pub fn text(&self) -> String {
+        self.response.text()
+    }
 
-fn parse_stream_event(data: &str) -> Result<Option<String>> {
-    if data.trim().is_empty() {
-        return Ok(None);
+    /// 停止理由（`message_delta` を受信するまでは None）
+    pub fn stop_reason(&self) -> Option<&str> {
+        self.response.stop_reason.as_deref()
     }
 
-    // JSONパースの前に、データが完全かどうかを簡単にチェック
-    let trimmed_data = data.trim();
-    if !trimmed_data.starts_with('{') || !trimmed_data.ends_with('}') {
-        return Ok(None); // 不完全なJSONはスキップ
+    /// ここまでに受信したトークン数
+    pub fn usage(&self) -> &Usage {
+        &self.response.usage
     }
 
-    let event: StreamEvent = serde_json::from_str(trimmed_data)
-        .map_err(|e| AskError::StreamError(format!("Failed to parse stream event: {}", e)))?;
+    pub fn finish(self) -> ChatResponse {
+        self.response
+    }
+}
 
-    match event.r#type.as_str() {
-        "content_block_start" => Ok(None),
-        "content_block_delta" => {
-            if let Some(delta) = event.delta {
-                Ok(Some(delta.text))
-            } else {
-                Ok(None)
-            }
-        }
-        "content_block_stop" => Ok(None),
-        "message_start" => Ok(None),
-        "message_delta" => Ok(None),
-        "message_stop" => Ok(None),
-        "ping" => Ok(None),
-        "error" => Err(AskError::StreamError(format!("Stream error: {:?}", event))),
-        _ => {
-            // 未知のイベントタイプは無視
-            Ok(None)
-        }
+/// `data:` 行を1つ解析する（空や不完全なデータは None）
+pub fn parse_event(data: &str) -> Result<Option<StreamEvent>> {
+    let data = data.trim();
+    if data.is_empty() {
+        return Ok(None);
     }
+
+    let event: StreamEvent = serde_json::from_str(data)
+        .map_err(|e| AskError::StreamError(format!("Failed to parse stream event: {}", e)))?;
+    Ok(Some(event))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse_stream_event(data: &str) -> Result<Option<String>> {
+        Ok(parse_event(data)?.and_then(|event| event.text_delta().map(str::to_string)))
+    }
+
     #[test]
     fn test_parse_stream_event_delta() {
         let data = r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#;
@@ -125,6 +211,232 @@ mod tests {
     #[test]
     fn test_parse_incomplete_json() {
         let result = parse_stream_event(r#"{"type":"content_block"#);
-        assert_eq!(result.unwrap(), None);
+        assert!(matches!(result, Err(AskError::StreamError(_))));
+    }
+
+    #[test]
+    fn test_parse_error_event() {
+        let data = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let event = parse_event(data).unwrap().unwrap();
+        assert_eq!(
+            event,
+            StreamEvent::Error {
+                r#type: "overloaded_error".to_string(),
+                message: "Overloaded".to_string(),
+            }
+        );
+        // HTTP のエラーレスポンスと同じ変種になる
+        let err = event.to_error().unwrap();
+        assert!(matches!(err, AskError::Overloaded { ref message, .. } if message == "Overloaded"));
+
+        let event = StreamEvent::Error {
+            r#type: "new_error".to_string(),
+            message: "Something".to_string(),
+        };
+        assert_eq!(
+            event.to_error().unwrap().to_string(),
+            "Streaming error: new_error: Something"
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_events() {
+        let ping = parse_event(r#"{"type":"ping"}"#).unwrap().unwrap();
+        assert_eq!(ping, StreamEvent::Ping);
+
+        let data = r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"hmm"}}"#;
+        let event = parse_event(data).unwrap().unwrap();
+        assert!(matches!(event, StreamEvent::Unknown(_)));
+        // 受け取った JSON のまま書き出せる
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::from_str::<serde_json::Value>(data).unwrap()
+        );
+
+        // 既知のイベントで必要な項目が欠けている場合はエラー
+        assert!(parse_event(r#"{"type":"content_block_stop"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_text_stream_adapter() {
+        let events = [
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":2}}"#,
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        ]
+        .map(|data| Ok(parse_event(data).unwrap().unwrap()));
+
+        let items: Vec<_> = text_stream(tokio_stream::iter(events)).collect().await;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), "Hi");
+        assert!(matches!(items[1], Err(AskError::Overloaded { .. })));
+    }
+
+    #[test]
+    fn test_accumulate_events() {
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","content":[],"model":"claude-test","stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":12,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":", world"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":7}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+
+        let mut accumulator = ResponseAccumulator::new("requested-model");
+        for data in events {
+            accumulator
+                .push(&parse_event(data).unwrap().unwrap())
+                .unwrap();
+        }
+
+        let response = accumulator.finish();
+        assert_eq!(response.id, "msg_1");
+        assert_eq!(response.model, "claude-test");
+        assert_eq!(response.text(), "Hello, world");
+        assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+        assert_eq!(response.usage.input_tokens, 12);
+        assert_eq!(response.usage.output_tokens, 7);
+    }
+
+    #[test]
+    fn test_accumulate_tool_use() {
+        let events = [
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"toolu_1","name":"add","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"a\": 2, "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"\"b\": 3}"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use","stop_sequence":null},"usage":{"output_tokens":9}}"#,
+        ];
+
+        let mut accumulator = ResponseAccumulator::new("claude-test");
+        for data in events {
+            accumulator
+                .push(&parse_event(data).unwrap().unwrap())
+                .unwrap();
+        }
+
+        let response = accumulator.finish();
+        assert!(response.wants_tools());
+        let uses: Vec<_> = response.tool_uses().collect();
+        assert_eq!(uses.len(), 1);
+        assert_eq!(uses[0].1, "add");
+        assert_eq!(uses[0].2, &serde_json::json!({"a": 2, "b": 3}));
+    }
+
+    #[test]
+    fn test_accumulate_invalid_tool_input() {
+        let start = r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"toolu_1","name":"add","input":{}}}"#;
+        let stop = r#"{"type":"content_block_stop","index":0}"#;
+
+        // 断片が空なら、開始時の入力のまま
+        let mut accumulator = ResponseAccumulator::new("claude-test");
+        for data in [
+            start,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":""}}"#,
+            stop,
+        ] {
+            accumulator
+                .push(&parse_event(data).unwrap().unwrap())
+                .unwrap();
+        }
+        let response = accumulator.finish();
+        assert_eq!(
+            response.tool_uses().next().unwrap().2,
+            &serde_json::json!({})
+        );
+
+        // 途中で切れた JSON は空の入力として扱わず、エラーにする
+        let mut accumulator = ResponseAccumulator::new("claude-test");
+        for data in [
+            start,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"a\": "}}"#,
+        ] {
+            accumulator
+                .push(&parse_event(data).unwrap().unwrap())
+                .unwrap();
+        }
+        let result = accumulator.push(&parse_event(stop).unwrap().unwrap());
+        assert!(matches!(result, Err(AskError::StreamError(message)) if message.contains("add")));
+    }
+
+    /// SSE の本文を1バイトずつ返すストリーム
+    fn byte_at_a_time(body: &str) -> impl Stream<Item = Result<Vec<u8>>> + Unpin {
+        tokio_stream::iter(
+            body.as_bytes()
+                .iter()
+                .map(|&byte| Ok(vec![byte]))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn sse_body(deltas: &[&str]) -> String {
+        let mut body = String::from(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"type\":\"message\",\"role\":\"assistant\",\"content\":[],\"model\":\"claude-test\",\"stop_reason\":null,\"stop_sequence\":null,\"usage\":{\"input_tokens\":5,\"output_tokens\":1}}}\n\n",
+        );
+        for delta in deltas {
+            let event = serde_json::json!({
+                "type": "content_block_delta",
+                "index": 0,
+                "delta": {"type": "text_delta", "text": delta}
+            });
+            body.push_str(&format!("event: content_block_delta\ndata: {}\n\n", event));
+        }
+        body.push_str("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n");
+        body
+    }
+
+    #[tokio::test]
+    async fn test_multibyte_text_split_across_chunks() {
+        let deltas = ["こんにちは", "、世界！", "🌏🦀", "日本語のテキスト"];
+        let body = sse_body(&deltas);
+
+        let texts: Vec<String> = Box::pin(text_stream(event_stream(byte_at_a_time(&body))))
+            .map(|text| text.unwrap())
+            .collect()
+            .await;
+        assert_eq!(texts, deltas);
+        assert!(!texts.concat().contains('\u{FFFD}'));
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_with_uneven_chunks() {
+        let body = sse_body(&["絵文字👍🏽と", "かな"]);
+        let bytes = body.as_bytes();
+        // 3バイトと5バイトのチャンクを交互に送り、文字の途中で切れるようにする
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        for size in [3, 5].iter().cycle() {
+            if start >= bytes.len() {
+                break;
+            }
+            let end = (start + size).min(bytes.len());
+            chunks.push(Ok::<_, AskError>(bytes[start..end].to_vec()));
+            start = end;
+        }
+
+        let mut accumulator = ResponseAccumulator::new("claude-test");
+        let mut events = Box::pin(event_stream(tokio_stream::iter(chunks)));
+        while let Some(event) = events.next().await {
+            accumulator.push(&event.unwrap()).unwrap();
+        }
+        assert_eq!(accumulator.text(), "絵文字👍🏽とかな");
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_reports_malformed_events() {
+        let body = "event: content_block_delta\r\ndata: {\"type\":\"content_block_delta\",\r\ndata: \"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\r\n\r\n\
+                    : comment\r\ndata: {\"type\":\r\n\r\n";
+        let mut events = Box::pin(event_stream(byte_at_a_time(body)));
+
+        // 複数行の data は改行で連結してから JSON として読む
+        let first = events.next().await.unwrap().unwrap();
+        assert_eq!(first.text_delta(), Some("Hi"));
+        assert!(matches!(
+            events.next().await.unwrap(),
+            Err(AskError::StreamError(_))
+        ));
+        assert!(events.next().await.is_none());
     }
 }
@@ -0,0 +1,254 @@
+use crate::client::models::{ChatResponse, ContentBlock, Message, ToolDefinition, Usage};
+use crate::error::Result;
+use futures::future::BoxFuture;
+use serde_json::Value;
+
+/// ツールループの繰り返し回数の既定の上限
+pub const DEFAULT_MAX_TOOL_ITERATIONS: usize = 10;
+
+/// モデルから呼び出せるツール
+///
+/// ライブラリの利用者が実装し、[`ToolSet`] に登録して使う。
+///
+/// ```
+/// use ask::client::{Tool, ToolOutput};
+/// use futures::future::BoxFuture;
+/// use serde_json::{json, Value};
+///
+/// struct Clock;
+///
+/// impl Tool for Clock {
+///     fn name(&self) -> &str {
+///         "current_time"
+///     }
+///
+///     fn description(&self) -> &str {
+///         "Returns the current time in UTC"
+///     }
+///
+///     fn input_schema(&self) -> Value {
+///         json!({"type": "object", "properties": {}})
+///     }
+///
+///     fn call(&self, _input: Value) -> BoxFuture<'_, ask::Result<ToolOutput>> {
+///         Box::pin(async { Ok(ToolOutput::text(chrono::Utc::now().to_rfc3339())) })
+///     }
+/// }
+/// ```
+pub trait Tool: Send + Sync {
+    /// モデルに見せるツール名
+    fn name(&self) -> &str;
+
+    /// ツールの説明（モデルがいつ使うかを判断する材料になる）
+    fn description(&self) -> &str;
+
+    /// 入力の JSON Schema
+    fn input_schema(&self) -> Value;
+
+    /// ツールを実行する
+    ///
+    /// `Err` を返した場合は、そのメッセージがエラーの結果としてモデルに返される。
+    fn call(&self, input: Value) -> BoxFuture<'_, Result<ToolOutput>>;
+
+    /// API に送るツールの定義
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: Some(self.description().to_string()),
+            input_schema: self.input_schema(),
+        }
+    }
+}
+
+/// ツールの実行結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolOutput {
+    pub content: String,
+    pub is_error: bool,
+}
+
+impl ToolOutput {
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            is_error: false,
+        }
+    }
+
+    /// モデルにエラーとして伝える結果
+    pub fn error(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            is_error: true,
+        }
+    }
+}
+
+/// 1回のツール呼び出しの記録
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: Value,
+    pub output: ToolOutput,
+}
+
+impl ToolCall {
+    /// モデルに返す `tool_result` ブロック
+    pub fn result_block(&self) -> ContentBlock {
+        ContentBlock::tool_result(
+            self.id.clone(),
+            self.output.content.clone(),
+            self.output.is_error,
+        )
+    }
+}
+
+/// ツールループの途中経過（進行状況の表示や記録に使う）
+#[derive(Debug, Clone, Copy)]
+pub enum ToolLoopEvent<'a> {
+    /// モデルから応答を受け取った
+    Response(&'a ChatResponse),
+    /// ツールを実行した
+    ToolCall(&'a ToolCall),
+}
+
+/// ツールループの結果
+#[derive(Debug, Clone)]
+pub struct ToolRun {
+    /// ループの中で追加されたメッセージ（アシスタントの応答とツールの結果）
+    pub messages: Vec<Message>,
+    /// 最後に受け取った応答
+    pub response: ChatResponse,
+    /// 実行したツール呼び出し（実行順）
+    pub calls: Vec<ToolCall>,
+    /// API を呼び出した回数
+    pub iterations: usize,
+    /// すべての呼び出しのトークン数の合計
+    pub usage: Usage,
+    /// 上限に達したため、モデルがまだツールを使おうとしている状態で止めたかどうか
+    pub limit_reached: bool,
+}
+
+impl ToolRun {
+    /// 最後の応答のテキスト
+    pub fn text(&self) -> String {
+        self.response.text()
+    }
+}
+
+/// 登録されたツールの集まり
+#[derive(Default)]
+pub struct ToolSet {
+    tools: Vec<Box<dyn Tool>>,
+}
+
+impl ToolSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ツールを追加する（同じ名前のツールは置き換える）
+    pub fn with(mut self, tool: impl Tool + 'static) -> Self {
+        self.register(Box::new(tool));
+        self
+    }
+
+    /// ツールを追加する（同じ名前のツールは置き換える）
+    pub fn register(&mut self, tool: Box<dyn Tool>) {
+        self.tools.retain(|existing| existing.name() != tool.name());
+        self.tools.push(tool);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Tool> {
+        self.tools
+            .iter()
+            .find(|tool| tool.name() == name)
+            .map(|tool| tool.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    /// API に送るツールの定義の一覧
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|tool| tool.definition()).collect()
+    }
+
+    /// ツール呼び出しを該当するツールに渡して実行する
+    ///
+    /// 未登録のツールやツールのエラーは、エラーの結果としてモデルに返す。
+    pub async fn dispatch(&self, id: &str, name: &str, input: &Value) -> ToolCall {
+        let output = match self.get(name) {
+            Some(tool) => match tool.call(input.clone()).await {
+                Ok(output) => output,
+                Err(e) => ToolOutput::error(e.to_string()),
+            },
+            None => ToolOutput::error(format!("Unknown tool: {}", name)),
+        };
+
+        ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            input: input.clone(),
+            output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AskError;
+    use serde_json::json;
+
+    struct Echo;
+
+    impl Tool for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echoes the text back"
+        }
+
+        fn input_schema(&self) -> Value {
+            json!({"type": "object", "properties": {"text": {"type": "string"}}})
+        }
+
+        fn call(&self, input: Value) -> BoxFuture<'_, Result<ToolOutput>> {
+            Box::pin(async move {
+                match input["text"].as_str() {
+                    Some(text) => Ok(ToolOutput::text(text)),
+                    None => Err(AskError::InvalidInput("text is required".to_string())),
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let tools = ToolSet::new().with(Echo);
+        assert_eq!(tools.definitions()[0].name, "echo");
+
+        let call = tools.dispatch("t1", "echo", &json!({"text": "hi"})).await;
+        assert_eq!(call.output, ToolOutput::text("hi"));
+
+        let call = tools.dispatch("t2", "echo", &json!({})).await;
+        assert!(call.output.is_error);
+        assert!(call.output.content.contains("text is required"));
+
+        let call = tools.dispatch("t3", "missing", &json!({})).await;
+        assert_eq!(call.output, ToolOutput::error("Unknown tool: missing"));
+        assert_eq!(
+            call.result_block(),
+            ContentBlock::tool_result("t3", "Unknown tool: missing", true)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::client::retry::RetryPolicy;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -6,6 +7,8 @@ pub struct Settings {
     pub output: OutputConfig,
     pub conversation: ConversationConfig,
     pub template: TemplateConfig,
+    #[serde(default)]
+    pub agent: AgentConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -15,6 +18,22 @@ pub struct ApiConfig {
     pub max_tokens: u32,
     pub stream: bool,
     pub temperature: f32,
+    /// 新しい会話で使うデフォルトのシステムプロンプト
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// 生成を止める文字列
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    /// リクエストの `metadata.user_id` に付ける ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// 失敗したリクエストを再試行する方針
+    #[serde(default)]
+    pub retry: RetryPolicy,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -36,6 +55,22 @@ pub struct ConversationConfig {
 pub struct TemplateConfig {
     pub default_template_dir: String,
     pub auto_load: bool,
+    /// 追加のテンプレートディレクトリ（チームで共有するディレクトリなど）。先にあるものが優先される
+    #[serde(default)]
+    pub search_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AgentConfig {
+    /// `run_command` で実行できるコマンド（プログラム名）
+    pub allowed_commands: Vec<String>,
+    /// ツールループの繰り返し回数の上限
+    pub max_iterations: usize,
+    /// コマンドのタイムアウト（秒）
+    pub command_timeout: u64,
+    /// ツールの出力としてモデルに返す最大バイト数
+    pub max_output_bytes: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -63,6 +98,12 @@ impl Default for ApiConfig {
             max_tokens: 4096,
             stream: true,
             temperature: 0.7,
+            system: None,
+            top_p: None,
+            top_k: None,
+            stop_sequences: Vec::new(),
+            user_id: None,
+            retry: RetryPolicy::default(),
         }
     }
 }
@@ -102,6 +143,21 @@ impl Default for TemplateConfig {
         Self {
             default_template_dir: home_dir,
             auto_load: true,
+            search_paths: Vec::new(),
+        }
+    }
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            allowed_commands: ["ls", "cat", "head", "tail", "wc", "grep"]
+                .iter()
+                .map(|command| command.to_string())
+                .collect(),
+            max_iterations: 20,
+            command_timeout: 60,
+            max_output_bytes: 64 * 1024,
         }
     }
 }
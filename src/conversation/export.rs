@@ -0,0 +1,98 @@
+use crate::client::models::Conversation;
+use crate::error::Result;
+
+/// 会話のエクスポート形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+    Text,
+}
+
+impl ExportFormat {
+    /// ファイル拡張子
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Text => "txt",
+        }
+    }
+
+    /// 拡張子から形式を推測する
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "txt" | "text" => Some(ExportFormat::Text),
+            _ => None,
+        }
+    }
+}
+
+/// 会話を指定した形式の文字列に変換する
+pub fn export_conversation(conversation: &Conversation, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(conversation)?),
+        ExportFormat::Markdown => Ok(to_markdown(conversation)),
+        ExportFormat::Text => Ok(to_text(conversation)),
+    }
+}
+
+fn to_markdown(conversation: &Conversation) -> String {
+    let mut output = format!("# {}\n\n", conversation.title);
+    output.push_str(&format!("- **ID**: `{}`\n", conversation.id));
+    output.push_str(&format!(
+        "- **Created**: {}\n",
+        conversation.created_at.to_rfc3339()
+    ));
+    output.push_str(&format!(
+        "- **Updated**: {}\n",
+        conversation.updated_at.to_rfc3339()
+    ));
+
+    if let Some(ref system) = conversation.system {
+        output.push_str(&format!("\n## System\n\n{}\n", system));
+    }
+
+    for message in &conversation.messages {
+        output.push_str(&format!(
+            "\n## {}\n\n{}\n",
+            role_label(&message.role),
+            message.content
+        ));
+    }
+
+    output
+}
+
+fn to_text(conversation: &Conversation) -> String {
+    let mut output = format!("{}\n", conversation.title);
+    output.push_str(&format!("ID: {}\n", conversation.id));
+    output.push_str(&format!(
+        "Created: {}\n",
+        conversation.created_at.to_rfc3339()
+    ));
+
+    if let Some(ref system) = conversation.system {
+        output.push_str(&format!("\nSystem:\n{}\n", system));
+    }
+
+    for message in &conversation.messages {
+        output.push_str(&format!(
+            "\n{}:\n{}\n",
+            role_label(&message.role),
+            message.content
+        ));
+    }
+
+    output
+}
+
+fn role_label(role: &str) -> &str {
+    match role {
+        "user" => "You",
+        "assistant" => "Claude",
+        other => other,
+    }
+}
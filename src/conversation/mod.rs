@@ -0,0 +1,39 @@
+pub mod export;
+pub mod store;
+
+pub use export::*;
+pub use store::*;
+
+use crate::client::models::Conversation;
+use crate::config::ConversationConfig;
+use crate::error::Result;
+
+/// 会話を履歴に保存し、上限を超えた古い会話を削除する
+pub fn save_to_history(conversation: &Conversation, config: &ConversationConfig) -> Result<()> {
+    let store = ConversationStore::open_default()?;
+    store.save(conversation)?;
+    store.prune(config.max_history_entries)?;
+    Ok(())
+}
+
+/// 最初のメッセージから会話タイトルを生成する
+pub fn generate_title(message: &str) -> String {
+    const MAX_TITLE_CHARS: usize = 50;
+
+    let first_line = message
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+
+    if first_line.is_empty() {
+        return "Untitled".to_string();
+    }
+
+    if first_line.chars().count() > MAX_TITLE_CHARS {
+        let truncated: String = first_line.chars().take(MAX_TITLE_CHARS).collect();
+        format!("{}...", truncated)
+    } else {
+        first_line.to_string()
+    }
+}
@@ -0,0 +1,299 @@
+use crate::client::models::{Conversation, Message, MessageContent};
+use crate::error::{AskError, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+
+/// スキーマのマイグレーション（順番に適用される）
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS conversations (
+         id TEXT PRIMARY KEY,
+         title TEXT NOT NULL,
+         created_at TEXT NOT NULL,
+         updated_at TEXT NOT NULL
+     );
+     CREATE TABLE IF NOT EXISTS messages (
+         id INTEGER PRIMARY KEY AUTOINCREMENT,
+         conversation_id TEXT NOT NULL
+             REFERENCES conversations(id) ON DELETE CASCADE,
+         position INTEGER NOT NULL,
+         role TEXT NOT NULL,
+         content TEXT NOT NULL
+     );
+     CREATE INDEX IF NOT EXISTS idx_messages_conversation
+         ON messages(conversation_id, position);",
+    "ALTER TABLE conversations ADD COLUMN system TEXT;",
+    // 添付ファイルを含むメッセージはブロックの JSON を保存し、content にはテキスト部分を残す
+    "ALTER TABLE messages ADD COLUMN blocks TEXT;",
+];
+
+/// 会話一覧表示用の要約
+#[derive(Debug, Clone)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub message_count: usize,
+}
+
+/// SQLiteに会話履歴を保存するストア
+pub struct ConversationStore {
+    conn: Connection,
+}
+
+impl ConversationStore {
+    /// 指定したパスのデータベースを開く（存在しなければ作成する）
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)?;
+        Self::with_connection(conn)
+    }
+
+    /// デフォルトの場所にあるデータベースを開く
+    pub fn open_default() -> Result<Self> {
+        Self::open(Self::default_path()?)
+    }
+
+    /// メモリ上のデータベースを開く（テスト用）
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    /// 履歴データベースのデフォルトパスを取得する
+    pub fn default_path() -> Result<PathBuf> {
+        directories::ProjectDirs::from("ltd", "engineers-hub", "ask")
+            .map(|dirs| dirs.data_dir().join("history.db"))
+            .ok_or_else(|| AskError::ConfigError("Could not determine data directory".to_string()))
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+
+        // スキーマは PRAGMA user_version で管理し、未適用のマイグレーションだけを順に実行する
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            conn.execute_batch(migration)?;
+            conn.execute_batch(&format!("PRAGMA user_version = {}", index + 1))?;
+        }
+
+        Ok(Self { conn })
+    }
+
+    /// 会話を保存する（既存の会話は上書きする）
+    pub fn save(&self, conversation: &Conversation) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        tx.execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at, system)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(id) DO UPDATE SET
+                 title = excluded.title,
+                 updated_at = excluded.updated_at,
+                 system = excluded.system",
+            params![
+                conversation.id,
+                conversation.title,
+                format_timestamp(&conversation.created_at),
+                format_timestamp(&conversation.updated_at),
+                conversation.system,
+            ],
+        )?;
+
+        tx.execute(
+            "DELETE FROM messages WHERE conversation_id = ?1",
+            params![conversation.id],
+        )?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO messages (conversation_id, position, role, content, blocks)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (position, message) in conversation.messages.iter().enumerate() {
+                let blocks = match message.content {
+                    MessageContent::Text(_) => None,
+                    MessageContent::Blocks(ref blocks) => Some(serde_json::to_string(blocks)?),
+                };
+                stmt.execute(params![
+                    conversation.id,
+                    position as i64,
+                    message.role,
+                    message.content.text(),
+                    blocks,
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// IDまたはIDの前方一致で会話を読み込む
+    pub fn load(&self, id: &str) -> Result<Option<Conversation>> {
+        let Some(full_id) = self.resolve_id(id)? else {
+            return Ok(None);
+        };
+
+        let conversation = self
+            .conn
+            .query_row(
+                "SELECT id, title, created_at, updated_at, system
+                 FROM conversations WHERE id = ?1",
+                params![full_id],
+                |row| {
+                    Ok(Conversation {
+                        id: row.get(0)?,
+                        title: row.get(1)?,
+                        created_at: parse_timestamp(row, 2)?,
+                        updated_at: parse_timestamp(row, 3)?,
+                        system: row.get(4)?,
+                        messages: Vec::new(),
+                    })
+                },
+            )
+            .optional()?;
+
+        let Some(mut conversation) = conversation else {
+            return Ok(None);
+        };
+
+        let mut stmt = self.conn.prepare(
+            "SELECT role, content, blocks FROM messages
+             WHERE conversation_id = ?1 ORDER BY position",
+        )?;
+        conversation.messages = stmt
+            .query_map(params![conversation.id], |row| {
+                let content = match row.get::<_, Option<String>>(2)? {
+                    Some(blocks) => {
+                        MessageContent::Blocks(serde_json::from_str(&blocks).map_err(|e| {
+                            rusqlite::Error::FromSqlConversionFailure(
+                                2,
+                                rusqlite::types::Type::Text,
+                                Box::new(e),
+                            )
+                        })?)
+                    }
+                    None => MessageContent::Text(row.get(1)?),
+                };
+                Ok(Message {
+                    role: row.get(0)?,
+                    content,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(Some(conversation))
+    }
+
+    /// 最近更新された順に会話の一覧を取得する
+    pub fn list(&self, limit: usize) -> Result<Vec<ConversationSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.title, c.created_at, c.updated_at,
+                    (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)
+             FROM conversations c
+             ORDER BY c.updated_at DESC, c.rowid DESC
+             LIMIT ?1",
+        )?;
+
+        let summaries = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(ConversationSummary {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    created_at: parse_timestamp(row, 2)?,
+                    updated_at: parse_timestamp(row, 3)?,
+                    message_count: row.get::<_, i64>(4)? as usize,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(summaries)
+    }
+
+    /// 会話を削除する（削除した場合は true を返す）
+    pub fn delete(&self, id: &str) -> Result<bool> {
+        let Some(full_id) = self.resolve_id(id)? else {
+            return Ok(false);
+        };
+
+        let deleted = self
+            .conn
+            .execute("DELETE FROM conversations WHERE id = ?1", params![full_id])?;
+        Ok(deleted > 0)
+    }
+
+    /// すべての会話を削除し、削除した件数を返す
+    pub fn clear(&self) -> Result<usize> {
+        let deleted = self.conn.execute("DELETE FROM conversations", [])?;
+        Ok(deleted)
+    }
+
+    /// 保存件数が上限を超えた分を古い順に削除する
+    pub fn prune(&self, max_entries: usize) -> Result<usize> {
+        let deleted = self.conn.execute(
+            "DELETE FROM conversations WHERE id NOT IN (
+                 SELECT id FROM conversations ORDER BY updated_at DESC, rowid DESC LIMIT ?1
+             )",
+            params![max_entries as i64],
+        )?;
+        Ok(deleted)
+    }
+
+    /// 完全なID、または一意に定まる前方一致からIDを解決する
+    fn resolve_id(&self, id: &str) -> Result<Option<String>> {
+        let exact: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT id FROM conversations WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if exact.is_some() {
+            return Ok(exact);
+        }
+
+        let escaped = id
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM conversations WHERE id LIKE ?1 || '%' ESCAPE '\\' LIMIT 2")?;
+        let matches = stmt
+            .query_map(params![escaped], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        match matches.len() {
+            0 => Ok(None),
+            1 => Ok(matches.into_iter().next()),
+            _ => Err(AskError::InvalidInput(format!(
+                "Conversation ID prefix '{}' is ambiguous",
+                id
+            ))),
+        }
+    }
+}
+
+/// 文字列比較で時系列順になるよう、固定桁のUTC形式で保存する
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+fn parse_timestamp(row: &rusqlite::Row<'_>, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let value: String = row.get(index)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            )
+        })
+}
@@ -38,8 +38,74 @@ pub enum AskError {
     #[error("Streaming error: {0}")]
     StreamError(String),
 
+    #[error("Line editor error: {0}")]
+    ReadlineError(#[from] rustyline::error::ReadlineError),
+
     #[error("Template error: {0}")]
     TemplateError(String),
+
+    #[error("Attachment error: {0}")]
+    AttachmentError(String),
+
+    #[error("Tool error: {0}")]
+    ToolError(String),
+
+    /// `invalid_request_error`（パラメータやメッセージの形式の誤り、プロンプトが長すぎるなど）
+    #[error("Invalid request: {message}")]
+    InvalidRequest {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    /// `permission_error`（API キーにそのリソースを使う権限がない）
+    #[error("Permission denied: {message}")]
+    PermissionDenied {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    /// `not_found_error`（存在しないモデルなど）
+    #[error("Not found: {message}")]
+    NotFound {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    /// `request_too_large`（リクエストのサイズが上限を超えている）
+    #[error("Request too large: {message}")]
+    RequestTooLarge {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    /// `overloaded_error`（API が一時的に過負荷になっている）
+    #[error("API overloaded: {message}")]
+    Overloaded {
+        message: String,
+        request_id: Option<String>,
+    },
+
+    /// `api_error`（API 側の予期しないエラー）
+    #[error("API server error: {message}")]
+    ApiServerError {
+        message: String,
+        request_id: Option<String>,
+    },
+}
+
+impl AskError {
+    /// API のエラーに付いていた `request-id`（問い合わせの際に使う）
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            AskError::InvalidRequest { request_id, .. }
+            | AskError::PermissionDenied { request_id, .. }
+            | AskError::NotFound { request_id, .. }
+            | AskError::RequestTooLarge { request_id, .. }
+            | AskError::Overloaded { request_id, .. }
+            | AskError::ApiServerError { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, AskError>;
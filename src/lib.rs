@@ -37,21 +37,22 @@
 //! }
 //! ```
 
+pub mod agent;
 pub mod cli;
 pub mod client;
 pub mod config;
+pub mod conversation;
 pub mod error;
-
-// 基本的なアプリケーション機能を実装していく予定
-// pub mod conversation;
-// pub mod template;
-// pub mod output;
+pub mod output;
+pub mod template;
 
 // Public API exports
 pub use cli::{Cli, CommandHandler};
 pub use client::{ClaudeClient, Conversation, Message};
 pub use config::{ConfigManager, Settings};
+pub use conversation::ConversationStore;
 pub use error::{AskError, Result};
+pub use template::TemplateStore;
 
 /// askライブラリのバージョン
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -63,12 +64,19 @@ pub const NAME: &str = env!("CARGO_PKG_NAME");
 pub async fn run_app() -> Result<()> {
     use clap::Parser;
 
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
     let command_handler = CommandHandler::new();
     let config_manager = ConfigManager::new();
 
+    // 色の設定はエラー表示を含むすべての出力に反映する
+    let config_color = config_manager
+        .load_settings()
+        .map(|settings| settings.output.color)
+        .unwrap_or(true);
+    output::init_color(cli.no_color, config_color);
+
     // サブコマンドの処理
-    if let Some(command) = cli.command {
+    if let Some(command) = cli.command.take() {
         match command {
             cli::Commands::Config { action } => {
                 return command_handler.handle_config_command(action).await;
@@ -76,24 +84,93 @@ pub async fn run_app() -> Result<()> {
             cli::Commands::History { action } => {
                 return command_handler.handle_history_command(action).await;
             }
+            cli::Commands::Template {
+                action:
+                    cli::TemplateAction::Use {
+                        name,
+                        var,
+                        allow_exec,
+                    },
+            } => {
+                // 展開したテンプレートを通常のメッセージとして送信する
+                // （フロントマターの指定は設定より優先し、CLIの指定はさらに優先する）
+                let (template, message) =
+                    command_handler.render_template(&name, &var, allow_exec)?;
+                let mut settings = config_manager.load_settings()?;
+                template.metadata.apply_to(&mut settings.api);
+                let system = get_system_prompt(&cli)
+                    .await?
+                    .or_else(|| template.metadata.system.clone());
+                return run_single_message(
+                    &cli,
+                    &config_manager,
+                    &settings,
+                    &message,
+                    system.as_deref(),
+                )
+                .await;
+            }
             cli::Commands::Template { action } => {
                 return command_handler.handle_template_command(action).await;
             }
+            cli::Commands::Agent {
+                task,
+                root,
+                allow,
+                yes,
+                max_iterations,
+                replay,
+            } => {
+                if let Some(id) = replay {
+                    let conversation =
+                        ConversationStore::open_default()?
+                            .load(&id)?
+                            .ok_or_else(|| {
+                                AskError::InvalidInput(format!("Conversation not found: {}", id))
+                            })?;
+                    agent::print_replay(&conversation);
+                    return Ok(());
+                }
+
+                let settings = config_manager.load_settings()?;
+                let mut agent_config = settings.agent.clone();
+                agent_config.allowed_commands.extend(allow);
+                if let Some(max_iterations) = max_iterations {
+                    agent_config.max_iterations = max_iterations;
+                }
+                return run_agent(
+                    &cli,
+                    &config_manager,
+                    &settings,
+                    agent_config,
+                    &task.unwrap_or_default(),
+                    root.as_deref().unwrap_or("."),
+                    yes,
+                )
+                .await;
+            }
         }
     }
 
     // メイン機能: メッセージの処理
     let message = get_input_message(&cli).await?;
 
-    if message.is_empty() {
-        return Err(AskError::InvalidInput("No message provided".to_string()));
-    }
-
-    // 対話モードかどうかをチェック
+    // 対話モードかどうかをチェック（対話モードではメッセージは省略可能）
     if cli.interactive {
-        run_interactive_mode(&cli, &config_manager).await?;
+        run_interactive_mode(&cli, &config_manager, message).await?;
+    } else if message.is_empty() {
+        return Err(AskError::InvalidInput("No message provided".to_string()));
     } else {
-        run_single_message(&cli, &config_manager, &message).await?;
+        let settings = config_manager.load_settings()?;
+        let system = get_system_prompt(&cli).await?;
+        run_single_message(
+            &cli,
+            &config_manager,
+            &settings,
+            &message,
+            system.as_deref(),
+        )
+        .await?;
     }
 
     Ok(())
@@ -101,8 +178,6 @@ pub async fn run_app() -> Result<()> {
 
 /// 入力メッセージを取得する
 async fn get_input_message(cli: &Cli) -> Result<String> {
-    use std::io::{self, Read};
-
     if let Some(ref message) = cli.message {
         return Ok(message.clone());
     }
@@ -113,96 +188,480 @@ async fn get_input_message(cli: &Cli) -> Result<String> {
     }
 
     // パイプからの入力をチェック
-    if atty::isnt(atty::Stream::Stdin) {
-        let mut buffer = String::new();
-        io::stdin().read_to_string(&mut buffer)?;
-        return Ok(buffer);
+    Ok(cli::read_piped_stdin()?.unwrap_or_default())
+}
+
+/// `--system` / `--system-file` で指定されたシステムプロンプトを取得する
+async fn get_system_prompt(cli: &Cli) -> Result<Option<String>> {
+    if let Some(ref system) = cli.system {
+        return Ok(Some(system.clone()));
+    }
+
+    if let Some(ref file_path) = cli.system_file {
+        let content = tokio::fs::read_to_string(file_path).await?;
+        return Ok(Some(content.trim_end().to_string()));
     }
 
-    Ok(String::new())
+    Ok(None)
 }
 
 /// 単一メッセージモードを実行
+///
+/// `system` は明示的に指定されたシステムプロンプト（CLI またはテンプレート）。
+/// 指定が無い場合、継続する会話は保存済みのものを使い、新しい会話は設定のデフォルトを使う。
 async fn run_single_message(
     cli: &Cli,
     config_manager: &ConfigManager,
+    settings: &Settings,
     message: &str,
+    system: Option<&str>,
 ) -> Result<()> {
     use colored::*;
     use std::io::{self, Write};
     use tokio_stream::StreamExt;
 
-    let settings = config_manager.load_settings()?;
-    let api_key = config_manager.get_api_key_with_fallback()?;
-
-    let client = ClaudeClient::new(api_key)?;
-
-    let model = cli.model.as_ref().unwrap_or(&settings.api.model);
-    let max_tokens = cli.max_tokens.unwrap_or(settings.api.max_tokens);
-    let temperature = cli.temperature.or(Some(settings.api.temperature));
-
-    let use_streaming = !cli.no_stream && settings.api.stream;
-
-    if use_streaming {
+    let api = api_config_with_overrides(cli, &settings.api)?;
+    let client = create_client(config_manager, &api, cli.verbose)?;
+    let use_streaming = api.stream;
+
+    // 既存の会話を継続する場合は履歴から読み込む
+    let mut conversation = match cli.conversation {
+        Some(ref id) => ConversationStore::open_default()?
+            .load(id)?
+            .ok_or_else(|| AskError::InvalidInput(format!("Conversation not found: {}", id)))?,
+        None => Conversation::new(if settings.conversation.auto_title {
+            conversation::generate_title(message)
+        } else {
+            "Untitled".to_string()
+        }),
+    };
+    match system {
+        Some(system) => conversation.system = Some(system.to_string()),
+        None if cli.conversation.is_none() => conversation.system = settings.api.system.clone(),
+        None => {}
+    }
+    conversation.add_message(client::user_message_with_attachments(
+        message,
+        &cli.attachments,
+    )?);
+
+    let format = cli
+        .output
+        .clone()
+        .map(config::OutputFormat::from)
+        .unwrap_or_else(|| settings.output.format.clone());
+    let mut renderer = output::renderer_for(&format, output::use_terminal_markdown());
+    let context = output::RenderContext {
+        model: api.model.clone(),
+        title: conversation.title.clone(),
+        conversation_id: settings
+            .conversation
+            .save_history
+            .then(|| conversation.id.clone()),
+        created_at: conversation.updated_at,
+    };
+    let mut stdout = io::stdout();
+
+    // --extract-code はコードブロックだけを出力し、--extract-code-to はファイルに書き出す
+    let mut extraction = (cli.extract_code.is_some() || cli.extract_code_to.is_some()).then(|| {
+        output::CodeExtraction::new(
+            cli.extract_code.as_deref(),
+            cli.extract_code.is_some(),
+            cli.extract_code_to.as_ref().map(std::path::PathBuf::from),
+        )
+    });
+    let show_reply = extraction.as_ref().map_or(true, |code| !code.prints());
+
+    let request = client
+        .messages()
+        .config(&api)
+        .messages(conversation.messages.clone())
+        .system_opt(conversation.system.as_deref());
+
+    let response = if use_streaming {
         // ストリーミングモード
         if cli.verbose {
-            println!("{}", "🤔 Thinking...".yellow());
+            output::on_stderr(|| eprintln!("{}", "🤔 Thinking...".yellow()));
         }
 
-        let stream = client
-            .stream_message(message, model, max_tokens, temperature)
-            .await?;
+        let stream = request.stream().await?;
         let mut pinned_stream = Box::pin(stream);
+        let mut accumulator = client::ResponseAccumulator::new(&api.model);
 
-        while let Some(chunk_result) = pinned_stream.next().await {
-            match chunk_result {
-                Ok(text) => {
-                    print!("{}", text);
-                    io::stdout().flush()?;
-                }
-                Err(e) => {
-                    eprintln!("\n{} {}", "Error:".red(), e);
-                    break;
+        if show_reply {
+            renderer.begin(&mut stdout, &context)?;
+        }
+        while let Some(event_result) = pinned_stream.next().await {
+            let error = match event_result {
+                Ok(event) => {
+                    let invalid = accumulator.push(&event).err();
+                    if let (Some(code), Some(text)) = (extraction.as_mut(), event.text_delta()) {
+                        code.push(&mut stdout, text)?;
+                    }
+                    if show_reply {
+                        renderer.event(&mut stdout, &event)?;
+                    }
+                    invalid.or_else(|| event.to_error())
                 }
+                Err(e) => Some(e),
+            };
+            // 途中で失敗した応答は表示を終えるだけで、履歴には保存しない
+            if let Some(e) = error {
+                writeln!(stdout)?;
+                stdout.flush()?;
+                return Err(e);
             }
         }
-        println!(); // 改行
+
+        let response = accumulator.finish();
+        if show_reply {
+            renderer.finish(&mut stdout, &response, &context, true)?;
+        }
+        response
     } else {
         // 非ストリーミングモード
         if cli.verbose {
-            println!("{}", "🤔 Processing request...".yellow());
+            output::on_stderr(|| eprintln!("{}", "🤔 Processing request...".yellow()));
+        }
+
+        let response = request.send().await?;
+        // 応答全体が揃っているため、設定に応じてページャーで表示する
+        if show_reply {
+            let mut rendered = Vec::new();
+            renderer.begin(&mut rendered, &context)?;
+            renderer.finish(&mut rendered, &response, &context, false)?;
+            output::page(&String::from_utf8_lossy(&rendered), settings.output.pager)?;
         }
+        if let Some(ref mut code) = extraction {
+            code.push(&mut stdout, &response.text())?;
+        }
+        response
+    };
+    let response_text = response.text();
+
+    if cli.verbose {
+        print_response_details(&response);
+    }
 
-        let response = client
-            .send_message(message, model, max_tokens, temperature)
-            .await?;
-        println!("{}", response);
+    if let Some(ref mut code) = extraction {
+        code.finish(&mut stdout)?;
+        if code.matched() == 0 {
+            output::on_stderr(|| {
+                eprintln!(
+                    "{}",
+                    "⚠️  No matching code blocks found in the reply".yellow()
+                )
+            });
+        }
+        for path in code.written() {
+            output::on_stderr(|| {
+                eprintln!("{} {}", "💾 Saved code block to".cyan(), path.display())
+            });
+        }
+    }
+
+    if settings.conversation.save_history && !response_text.is_empty() {
+        conversation.add_message(Message::assistant(response_text));
+        conversation::save_to_history(&conversation, &settings.conversation)?;
+
+        if cli.verbose {
+            output::on_stderr(|| {
+                eprintln!(
+                    "{} {}",
+                    "💾 Saved as conversation:".cyan(),
+                    conversation.id.yellow()
+                )
+            });
+        }
     }
 
     Ok(())
 }
 
-/// 対話モードを実行
-async fn run_interactive_mode(_cli: &Cli, _config_manager: &ConfigManager) -> Result<()> {
+/// エージェントを実行する
+///
+/// ツールの呼び出しと結果はすべて会話として履歴に保存し、`ask agent --replay` で確認できる。
+async fn run_agent(
+    cli: &Cli,
+    config_manager: &ConfigManager,
+    settings: &Settings,
+    agent_config: config::AgentConfig,
+    task: &str,
+    root: &str,
+    auto_approve: bool,
+) -> Result<()> {
     use colored::*;
 
-    println!("{}", "🎯 Interactive mode starting...".green().bold());
-    println!(
-        "{}",
-        "Type 'exit' or 'quit' to end the conversation.".yellow()
-    );
-    println!("{}", "Type 'help' for available commands.".yellow());
-    println!();
+    if task.trim().is_empty() {
+        return Err(AskError::InvalidInput("No task provided".to_string()));
+    }
 
-    // 対話モードの実装は後で行う
-    println!(
-        "{}",
-        "(Interactive mode will be fully implemented)".yellow()
-    );
+    let api = api_config_with_overrides(cli, &settings.api)?;
+    let client = create_client(config_manager, &api, cli.verbose)?;
+
+    let sandbox = agent::Sandbox::new(root)?;
+    let approver: std::sync::Arc<dyn agent::Approver> = if auto_approve {
+        std::sync::Arc::new(agent::AutoApprover(true))
+    } else {
+        std::sync::Arc::new(agent::PromptApprover)
+    };
+    let max_iterations = agent_config.max_iterations;
+    let tools = agent::local_tools(std::sync::Arc::new(agent::ToolContext {
+        sandbox: sandbox.clone(),
+        config: agent_config,
+        approver,
+        // コマンドは --yes でも1つずつ確認する
+        command_approver: std::sync::Arc::new(agent::PromptApprover),
+    }));
+
+    let mut conversation =
+        Conversation::new(format!("Agent: {}", conversation::generate_title(task)));
+    conversation.system = Some(match get_system_prompt(cli).await? {
+        Some(system) => format!("{}\n\n{}", agent::AGENT_SYSTEM_PROMPT, system),
+        None => agent::AGENT_SYSTEM_PROMPT.to_string(),
+    });
+    conversation.add_message(Message::user(task));
+
+    let store = settings
+        .conversation
+        .save_history
+        .then(ConversationStore::open_default)
+        .transpose()?;
+    let mut log = agent::SessionLog::new(conversation, store)?;
+
+    output::on_stderr(|| {
+        eprintln!(
+            "{} {}",
+            "🤖 Agent working in".cyan(),
+            sandbox.root().display().to_string().bold()
+        )
+    });
+
+    let request = client
+        .messages()
+        .config(&api)
+        .messages(log.conversation().messages.clone())
+        .system_opt(log.conversation().system.as_deref());
+
+    let mut save_error = None;
+    let result = request
+        .run_tools_with(&tools, max_iterations, |event| {
+            match event {
+                client::ToolLoopEvent::Response(response) => {
+                    let text = response.text();
+                    if !text.trim().is_empty() {
+                        println!("{}", text.trim_end());
+                    }
+                    if cli.verbose {
+                        print_response_details(response);
+                    }
+                }
+                client::ToolLoopEvent::ToolCall(call) => {
+                    let marker = if call.output.is_error {
+                        "✗".red()
+                    } else {
+                        "✓".green()
+                    };
+                    let summary = call.output.content.lines().next().unwrap_or("");
+                    output::on_stderr(|| {
+                        eprintln!(
+                            "{} {} {} {}",
+                            "🔧".yellow(),
+                            call.name.bold(),
+                            call.input.to_string().dimmed(),
+                            marker
+                        )
+                    });
+                    if call.output.is_error || cli.verbose {
+                        output::on_stderr(|| eprintln!("   {}", summary.dimmed()));
+                    }
+                }
+            }
+            if let Err(e) = log.record(event) {
+                save_error.get_or_insert(e);
+            }
+        })
+        .await;
+    log.finish()?;
+    if let Some(e) = save_error {
+        return Err(e);
+    }
+    let run = result?;
+
+    if run.limit_reached {
+        output::on_stderr(|| {
+            eprintln!(
+                "{} Stopped after {} model calls; the task may be unfinished",
+                "⚠️ ".yellow(),
+                run.iterations
+            )
+        });
+    }
+    if cli.verbose {
+        output::on_stderr(|| {
+            eprintln!(
+                "{} {} tool calls, {} in / {} out tokens",
+                "📊 Total:".cyan(),
+                run.calls.len(),
+                run.usage.input_tokens,
+                run.usage.output_tokens
+            )
+        });
+    }
+    if settings.conversation.save_history {
+        conversation::save_to_history(log.conversation(), &settings.conversation)?;
+        output::on_stderr(|| {
+            eprintln!(
+                "{} {} (replay with {})",
+                "💾 Saved agent session:".cyan(),
+                log.conversation().id.yellow(),
+                format!("ask agent --replay {}", log.conversation().id).cyan()
+            )
+        });
+    }
 
     Ok(())
 }
 
+/// CLIの指定を反映したAPI設定を作成する（保存済みの設定は変更しない）
+fn api_config_with_overrides(cli: &Cli, base: &config::ApiConfig) -> Result<config::ApiConfig> {
+    let mut api = base.clone();
+    if let Some(ref model) = cli.model {
+        api.model = model.clone();
+    }
+    if let Some(max_tokens) = cli.max_tokens {
+        api.max_tokens = max_tokens;
+    }
+    if let Some(temperature) = cli.temperature {
+        api.temperature = temperature;
+    }
+    if let Some(top_p) = cli.top_p {
+        if !(0.0..=1.0).contains(&top_p) {
+            return Err(AskError::InvalidInput(
+                "top_p must be between 0.0 and 1.0".to_string(),
+            ));
+        }
+        api.top_p = Some(top_p);
+    }
+    if let Some(top_k) = cli.top_k {
+        if top_k == 0 {
+            return Err(AskError::InvalidInput(
+                "top_k must be a positive integer".to_string(),
+            ));
+        }
+        api.top_k = Some(top_k);
+    }
+    if !cli.stop_sequences.is_empty() {
+        api.stop_sequences = cli.stop_sequences.clone();
+    }
+    if let Some(ref user_id) = cli.user_id {
+        api.user_id = Some(user_id.clone());
+    }
+    if cli.no_stream {
+        api.stream = false;
+    }
+    Ok(api)
+}
+
+/// API クライアントを作成する（verbose の場合は再試行までの残り時間を表示する）
+fn create_client(
+    config_manager: &ConfigManager,
+    api: &config::ApiConfig,
+    verbose: bool,
+) -> Result<ClaudeClient> {
+    let api_key = config_manager.get_api_key_with_fallback()?;
+    let client = ClaudeClient::new(api_key)?.with_retry_policy(api.retry.clone());
+    Ok(if verbose {
+        client.on_retry(print_retry_notice)
+    } else {
+        client
+    })
+}
+
+/// 再試行までの残り時間を同じ行に表示する（verbose 用）
+fn print_retry_notice(notice: &client::RetryNotice<'_>) {
+    use colored::*;
+
+    let attempt = format!("(attempt {}/{})", notice.attempt, notice.max_attempts);
+    if notice.remaining.is_zero() {
+        output::on_stderr(|| {
+            eprintln!(
+                "\r{} {} - retrying now {}   ",
+                "⏳".yellow(),
+                notice.reason,
+                attempt
+            )
+        });
+    } else {
+        output::on_stderr(|| {
+            eprint!(
+                "\r{} {} - retrying in {}s {}",
+                "⏳".yellow(),
+                notice.reason,
+                notice.remaining.as_secs_f64().ceil(),
+                attempt
+            )
+        });
+    }
+}
+
+/// 停止理由とトークン数を表示する（verbose 用）
+fn print_response_details(response: &client::ChatResponse) {
+    use colored::*;
+
+    let stop_reason = response.stop_reason.as_deref().unwrap_or("unknown");
+    match response.stop_sequence {
+        Some(ref sequence) => output::on_stderr(|| {
+            eprintln!(
+                "{} {} ({:?})",
+                "🛑 Stop reason:".cyan(),
+                stop_reason,
+                sequence
+            )
+        }),
+        None => output::on_stderr(|| eprintln!("{} {}", "🛑 Stop reason:".cyan(), stop_reason)),
+    }
+    output::on_stderr(|| {
+        eprintln!(
+            "{} {} in / {} out",
+            "📊 Tokens:".cyan(),
+            response.usage.input_tokens,
+            response.usage.output_tokens
+        )
+    });
+}
+
+/// 対話モードを実行
+async fn run_interactive_mode(
+    cli: &Cli,
+    config_manager: &ConfigManager,
+    initial_message: String,
+) -> Result<()> {
+    let settings = config_manager.load_settings()?;
+
+    // CLIの指定はこのセッションの間だけ有効
+    let api = api_config_with_overrides(cli, &settings.api)?;
+    let client = create_client(config_manager, &api, cli.verbose)?;
+
+    let mut conversation = match cli.conversation {
+        Some(ref id) => ConversationStore::open_default()?
+            .load(id)?
+            .ok_or_else(|| AskError::InvalidInput(format!("Conversation not found: {}", id)))?,
+        None => {
+            let mut conversation = Conversation::new("Interactive session".to_string());
+            conversation.system = api.system.clone();
+            conversation
+        }
+    };
+    if let Some(system) = get_system_prompt(cli).await? {
+        conversation.system = Some(system);
+    }
+
+    let mut session =
+        cli::InteractiveSession::new(client, settings, api, conversation, cli.verbose);
+    session.run(Some(initial_message)).await
+}
+
 // 依存関係のre-export
 pub use clap;
 pub use serde;
@@ -10,13 +10,13 @@ async fn main() {
 
     // アプリケーションの実行
     if let Err(e) = run_app().await {
-        handle_error(e);
+        ask::output::on_stderr(|| handle_error(e));
         process::exit(1);
     }
 }
 
 fn handle_error(error: AskError) {
-    match error {
+    match &error {
         AskError::AuthenticationFailed => {
             eprintln!("{} Authentication failed", "❌ Error:".red().bold());
             eprintln!(
@@ -55,8 +55,60 @@ fn handle_error(error: AskError) {
             eprintln!("Please wait a moment and try again.");
         }
 
+        AskError::AttachmentError(msg) => {
+            eprintln!("{} {}", "❌ Attachment Error:".red().bold(), msg);
+            eprintln!("Supported files: PNG, JPEG, GIF, WebP (up to 5 MB), PDF and plain text");
+        }
+
+        AskError::InvalidRequest { message, .. } => {
+            eprintln!("{} {}", "❌ Invalid Request:".red().bold(), message);
+            eprintln!(
+                "Check the model and parameters with {}. If the prompt is too long, start a new conversation or shorten the input.",
+                "ask config show".cyan()
+            );
+        }
+
+        AskError::PermissionDenied { message, .. } => {
+            eprintln!("{} {}", "❌ Permission Denied:".red().bold(), message);
+            eprintln!("Your API key does not have access to this model or feature.");
+        }
+
+        AskError::NotFound { message, .. } => {
+            eprintln!("{} {}", "❌ Not Found:".red().bold(), message);
+            eprintln!(
+                "Check the model name (set with {} or {}).",
+                "--model".cyan(),
+                "ask config set-model NAME".cyan()
+            );
+        }
+
+        AskError::RequestTooLarge { message, .. } => {
+            eprintln!("{} {}", "❌ Request Too Large:".red().bold(), message);
+            eprintln!(
+                "Requests are limited to {} MB. Use smaller attachments or start a new conversation.",
+                ask::client::MAX_REQUEST_BYTES / (1024 * 1024)
+            );
+        }
+
+        AskError::Overloaded { message, .. } => {
+            eprintln!("{} {}", "❌ API Overloaded:".red().bold(), message);
+            eprintln!(
+                "The API is busy. Try again in a few moments, or raise {} to keep retrying.",
+                "[api.retry] max_attempts".yellow()
+            );
+        }
+
+        AskError::ApiServerError { message, .. } => {
+            eprintln!("{} {}", "❌ API Server Error:".red().bold(), message);
+            eprintln!("This is a problem on the API side. Please try again later.");
+        }
+
         _ => {
             eprintln!("{} {}", "❌ Error:".red().bold(), error);
         }
     }
+
+    if let Some(request_id) = error.request_id() {
+        eprintln!("{} {}", "Request ID:".dimmed(), request_id);
+    }
 }
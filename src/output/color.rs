@@ -0,0 +1,71 @@
+use std::sync::OnceLock;
+
+/// 標準出力と標準エラー出力それぞれで色を使うか（`init_color` で決まる）
+static STREAM_COLORS: OnceLock<StreamColors> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy)]
+struct StreamColors {
+    stdout: bool,
+    stderr: bool,
+}
+
+/// 色付きの出力を使うか判定する
+///
+/// `--no-color`、設定の `color = false`、環境変数 `NO_COLOR`、出力先が端末でない場合は無効。
+pub fn should_use_color(
+    no_color_flag: bool,
+    config_color: bool,
+    no_color_env: bool,
+    is_tty: bool,
+) -> bool {
+    !no_color_flag && config_color && !no_color_env && is_tty
+}
+
+/// 色の設定を標準出力と標準エラー出力のそれぞれについて判定する
+///
+/// `colored` の出力には標準出力の判定を反映する。標準エラー出力への書き込みは
+/// [`on_stderr`] で囲むと、標準エラー出力の判定に切り替わる。
+pub fn init_color(no_color_flag: bool, config_color: bool) -> bool {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let decide =
+        |stream| should_use_color(no_color_flag, config_color, no_color_env, atty::is(stream));
+    let colors = StreamColors {
+        stdout: decide(atty::Stream::Stdout),
+        stderr: decide(atty::Stream::Stderr),
+    };
+    let _ = STREAM_COLORS.set(colors);
+    colored::control::set_override(colors.stdout);
+    colors.stdout
+}
+
+/// 標準エラー出力に書く間だけ、標準エラー出力の色の設定に切り替える
+///
+/// `2>log` のように標準エラー出力だけがリダイレクトされていても、エスケープシーケンスを書き込まない。
+pub fn on_stderr<T>(print: impl FnOnce() -> T) -> T {
+    let Some(colors) = STREAM_COLORS.get() else {
+        return print();
+    };
+    colored::control::set_override(colors.stderr);
+    let result = print();
+    colored::control::set_override(colors.stdout);
+    result
+}
+
+/// 現在、色付きの出力が有効か
+pub fn color_enabled() -> bool {
+    colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_use_color() {
+        assert!(should_use_color(false, true, false, true));
+        assert!(!should_use_color(true, true, false, true));
+        assert!(!should_use_color(false, false, false, true));
+        assert!(!should_use_color(false, true, true, true));
+        assert!(!should_use_color(false, true, false, false));
+    }
+}
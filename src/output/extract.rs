@@ -0,0 +1,445 @@
+use crate::error::{AskError, Result};
+use crate::template::detect_language;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// コードブロックの開始・終了を表すフェンス（```` ``` ```` または `~~~`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fence {
+    pub marker: char,
+    pub len: usize,
+    /// フェンスの後ろの情報文字列（言語やファイル名）
+    pub info: String,
+}
+
+impl Fence {
+    /// 行がコードブロックの開始フェンスなら解析する
+    pub fn open(line: &str) -> Option<Self> {
+        let trimmed = line.trim_start();
+        if line.len() - trimmed.len() > 3 {
+            return None;
+        }
+        let marker = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+        let len = trimmed.chars().take_while(|&c| c == marker).count();
+        if len < 3 {
+            return None;
+        }
+        let info = trimmed[len..].trim();
+        if marker == '`' && info.contains('`') {
+            return None;
+        }
+        Some(Self {
+            marker,
+            len,
+            info: info.to_string(),
+        })
+    }
+
+    /// 行がこのフェンスを閉じるか
+    pub fn closes(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        trimmed.chars().count() >= self.len && trimmed.chars().all(|c| c == self.marker)
+    }
+
+    /// 言語（`rust`、`python:app.py` の `python` など）
+    pub fn language(&self) -> Option<String> {
+        let token = self.info.split_whitespace().next()?;
+        let language = token
+            .split_once(':')
+            .map_or(token, |(language, _)| language);
+        if language.is_empty() {
+            return None;
+        }
+        if looks_like_path(language) {
+            // ```src/main.rs のようにファイル名だけが書かれている場合は拡張子から推測する
+            return detect_language(Path::new(language)).map(str::to_string);
+        }
+        Some(language.to_string())
+    }
+
+    /// ファイル名のヒント
+    ///
+    /// `python:app.py`、`rust title=src/main.rs`（`file=`、`filename=` も可）、
+    /// `rust src/main.rs`、`src/main.rs` の形式に対応する。
+    pub fn filename(&self) -> Option<String> {
+        let mut tokens = self.info.split_whitespace();
+        let first = tokens.next()?;
+        if let Some((_, name)) = first.split_once(':') {
+            return Some(name.to_string()).filter(|name| !name.is_empty());
+        }
+        if looks_like_path(first) {
+            return Some(first.to_string());
+        }
+
+        for token in tokens {
+            for key in ["title=", "filename=", "file="] {
+                if let Some(value) = token.strip_prefix(key) {
+                    let value = value.trim_matches(|c| c == '"' || c == '\'');
+                    if !value.is_empty() {
+                        return Some(value.to_string());
+                    }
+                }
+            }
+            if looks_like_path(token) {
+                return Some(token.to_string());
+            }
+        }
+        None
+    }
+}
+
+fn looks_like_path(token: &str) -> bool {
+    !token.contains('=')
+        && (token.contains('/') || Path::new(token).extension().is_some())
+        && !token.starts_with('.')
+}
+
+/// 応答から取り出したコードブロック
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// 応答の中での番号（1から）
+    pub index: usize,
+    pub language: Option<String>,
+    pub filename: Option<String>,
+    pub content: String,
+}
+
+impl CodeBlock {
+    /// 言語で絞り込むときに一致するか（大文字小文字や `py` / `python` などの別名は区別しない）
+    pub fn matches(&self, language: &str) -> bool {
+        self.language.as_deref().is_some_and(|own| {
+            own.eq_ignore_ascii_case(language)
+                || (extension_for_language(own) != "txt"
+                    && extension_for_language(own) == extension_for_language(language))
+        })
+    }
+
+    /// ファイルに書き出すときの名前（ヒントが無い場合は番号と拡張子から作る）
+    pub fn file_name(&self) -> String {
+        match self.filename {
+            Some(ref name) => name.clone(),
+            None => format!(
+                "code_{}.{}",
+                self.index,
+                extension_for_language(self.language.as_deref().unwrap_or(""))
+            ),
+        }
+    }
+}
+
+/// 少しずつ届くテキストからコードブロックを取り出す
+#[derive(Debug, Default)]
+pub struct CodeExtractor {
+    /// まだ改行が届いていない行
+    line: String,
+    open: Option<(Fence, String)>,
+    count: usize,
+}
+
+impl CodeExtractor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// テキストを追加し、閉じたコードブロックを返す
+    pub fn push(&mut self, text: &str) -> Vec<CodeBlock> {
+        self.line.push_str(text);
+        let mut blocks = Vec::new();
+        while let Some(end) = self.line.find('\n') {
+            let line: String = self.line.drain(..=end).collect();
+            if let Some(block) = self.process_line(&line) {
+                blocks.push(block);
+            }
+        }
+        blocks
+    }
+
+    /// 残りを処理し、閉じていないコードブロックがあれば返す
+    pub fn finish(&mut self) -> Vec<CodeBlock> {
+        let line = std::mem::take(&mut self.line);
+        let mut blocks: Vec<CodeBlock> = self.process_line(&line).into_iter().collect();
+        if let Some((fence, content)) = self.open.take() {
+            blocks.push(self.block(&fence, content));
+        }
+        blocks
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<CodeBlock> {
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        match self.open.take() {
+            Some((fence, content)) if fence.closes(trimmed) => Some(self.block(&fence, content)),
+            Some((fence, mut content)) => {
+                content.push_str(line);
+                self.open = Some((fence, content));
+                None
+            }
+            None => {
+                self.open = Fence::open(trimmed).map(|fence| (fence, String::new()));
+                None
+            }
+        }
+    }
+
+    fn block(&mut self, fence: &Fence, content: String) -> CodeBlock {
+        self.count += 1;
+        CodeBlock {
+            index: self.count,
+            language: fence.language(),
+            filename: fence.filename(),
+            content,
+        }
+    }
+}
+
+/// テキストからコードブロックをすべて取り出す
+pub fn extract_code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut extractor = CodeExtractor::new();
+    let mut blocks = extractor.push(text);
+    blocks.extend(extractor.finish());
+    blocks
+}
+
+/// `--extract-code` / `--extract-code-to` の処理
+///
+/// 取り出したコードブロックを出力し、ディレクトリが指定されていればファイルにも書き出す。
+pub struct CodeExtraction {
+    extractor: CodeExtractor,
+    language: Option<String>,
+    print: bool,
+    dir: Option<PathBuf>,
+    printed: usize,
+    written: Vec<PathBuf>,
+}
+
+impl CodeExtraction {
+    /// `language` で絞り込み、`print` が有効なら出力し、`dir` があれば書き出す
+    pub fn new(language: Option<&str>, print: bool, dir: Option<PathBuf>) -> Self {
+        Self {
+            extractor: CodeExtractor::new(),
+            language: language
+                .filter(|language| !language.is_empty())
+                .map(str::to_string),
+            print,
+            dir,
+            printed: 0,
+            written: Vec::new(),
+        }
+    }
+
+    /// コードブロックだけを出力するか（応答の本文は表示しない）
+    pub fn prints(&self) -> bool {
+        self.print
+    }
+
+    /// 応答のテキストを追加する
+    pub fn push(&mut self, out: &mut dyn Write, text: &str) -> Result<()> {
+        let blocks = self.extractor.push(text);
+        self.handle(out, blocks)
+    }
+
+    /// 残りを処理する
+    pub fn finish(&mut self, out: &mut dyn Write) -> Result<()> {
+        let blocks = self.extractor.finish();
+        self.handle(out, blocks)
+    }
+
+    /// 書き出したファイルのパス
+    pub fn written(&self) -> &[PathBuf] {
+        &self.written
+    }
+
+    /// 出力・書き出しの対象になったコードブロックの数
+    pub fn matched(&self) -> usize {
+        if self.print {
+            self.printed
+        } else {
+            self.written.len()
+        }
+    }
+
+    fn handle(&mut self, out: &mut dyn Write, blocks: Vec<CodeBlock>) -> Result<()> {
+        for block in blocks {
+            if let Some(ref language) = self.language {
+                if !block.matches(language) {
+                    continue;
+                }
+            }
+
+            if self.print {
+                if self.printed > 0 {
+                    writeln!(out)?;
+                }
+                write!(out, "{}", block.content)?;
+                if !block.content.ends_with('\n') {
+                    writeln!(out)?;
+                }
+                out.flush()?;
+                self.printed += 1;
+            }
+
+            if let Some(ref dir) = self.dir {
+                let path = dir.join(safe_relative_path(&block)?);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let path = write_new_file(&path, &block.content)?;
+                self.written.push(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ファイル名のヒントを出力先ディレクトリの中に収まる相対パスにする
+fn safe_relative_path(block: &CodeBlock) -> Result<PathBuf> {
+    let name = block.file_name();
+    let path = Path::new(&name);
+    let safe = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if safe {
+        return Ok(path.to_path_buf());
+    }
+
+    // ディレクトリの外を指すヒントは使わず、ファイル名だけを使う
+    path.file_name()
+        .filter(|name| !name.is_empty() && *name != "..")
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            AskError::InvalidInput(format!("Invalid file name for code block: {}", name))
+        })
+}
+
+/// 既存のファイルは上書きせず、空いている `-2`, `-3`, ... 付きの名前に書き出す
+///
+/// 同じ実行の中で同じ名前になったブロックも、先に書き出したファイルと重ならない。
+fn write_new_file(path: &Path, content: &str) -> Result<PathBuf> {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut candidate = path.to_path_buf();
+    for n in 2.. {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(mut file) => {
+                file.write_all(content.as_bytes())?;
+                return Ok(candidate);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                candidate = path.with_file_name(format!("{}-{}{}", stem, n, extension));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!("ran out of file names")
+}
+
+/// 言語名からファイルの拡張子を決める
+pub fn extension_for_language(language: &str) -> &'static str {
+    match language.to_lowercase().as_str() {
+        "rust" | "rs" => "rs",
+        "python" | "py" => "py",
+        "javascript" | "js" => "js",
+        "jsx" => "jsx",
+        "typescript" | "ts" => "ts",
+        "tsx" => "tsx",
+        "go" | "golang" => "go",
+        "java" => "java",
+        "kotlin" | "kt" => "kt",
+        "scala" => "scala",
+        "c" => "c",
+        "cpp" | "c++" => "cpp",
+        "csharp" | "cs" | "c#" => "cs",
+        "ruby" | "rb" => "rb",
+        "php" => "php",
+        "swift" => "swift",
+        "dart" => "dart",
+        "lua" => "lua",
+        "perl" => "pl",
+        "r" => "r",
+        "elixir" => "ex",
+        "haskell" => "hs",
+        "bash" | "sh" | "shell" | "console" => "sh",
+        "zsh" => "zsh",
+        "fish" => "fish",
+        "powershell" | "ps1" => "ps1",
+        "sql" => "sql",
+        "html" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "markdown" | "md" => "md",
+        "protobuf" | "proto" => "proto",
+        "hcl" | "terraform" => "tf",
+        "dockerfile" | "docker" => "dockerfile",
+        "makefile" | "make" => "mk",
+        "diff" | "patch" => "diff",
+        _ => "txt",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fence_hints() {
+        let fence = |info: &str| Fence::open(&format!("```{}", info)).unwrap();
+
+        assert_eq!(fence("rust").language().as_deref(), Some("rust"));
+        assert_eq!(fence("rust").filename(), None);
+        assert_eq!(fence("python:app.py").language().as_deref(), Some("python"));
+        assert_eq!(fence("python:app.py").filename().as_deref(), Some("app.py"));
+        assert_eq!(
+            fence("rust title=\"src/main.rs\"").filename().as_deref(),
+            Some("src/main.rs")
+        );
+        assert_eq!(
+            fence("rust src/lib.rs").filename().as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(fence("src/main.rs").language().as_deref(), Some("rust"));
+        assert_eq!(fence("").language(), None);
+
+        assert!(Fence::open("``not a fence").is_none());
+        assert!(Fence::open("````").unwrap().closes("`````"));
+        assert!(!Fence::open("````").unwrap().closes("```"));
+    }
+
+    #[test]
+    fn test_file_names() {
+        let block = |language: Option<&str>, filename: Option<&str>| CodeBlock {
+            index: 3,
+            language: language.map(str::to_string),
+            filename: filename.map(str::to_string),
+            content: String::new(),
+        };
+
+        assert_eq!(block(Some("python"), None).file_name(), "code_3.py");
+        assert_eq!(block(None, None).file_name(), "code_3.txt");
+        assert_eq!(block(Some("rust"), Some("main.rs")).file_name(), "main.rs");
+        assert_eq!(
+            safe_relative_path(&block(None, Some("../../etc/passwd"))).unwrap(),
+            PathBuf::from("passwd")
+        );
+        assert_eq!(
+            safe_relative_path(&block(None, Some("src/lib.rs"))).unwrap(),
+            PathBuf::from("src/lib.rs")
+        );
+    }
+}
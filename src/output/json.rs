@@ -0,0 +1,72 @@
+use crate::client::models::{ChatResponse, ContentBlock, StreamEvent, Usage};
+use crate::error::Result;
+use crate::output::{RenderContext, Renderer};
+use serde::Serialize;
+use std::io::Write;
+
+/// 応答を JSON で出力する
+///
+/// 非ストリーミングでは応答全体を1つのオブジェクトとして、ストリーミングでは
+/// 受信したイベントを1行ずつ（NDJSON）出力し、最後に `"type": "result"` の行を出力する。
+pub struct JsonRenderer;
+
+/// JSON 出力の応答全体
+#[derive(Debug, Serialize)]
+pub struct JsonResponse<'a> {
+    pub id: &'a str,
+    pub model: &'a str,
+    pub stop_reason: Option<&'a str>,
+    pub stop_sequence: Option<&'a str>,
+    pub usage: &'a Usage,
+    pub content: &'a [ContentBlock],
+    pub conversation_id: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonResult<'a> {
+    r#type: &'static str,
+    #[serde(flatten)]
+    response: JsonResponse<'a>,
+}
+
+impl<'a> JsonResponse<'a> {
+    pub fn new(response: &'a ChatResponse, context: &'a RenderContext) -> Self {
+        Self {
+            id: &response.id,
+            model: &response.model,
+            stop_reason: response.stop_reason.as_deref(),
+            stop_sequence: response.stop_sequence.as_deref(),
+            usage: &response.usage,
+            content: &response.content,
+            conversation_id: context.conversation_id.as_deref(),
+        }
+    }
+}
+
+impl Renderer for JsonRenderer {
+    fn event(&mut self, out: &mut dyn Write, event: &StreamEvent) -> Result<()> {
+        writeln!(out, "{}", serde_json::to_string(event)?)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        response: &ChatResponse,
+        context: &RenderContext,
+        streamed: bool,
+    ) -> Result<()> {
+        let response = JsonResponse::new(response, context);
+        if streamed {
+            let result = JsonResult {
+                r#type: "result",
+                response,
+            };
+            writeln!(out, "{}", serde_json::to_string(&result)?)?;
+        } else {
+            writeln!(out, "{}", serde_json::to_string_pretty(&response)?)?;
+        }
+        Ok(())
+    }
+}
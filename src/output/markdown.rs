@@ -0,0 +1,43 @@
+use crate::client::models::{ChatResponse, StreamEvent};
+use crate::error::Result;
+use crate::output::{RenderContext, Renderer};
+use std::io::Write;
+
+/// 応答の前にメタデータのヘッダを付けて Markdown として出力する
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn begin(&mut self, out: &mut dyn Write, context: &RenderContext) -> Result<()> {
+        writeln!(out, "# {}\n", context.title)?;
+        writeln!(out, "- **Model**: `{}`", context.model)?;
+        if let Some(ref id) = context.conversation_id {
+            writeln!(out, "- **Conversation**: `{}`", id)?;
+        }
+        writeln!(out, "- **Date**: {}", context.created_at.to_rfc3339())?;
+        writeln!(out, "\n---\n")?;
+        Ok(())
+    }
+
+    fn event(&mut self, out: &mut dyn Write, event: &StreamEvent) -> Result<()> {
+        if let Some(text) = event.text_delta() {
+            write!(out, "{}", text)?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        response: &ChatResponse,
+        _context: &RenderContext,
+        streamed: bool,
+    ) -> Result<()> {
+        if streamed {
+            writeln!(out)?;
+        } else {
+            writeln!(out, "{}", response.text())?;
+        }
+        Ok(())
+    }
+}
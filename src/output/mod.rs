@@ -0,0 +1,72 @@
+pub mod color;
+pub mod extract;
+pub mod json;
+pub mod markdown;
+pub mod pager;
+pub mod plain;
+pub mod terminal;
+
+pub use color::*;
+pub use extract::*;
+pub use json::*;
+pub use markdown::*;
+pub use pager::*;
+pub use plain::*;
+pub use terminal::*;
+
+use crate::client::models::{ChatResponse, StreamEvent};
+use crate::config::OutputFormat;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use std::io::Write;
+
+/// 応答を出力するときの付加情報
+#[derive(Debug, Clone)]
+pub struct RenderContext {
+    /// リクエストしたモデル
+    pub model: String,
+    pub title: String,
+    /// 履歴に保存される会話の ID（保存しない場合は None）
+    pub conversation_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 出力形式ごとの応答の書き出し方
+///
+/// ストリーミングでは `begin` → `event`（イベントごと）→ `finish(streamed = true)`、
+/// 非ストリーミングでは `begin` → `finish(streamed = false)` の順に呼ばれる。
+pub trait Renderer {
+    /// 応答の前に出力する（ヘッダなど）
+    fn begin(&mut self, _out: &mut dyn Write, _context: &RenderContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// ストリーミングのイベントを1つ出力する
+    fn event(&mut self, out: &mut dyn Write, event: &StreamEvent) -> Result<()>;
+
+    /// 応答の全体を受け取って出力を終える
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        response: &ChatResponse,
+        context: &RenderContext,
+        streamed: bool,
+    ) -> Result<()>;
+}
+
+/// 出力形式に対応するレンダラーを作成する
+///
+/// `terminal` が有効な場合、テキストの応答は Markdown として整形して表示する。
+pub fn renderer_for(format: &OutputFormat, terminal: bool) -> Box<dyn Renderer> {
+    match format {
+        OutputFormat::Plain if terminal => Box::new(TerminalRenderer::new()),
+        OutputFormat::Plain => Box::new(PlainRenderer),
+        OutputFormat::Json => Box::new(JsonRenderer),
+        OutputFormat::Markdown => Box::new(MarkdownRenderer),
+    }
+}
+
+/// 応答を端末向けに整形するか（色が有効で、標準出力が端末の場合）
+pub fn use_terminal_markdown() -> bool {
+    color_enabled() && atty::is(atty::Stream::Stdout)
+}
@@ -0,0 +1,60 @@
+use crate::error::Result;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// `$PAGER` が設定されていない場合のページャー
+pub const DEFAULT_PAGER: &str = "less -R";
+
+/// ページャーのコマンド（`$PAGER` が空の場合は使わない）
+pub fn pager_command() -> Option<String> {
+    match std::env::var("PAGER") {
+        Ok(pager) if pager.trim().is_empty() => None,
+        Ok(pager) => Some(pager),
+        Err(_) => Some(DEFAULT_PAGER.to_string()),
+    }
+}
+
+/// 内容をページャーで表示する
+///
+/// `enabled` が無効な場合や標準出力が端末でない場合、ページャーを起動できない
+/// 場合はそのまま標準出力に書き出す。
+pub fn page(content: &str, enabled: bool) -> Result<()> {
+    if enabled && atty::is(atty::Stream::Stdout) {
+        if let Some(command) = pager_command() {
+            if run_pager(&command, content)? {
+                return Ok(());
+            }
+        }
+    }
+
+    let mut stdout = io::stdout();
+    stdout.write_all(content.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// ページャーに内容を渡す（起動できなかった場合は false）
+fn run_pager(command: &str, content: &str) -> Result<bool> {
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Ok(false);
+    };
+
+    let Ok(mut child) = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn()
+    else {
+        return Ok(false);
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // 途中でページャーを閉じた場合の BrokenPipe は無視する
+        match stdin.write_all(content.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(true)
+}
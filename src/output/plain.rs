@@ -0,0 +1,32 @@
+use crate::client::models::{ChatResponse, StreamEvent};
+use crate::error::Result;
+use crate::output::{RenderContext, Renderer};
+use std::io::Write;
+
+/// 応答のテキストだけを出力する
+pub struct PlainRenderer;
+
+impl Renderer for PlainRenderer {
+    fn event(&mut self, out: &mut dyn Write, event: &StreamEvent) -> Result<()> {
+        if let Some(text) = event.text_delta() {
+            write!(out, "{}", text)?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        response: &ChatResponse,
+        _context: &RenderContext,
+        streamed: bool,
+    ) -> Result<()> {
+        if streamed {
+            writeln!(out)?; // 改行
+        } else {
+            writeln!(out, "{}", response.text())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,497 @@
+use crate::client::models::{ChatResponse, StreamEvent};
+use crate::error::Result;
+use crate::output::{Fence, RenderContext, Renderer};
+use colored::*;
+use std::io::Write;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::as_24_bit_terminal_escaped;
+use unicode_width::UnicodeWidthStr;
+
+/// コードブロックのハイライトに使うテーマ
+const THEME: &str = "base16-ocean.dark";
+
+/// Markdown を端末向けに整形して出力する
+///
+/// ストリーミング中は完成した行から順に整形する（表は表全体が揃うまで待つ）。
+pub struct TerminalRenderer {
+    markdown: MarkdownStream,
+}
+
+impl TerminalRenderer {
+    pub fn new() -> Self {
+        Self {
+            markdown: MarkdownStream::new(true),
+        }
+    }
+}
+
+impl Default for TerminalRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn event(&mut self, out: &mut dyn Write, event: &StreamEvent) -> Result<()> {
+        if let Some(text) = event.text_delta() {
+            write!(out, "{}", self.markdown.push(text))?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(
+        &mut self,
+        out: &mut dyn Write,
+        response: &ChatResponse,
+        _context: &RenderContext,
+        streamed: bool,
+    ) -> Result<()> {
+        let mut rest = String::new();
+        if !streamed {
+            rest.push_str(&self.markdown.push(&response.text()));
+        }
+        rest.push_str(&self.markdown.finish());
+
+        write!(out, "{}", rest)?;
+        if !rest.is_empty() && !rest.ends_with('\n') {
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Markdown のテキストをまとめて端末向けに整形する
+pub fn render_markdown(text: &str, color: bool) -> String {
+    let mut markdown = MarkdownStream::new(color);
+    let mut output = markdown.push(text);
+    output.push_str(&markdown.finish());
+    output
+}
+
+/// 少しずつ届く Markdown を行単位で整形する
+///
+/// `color` が無効な場合は入力をそのまま返す。
+pub struct MarkdownStream {
+    color: bool,
+    /// まだ改行が届いていない行
+    line: String,
+    code: Option<CodeBlock>,
+    /// 列幅を揃えるために溜めている表の行
+    table: Vec<String>,
+}
+
+struct CodeBlock {
+    fence: Fence,
+    highlighter: Option<HighlightLines<'static>>,
+}
+
+impl MarkdownStream {
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            line: String::new(),
+            code: None,
+            table: Vec::new(),
+        }
+    }
+
+    /// テキストを追加し、整形できた部分を返す
+    pub fn push(&mut self, text: &str) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+
+        self.line.push_str(text);
+        let mut output = String::new();
+        while let Some(end) = self.line.find('\n') {
+            let line: String = self.line.drain(..=end).collect();
+            self.render_line(line.trim_end_matches(['\n', '\r']), &mut output);
+        }
+        output
+    }
+
+    /// 残っている行を整形して返す
+    pub fn finish(&mut self) -> String {
+        let mut output = String::new();
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+            self.render_line(&line, &mut output);
+            // 改行で終わっていなかった行には改行を付けない
+            if output.ends_with('\n') && self.table.is_empty() {
+                output.pop();
+            }
+        }
+        self.flush_table(&mut output);
+        self.code = None;
+        output
+    }
+
+    fn render_line(&mut self, line: &str, output: &mut String) {
+        if let Some(ref mut code) = self.code {
+            if code.fence.closes(line) {
+                self.code = None;
+                output.push_str(&format!("{}\n", line.dimmed()));
+            } else {
+                output.push_str(&highlight(code, line));
+            }
+            return;
+        }
+
+        if is_table_row(line) {
+            self.table.push(line.to_string());
+            return;
+        }
+        self.flush_table(output);
+
+        if let Some(fence) = Fence::open(line) {
+            self.code = Some(CodeBlock {
+                highlighter: fence
+                    .language()
+                    .and_then(|language| highlighter_for(&language)),
+                fence,
+            });
+            output.push_str(&format!("{}\n", line.dimmed()));
+            return;
+        }
+
+        output.push_str(&render_block_line(line));
+        output.push('\n');
+    }
+
+    fn flush_table(&mut self, output: &mut String) {
+        if self.table.is_empty() {
+            return;
+        }
+        let rows = std::mem::take(&mut self.table);
+        output.push_str(&render_table(&rows));
+    }
+}
+
+fn render_block_line(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    // 見出し
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+        let text = render_inline(trimmed[level..].trim());
+        return match level {
+            1 => text.cyan().bold().underline().to_string(),
+            2 => text.cyan().bold().to_string(),
+            _ => text.bold().to_string(),
+        };
+    }
+
+    // 区切り線
+    let compact: Vec<char> = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.len() >= 3
+        && matches!(compact[0], '-' | '*' | '_')
+        && compact.iter().all(|&c| c == compact[0])
+    {
+        return "─".repeat(40).dimmed().to_string();
+    }
+
+    // 引用
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        return format!(
+            "{}{} {}",
+            indent,
+            "│".dimmed(),
+            render_inline(quote.trim_start()).italic()
+        );
+    }
+
+    // 箇条書き
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(marker) {
+            return format!("{}{} {}", indent, "•".yellow(), render_list_item(item));
+        }
+    }
+
+    // 番号付きリスト
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        let rest = &trimmed[digits..];
+        if let Some(item) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return format!(
+                "{}{} {}",
+                indent,
+                format!("{}.", &trimmed[..digits]).yellow(),
+                render_inline(item)
+            );
+        }
+    }
+
+    format!("{}{}", indent, render_inline(trimmed))
+}
+
+fn render_list_item(item: &str) -> String {
+    for (marker, checked) in [("[ ] ", false), ("[x] ", true), ("[X] ", true)] {
+        if let Some(rest) = item.strip_prefix(marker) {
+            let checkbox = if checked {
+                "☑".green()
+            } else {
+                "☐".normal()
+            };
+            return format!("{} {}", checkbox, render_inline(rest));
+        }
+    }
+    render_inline(item)
+}
+
+/// 強調・インラインコード・リンクを整形する
+fn render_inline(text: &str) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('`') {
+            if let Some(end) = after.find('`') {
+                output.push_str(&after[..end].yellow().to_string());
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+
+        let mut styled = false;
+        for (marker, bold) in [("**", true), ("__", true), ("*", false), ("_", false)] {
+            let Some(after) = rest.strip_prefix(marker) else {
+                continue;
+            };
+            // 空白に挟まれた記号や単語中の `_` は強調として扱わない
+            if after.starts_with(char::is_whitespace)
+                || (marker.starts_with('_') && output.ends_with(char::is_alphanumeric))
+            {
+                continue;
+            }
+            if let Some(end) = after.find(marker).filter(|&end| end > 0) {
+                let inner = render_inline(&after[..end]);
+                output.push_str(&if bold {
+                    inner.bold().to_string()
+                } else {
+                    inner.italic().to_string()
+                });
+                rest = &after[end + marker.len()..];
+                styled = true;
+                break;
+            }
+        }
+        if styled {
+            continue;
+        }
+
+        if let Some(after) = rest.strip_prefix('[') {
+            if let Some((label, url, consumed)) = parse_link(after) {
+                output.push_str(&format!(
+                    "{} {}",
+                    label.blue().underline(),
+                    format!("({})", url).dimmed()
+                ));
+                rest = &after[consumed..];
+                continue;
+            }
+        }
+
+        let c = rest.chars().next().expect("rest is not empty");
+        output.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    output
+}
+
+/// `label](url)` を解析し、ラベル・URL・消費したバイト数を返す
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find(']')?;
+    let after = text[label_end + 1..].strip_prefix('(')?;
+    let url_end = after.find(')')?;
+    Some((
+        &text[..label_end],
+        &after[..url_end],
+        label_end + 2 + url_end + 1,
+    ))
+}
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    &THEME_SET.get_or_init(ThemeSet::load_defaults).themes[THEME]
+}
+
+fn highlighter_for(language: &str) -> Option<HighlightLines<'static>> {
+    if language.is_empty() {
+        return None;
+    }
+    let syntaxes = syntax_set();
+    let syntax = syntaxes
+        .find_syntax_by_token(language)
+        .or_else(|| syntaxes.find_syntax_by_extension(language))?;
+    Some(HighlightLines::new(syntax, theme()))
+}
+
+fn highlight(code: &mut CodeBlock, line: &str) -> String {
+    let Some(ref mut highlighter) = code.highlighter else {
+        return format!("{}\n", line);
+    };
+
+    let line = format!("{}\n", line);
+    match highlighter.highlight_line(&line, syntax_set()) {
+        Ok(ranges) => format!("{}\x1b[0m", as_24_bit_terminal_escaped(&ranges, false)),
+        Err(_) => line,
+    }
+}
+
+fn is_table_row(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.len() > 1 && trimmed.starts_with('|')
+}
+
+fn split_row(row: &str) -> Vec<String> {
+    let trimmed = row.trim();
+    let inner = trimmed.strip_prefix('|').unwrap_or(trimmed);
+    let inner = inner.strip_suffix('|').unwrap_or(inner);
+    inner
+        .split('|')
+        .map(|cell| cell.trim().to_string())
+        .collect()
+}
+
+fn is_separator_row(cells: &[String]) -> bool {
+    cells.iter().all(|cell| {
+        !cell.is_empty() && cell.chars().all(|c| matches!(c, '-' | ':')) && cell.contains('-')
+    })
+}
+
+fn render_table(rows: &[String]) -> String {
+    let rows: Vec<Vec<String>> = rows.iter().map(|row| split_row(row)).collect();
+    let has_header = rows.len() > 1 && is_separator_row(&rows[1]);
+    let body: Vec<&Vec<String>> = rows
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !(has_header && *i == 1))
+        .map(|(_, row)| row)
+        .collect();
+
+    let columns = body.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            body.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.width())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut output = String::new();
+    for (i, row) in body.iter().enumerate() {
+        let cells: Vec<String> = (0..columns)
+            .map(|column| {
+                let cell = row.get(column).map(String::as_str).unwrap_or("");
+                let padding = " ".repeat(widths[column] - cell.width());
+                if has_header && i == 0 {
+                    format!("{}{}", cell.bold(), padding)
+                } else {
+                    format!("{}{}", render_inline(cell), padding)
+                }
+            })
+            .collect();
+        output.push_str(&format!("{}\n", cells.join(&format!(" {} ", "│".dimmed()))));
+
+        if has_header && i == 0 {
+            let rule: Vec<String> = widths.iter().map(|&width| "─".repeat(width)).collect();
+            output.push_str(&format!("{}\n", rule.join("─┼─").dimmed()));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "# Title\n\nSome **bold** and `code`.\n\n| Name | 値 |\n|------|----|\n| a | 1 |\n| long name | 2 |\n\n```rust\nfn main() {}\n```\n- item";
+
+    /// ANSI エスケープシーケンスを取り除く
+    fn strip_ansi(text: &str) -> String {
+        let mut output = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                for c in chars.by_ref() {
+                    if c == 'm' {
+                        break;
+                    }
+                }
+            } else {
+                output.push(c);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_without_color_passes_text_through() {
+        assert_eq!(render_markdown(SAMPLE, false), SAMPLE);
+    }
+
+    #[test]
+    fn test_chunked_input_renders_like_whole_text() {
+        let whole = render_markdown(SAMPLE, true);
+
+        let mut markdown = MarkdownStream::new(true);
+        let mut chunked = String::new();
+        for c in SAMPLE.chars() {
+            chunked.push_str(&markdown.push(&c.to_string()));
+        }
+        chunked.push_str(&markdown.finish());
+
+        assert_eq!(chunked, whole);
+        assert!(strip_ansi(&whole).ends_with("fn main() {}\n```\n• item"));
+    }
+
+    #[test]
+    fn test_code_block_is_highlighted() {
+        let output = render_markdown("```rust\nlet x = 1;\n```\n", true);
+        assert!(output.contains("\x1b[38;2;"));
+        assert_eq!(strip_ansi(&output), "```rust\nlet x = 1;\n```\n");
+
+        // 未知の言語はそのまま表示する
+        let output = render_markdown("```unknown-lang\nx\n```\n", true);
+        assert_eq!(strip_ansi(&output), "```unknown-lang\nx\n```\n");
+    }
+
+    #[test]
+    fn test_table_columns_are_aligned() {
+        let output = render_markdown("| a | b |\n|---|---|\n| long | 値 |\ntext\n", true);
+        assert_eq!(
+            strip_ansi(&output),
+            "a    │ b \n─────┼───\nlong │ 値\ntext\n"
+        );
+    }
+
+    #[test]
+    fn test_block_and_inline_styles() {
+        assert_eq!(
+            strip_ansi(&render_inline("a **b** `c` [d](e)")),
+            "a b c d (e)"
+        );
+        assert_eq!(
+            strip_ansi(&render_inline("snake_case_name")),
+            "snake_case_name"
+        );
+        assert_eq!(strip_ansi(&render_block_line("## Heading")), "Heading");
+        assert_eq!(strip_ansi(&render_block_line("  - item")), "  • item");
+        assert_eq!(strip_ansi(&render_block_line("- [x] done")), "• ☑ done");
+        assert_eq!(strip_ansi(&render_block_line("2. second")), "2. second");
+        assert_eq!(strip_ansi(&render_block_line("> quote")), "│ quote");
+    }
+}
@@ -0,0 +1,312 @@
+use crate::config::ApiConfig;
+use crate::error::{AskError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// テンプレートのフロントマター
+///
+/// TOML の場合は `+++`、YAML の場合は `---` で囲んでファイルの先頭に書く。
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, VariableSpec>,
+}
+
+/// テンプレート変数の定義
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VariableSpec {
+    #[serde(default, rename = "type")]
+    pub kind: VariableType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_default",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default: Option<String>,
+}
+
+/// デフォルト値として書ける値（数値や真偽値、リストも文字列として扱う）
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DefaultValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<DefaultValue>),
+}
+
+impl DefaultValue {
+    fn into_string(self) -> String {
+        match self {
+            DefaultValue::Bool(value) => value.to_string(),
+            DefaultValue::Integer(value) => value.to_string(),
+            DefaultValue::Float(value) => value.to_string(),
+            DefaultValue::String(value) => value,
+            DefaultValue::List(values) => values
+                .into_iter()
+                .map(DefaultValue::into_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+}
+
+fn deserialize_default<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<DefaultValue>::deserialize(deserializer)?.map(DefaultValue::into_string))
+}
+
+/// テンプレート変数の型
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    #[default]
+    String,
+    Integer,
+    Number,
+    Boolean,
+    List,
+}
+
+/// フロントマターの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontMatterFormat {
+    Toml,
+    Yaml,
+}
+
+impl FrontMatterFormat {
+    fn delimiter(&self) -> &'static str {
+        match self {
+            FrontMatterFormat::Toml => "+++",
+            FrontMatterFormat::Yaml => "---",
+        }
+    }
+}
+
+impl std::fmt::Display for VariableType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariableType::String => write!(f, "string"),
+            VariableType::Integer => write!(f, "integer"),
+            VariableType::Number => write!(f, "number"),
+            VariableType::Boolean => write!(f, "boolean"),
+            VariableType::List => write!(f, "list"),
+        }
+    }
+}
+
+impl TemplateMetadata {
+    /// モデルやパラメータの指定を API 設定に上書きする
+    pub fn apply_to(&self, api: &mut ApiConfig) {
+        if let Some(ref model) = self.model {
+            api.model = model.clone();
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            api.max_tokens = max_tokens;
+        }
+        if let Some(temperature) = self.temperature {
+            api.temperature = temperature;
+        }
+    }
+
+    /// 与えられた変数を検証し、デフォルト値を補った変数一覧を返す
+    pub fn resolve_variables(
+        &self,
+        provided: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        let mut resolved = provided.clone();
+        let mut missing = Vec::new();
+
+        for (name, spec) in &self.variables {
+            match provided.get(name) {
+                Some(value) => spec.kind.validate(name, value)?,
+                None => match spec.default {
+                    Some(ref default) => {
+                        resolved.insert(name.clone(), default.clone());
+                    }
+                    None if spec.required => missing.push(name.as_str()),
+                    None => {}
+                },
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(AskError::TemplateError(format!(
+                "Missing required template variables: {}",
+                missing.join(", ")
+            )));
+        }
+
+        Ok(resolved)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=1.0).contains(&temperature) {
+                return Err(AskError::TemplateError(
+                    "Front-matter temperature must be between 0.0 and 1.0".to_string(),
+                ));
+            }
+        }
+
+        for (name, spec) in &self.variables {
+            if let Some(ref default) = spec.default {
+                spec.kind.validate(name, default)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl VariableType {
+    /// 値がこの型として解釈できるか確認する
+    pub fn validate(&self, name: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        let valid = match self {
+            VariableType::String | VariableType::List => true,
+            VariableType::Integer => value.parse::<i64>().is_ok(),
+            VariableType::Number => value.parse::<f64>().is_ok(),
+            VariableType::Boolean => matches!(
+                value.to_lowercase().as_str(),
+                "true" | "false" | "yes" | "no" | "1" | "0"
+            ),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(AskError::TemplateError(format!(
+                "Variable '{}' must be of type {}: '{}'",
+                name, self, value
+            )))
+        }
+    }
+}
+
+/// ファイルの内容をフロントマターと本文に分割する
+///
+/// フロントマターが無い場合はデフォルトのメタデータと内容全体を返す。
+pub fn parse_front_matter(raw: &str) -> Result<(TemplateMetadata, String)> {
+    let raw = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+
+    let format = match raw.lines().next().map(str::trim_end) {
+        Some("+++") => FrontMatterFormat::Toml,
+        Some("---") => FrontMatterFormat::Yaml,
+        _ => return Ok((TemplateMetadata::default(), raw.to_string())),
+    };
+    let delimiter = format.delimiter();
+
+    // 先頭の区切り行を飛ばし、閉じの区切り行を探す
+    let after_open = &raw[raw.find('\n').map(|i| i + 1).unwrap_or(raw.len())..];
+    let mut offset = 0;
+    let mut close = None;
+    for line in after_open.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            close = Some((offset, offset + line.len()));
+            break;
+        }
+        offset += line.len();
+    }
+
+    let (header_end, body_start) = close.ok_or_else(|| {
+        AskError::TemplateError(format!(
+            "Front-matter is not closed (expected a '{}' line)",
+            delimiter
+        ))
+    })?;
+
+    let header = &after_open[..header_end];
+    let metadata: TemplateMetadata = match format {
+        FrontMatterFormat::Toml => toml::from_str(header)
+            .map_err(|e| AskError::TemplateError(format!("Invalid TOML front-matter: {}", e)))?,
+        FrontMatterFormat::Yaml if header.trim().is_empty() => TemplateMetadata::default(),
+        FrontMatterFormat::Yaml => serde_yaml::from_str(header)
+            .map_err(|e| AskError::TemplateError(format!("Invalid YAML front-matter: {}", e)))?,
+    };
+    metadata.validate()?;
+
+    Ok((metadata, after_open[body_start..].to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml_front_matter() {
+        let raw = "+++\nmodel = \"claude-3-5-haiku-20241022\"\ntemperature = 0.2\n\n[variables.code]\nrequired = true\n+++\nReview {{code}}\n";
+        let (metadata, body) = parse_front_matter(raw).unwrap();
+
+        assert_eq!(metadata.model.as_deref(), Some("claude-3-5-haiku-20241022"));
+        assert_eq!(metadata.temperature, Some(0.2));
+        assert!(metadata.variables["code"].required);
+        assert_eq!(body, "Review {{code}}\n");
+    }
+
+    #[test]
+    fn test_parse_yaml_front_matter() {
+        let raw = "---\ndescription: Summaries\nmax_tokens: 500\nvariables:\n  count:\n    type: integer\n    default: 3\n---\nSummarize in {{count}} points";
+        let (metadata, body) = parse_front_matter(raw).unwrap();
+
+        assert_eq!(metadata.description.as_deref(), Some("Summaries"));
+        assert_eq!(metadata.max_tokens, Some(500));
+        assert_eq!(metadata.variables["count"].kind, VariableType::Integer);
+        assert_eq!(metadata.variables["count"].default.as_deref(), Some("3"));
+        assert_eq!(body, "Summarize in {{count}} points");
+    }
+
+    #[test]
+    fn test_no_front_matter() {
+        let (metadata, body) = parse_front_matter("Just {{text}}").unwrap();
+        assert_eq!(metadata, TemplateMetadata::default());
+        assert_eq!(body, "Just {{text}}");
+    }
+
+    #[test]
+    fn test_invalid_front_matter() {
+        assert!(parse_front_matter("+++\nmodel = \n+++\nbody").is_err());
+        assert!(parse_front_matter("---\nmodel: x\nbody without close").is_err());
+        assert!(parse_front_matter("+++\nunknown = 1\n+++\n").is_err());
+        assert!(parse_front_matter("+++\ntemperature = 3.0\n+++\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_variables() {
+        let (metadata, _) = parse_front_matter(
+            "+++\n[variables.code]\nrequired = true\n[variables.count]\ntype = \"integer\"\ndefault = \"3\"\n+++\n",
+        )
+        .unwrap();
+
+        let err = metadata.resolve_variables(&HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("code"));
+
+        let mut provided = HashMap::new();
+        provided.insert("code".to_string(), "fn main() {}".to_string());
+        let resolved = metadata.resolve_variables(&provided).unwrap();
+        assert_eq!(resolved["count"], "3");
+
+        provided.insert("count".to_string(), "many".to_string());
+        assert!(metadata.resolve_variables(&provided).is_err());
+    }
+}
@@ -0,0 +1,35 @@
+pub mod metadata;
+pub mod pack;
+pub mod render;
+pub mod sources;
+pub mod store;
+
+pub use metadata::*;
+pub use pack::*;
+pub use render::*;
+pub use sources::*;
+pub use store::*;
+
+use crate::error::{AskError, Result};
+use std::collections::HashMap;
+
+/// `key=value` 形式の変数指定を解析する
+pub fn parse_variables(vars: &[String]) -> Result<HashMap<String, String>> {
+    vars.iter()
+        .map(|var| {
+            let (key, value) = var.split_once('=').ok_or_else(|| {
+                AskError::TemplateError(format!("Invalid variable '{}': expected key=value", var))
+            })?;
+
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(AskError::TemplateError(format!(
+                    "Invalid variable '{}': name must not be empty",
+                    var
+                )));
+            }
+
+            Ok((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
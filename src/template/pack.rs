@@ -0,0 +1,295 @@
+use crate::error::{AskError, Result};
+use crate::template::store::{
+    exists_in, save_in, validate, validate_name, TemplateStore, TEMPLATE_EXTENSION,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// テンプレートパックの形式のバージョン
+pub const PACK_VERSION: u32 = 1;
+
+/// 複数のテンプレートをまとめた共有用のパック
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplatePack {
+    pub version: u32,
+    pub templates: Vec<PackedTemplate>,
+}
+
+/// パックに含まれるテンプレート（フロントマターを含むファイルの内容）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedTemplate {
+    pub name: String,
+    pub content: String,
+}
+
+/// パックのファイル形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackFormat {
+    /// `{"version": 1, "templates": [{"name": ..., "content": ...}]}`
+    Json,
+    /// `<name>.md` ファイルを並べた tar アーカイブ
+    Tar,
+}
+
+impl PackFormat {
+    /// ファイルの拡張子から形式を判別する
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(PackFormat::Json),
+            Some(ext) if ext.eq_ignore_ascii_case("tar") => Ok(PackFormat::Tar),
+            _ => Err(AskError::TemplateError(format!(
+                "Unsupported template pack '{}': use a .tar or .json file",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// 取り込むテンプレートと同じ名前のテンプレートが既にある場合の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictStrategy {
+    /// 既存のテンプレートを残す
+    #[default]
+    Skip,
+    /// 既存のテンプレートを上書きする
+    Overwrite,
+    /// `name-1`, `name-2`, ... のように別名で取り込む
+    Rename,
+}
+
+/// テンプレート1つ分の取り込み結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+    Created {
+        name: String,
+        path: PathBuf,
+    },
+    Overwritten {
+        name: String,
+        path: PathBuf,
+    },
+    Renamed {
+        from: String,
+        to: String,
+        path: PathBuf,
+    },
+    Skipped {
+        name: String,
+    },
+}
+
+impl TemplatePack {
+    /// ストアからパックを作成する（`names` が空の場合はすべてのテンプレート）
+    pub fn from_store(store: &TemplateStore, names: &[String]) -> Result<Self> {
+        let names = if names.is_empty() {
+            store.list()?
+        } else {
+            names.to_vec()
+        };
+
+        let templates = names
+            .iter()
+            .map(|name| {
+                let template = store.load(name)?;
+                Ok(PackedTemplate {
+                    name: template.name,
+                    content: template.content,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            version: PACK_VERSION,
+            templates,
+        })
+    }
+
+    /// ファイルからパックを読み込み、すべてのテンプレートを検証する
+    pub fn read_from(path: &Path) -> Result<Self> {
+        let format = PackFormat::from_path(path)?;
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes, format)
+    }
+
+    /// パックをファイルに書き出す
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let format = PackFormat::from_path(path)?;
+        std::fs::write(path, self.to_bytes(format)?)?;
+        Ok(())
+    }
+
+    /// バイト列からパックを読み込み、すべてのテンプレートを検証する
+    pub fn from_bytes(bytes: &[u8], format: PackFormat) -> Result<Self> {
+        let pack = match format {
+            PackFormat::Json => {
+                let pack: TemplatePack = serde_json::from_slice(bytes).map_err(|e| {
+                    AskError::TemplateError(format!("Invalid template pack: {}", e))
+                })?;
+                if pack.version > PACK_VERSION {
+                    return Err(AskError::TemplateError(format!(
+                        "Unsupported template pack version {} (this version of ask supports up to {})",
+                        pack.version, PACK_VERSION
+                    )));
+                }
+                pack
+            }
+            PackFormat::Tar => read_tar(bytes)?,
+        };
+
+        pack.validate()?;
+        Ok(pack)
+    }
+
+    /// パックを指定した形式のバイト列にする
+    pub fn to_bytes(&self, format: PackFormat) -> Result<Vec<u8>> {
+        match format {
+            PackFormat::Json => Ok(serde_json::to_vec_pretty(self)?),
+            PackFormat::Tar => write_tar(self),
+        }
+    }
+
+    /// 名前の重複と、各テンプレートの名前・内容を検証する
+    pub fn validate(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for template in &self.templates {
+            validate_name(&template.name)?;
+            if !seen.insert(template.name.as_str()) {
+                return Err(AskError::TemplateError(format!(
+                    "Template pack contains '{}' more than once",
+                    template.name
+                )));
+            }
+            validate(&template.name, &template.content)?;
+        }
+        Ok(())
+    }
+}
+
+/// パックのテンプレートをストアのデフォルトのディレクトリに取り込む
+///
+/// 名前の衝突は取り込み先のディレクトリだけで判断し、プロジェクトや共有の
+/// 検索パスにあるテンプレートには触れない。
+/// パック全体を検証してから書き込むため、不正なテンプレートが含まれている場合は
+/// 何も取り込まない。
+pub fn import_pack(
+    pack: &TemplatePack,
+    store: &TemplateStore,
+    strategy: ConflictStrategy,
+) -> Result<Vec<ImportOutcome>> {
+    pack.validate()?;
+    let dir = store.dir();
+
+    let pack_names: HashSet<&str> = pack.templates.iter().map(|t| t.name.as_str()).collect();
+    let mut outcomes = Vec::with_capacity(pack.templates.len());
+
+    for template in &pack.templates {
+        let name = &template.name;
+        if !exists_in(dir, name)? {
+            let path = save_in(dir, name, &template.content)?;
+            outcomes.push(ImportOutcome::Created {
+                name: name.clone(),
+                path,
+            });
+            continue;
+        }
+
+        let outcome = match strategy {
+            ConflictStrategy::Skip => ImportOutcome::Skipped { name: name.clone() },
+            ConflictStrategy::Overwrite => ImportOutcome::Overwritten {
+                name: name.clone(),
+                path: save_in(dir, name, &template.content)?,
+            },
+            ConflictStrategy::Rename => {
+                let new_name = available_name(dir, name, &pack_names)?;
+                ImportOutcome::Renamed {
+                    from: name.clone(),
+                    path: save_in(dir, &new_name, &template.content)?,
+                    to: new_name,
+                }
+            }
+        };
+        outcomes.push(outcome);
+    }
+
+    Ok(outcomes)
+}
+
+/// 取り込み先にもパックにも無い `name-N` を探す
+fn available_name(dir: &Path, name: &str, reserved: &HashSet<&str>) -> Result<String> {
+    for n in 1.. {
+        let candidate = format!("{}-{}", name, n);
+        if !reserved.contains(candidate.as_str()) && !exists_in(dir, &candidate)? {
+            return Ok(candidate);
+        }
+    }
+    unreachable!("ran out of candidate template names")
+}
+
+fn read_tar(bytes: &[u8]) -> Result<TemplatePack> {
+    let invalid = |e: std::io::Error| {
+        AskError::TemplateError(format!("Invalid template pack archive: {}", e))
+    };
+
+    let mut archive = tar::Archive::new(bytes);
+    let mut templates = Vec::new();
+
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path().map_err(invalid)?.into_owned();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(TEMPLATE_EXTENSION) {
+            continue;
+        }
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| {
+                AskError::TemplateError(format!(
+                    "Invalid template file name in pack: {}",
+                    path.display()
+                ))
+            })?
+            .to_string();
+
+        let mut content = String::new();
+        entry.read_to_string(&mut content).map_err(|_| {
+            AskError::TemplateError(format!(
+                "Template '{}' in pack is not valid UTF-8 text",
+                name
+            ))
+        })?;
+
+        templates.push(PackedTemplate { name, content });
+    }
+
+    Ok(TemplatePack {
+        version: PACK_VERSION,
+        templates,
+    })
+}
+
+fn write_tar(pack: &TemplatePack) -> Result<Vec<u8>> {
+    let mtime = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut builder = tar::Builder::new(Vec::new());
+    for template in &pack.templates {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(template.content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+
+        let path = format!("{}.{}", template.name, TEMPLATE_EXTENSION);
+        builder.append_data(&mut header, path, template.content.as_bytes())?;
+    }
+
+    Ok(builder.into_inner()?)
+}
@@ -0,0 +1,783 @@
+use crate::error::{AskError, Result};
+use std::collections::{BTreeSet, HashMap};
+
+/// インクルードの最大の深さ（循環の検出とは別の安全装置）
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// `{% include "name" %}` で読み込むテンプレートの取得元
+pub trait TemplateLoader {
+    /// 名前からテンプレートの本文（フロントマターを除いたもの）を取得する
+    fn load_body(&self, name: &str) -> Result<String>;
+}
+
+/// テンプレートを変数の値で展開する
+///
+/// 対応する構文:
+/// - `{{ var }}`、`{{ var | upper | indent(2) }}`
+/// - `{% if var %}` / `{% if not var %}` / `{% else %}` / `{% endif %}`
+/// - `{% for item in list %}` / `{% endfor %}`（カンマ区切りまたは JSON 配列）
+/// - `{% include "name" %}`（`loader` が必要）
+/// - `{# comment #}`
+///
+/// 値が与えられていない変数があった場合は、その名前をすべて含むエラーを返す。
+pub fn render(content: &str, variables: &HashMap<String, String>) -> Result<String> {
+    Renderer {
+        loader: None,
+        variables,
+    }
+    .render_root(None, content)
+}
+
+/// インクルードに対応してテンプレートを展開する
+pub fn render_with_loader(
+    name: &str,
+    content: &str,
+    variables: &HashMap<String, String>,
+    loader: &dyn TemplateLoader,
+) -> Result<String> {
+    Renderer {
+        loader: Some(loader),
+        variables,
+    }
+    .render_root(Some(name), content)
+}
+
+/// テンプレートで使われている変数名を出現順に（重複なしで）返す
+///
+/// ループ変数とインクルード先のテンプレートの変数は含まない。
+pub fn variables(content: &str) -> Result<Vec<String>> {
+    let nodes = parse(content)?;
+    let mut names = Vec::new();
+    collect_variables(&nodes, &mut Vec::new(), &mut names);
+    Ok(names)
+}
+
+/// 変数の値をリストとして解釈する（JSON 配列またはカンマ区切り）
+pub fn split_list(value: &str) -> Result<Vec<String>> {
+    let trimmed = value.trim();
+
+    if trimmed.starts_with('[') {
+        let items: Vec<serde_json::Value> = serde_json::from_str(trimmed).map_err(|e| {
+            AskError::TemplateError(format!("Invalid JSON array '{}': {}", trimmed, e))
+        })?;
+        return Ok(items
+            .into_iter()
+            .map(|item| match item {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            })
+            .collect());
+    }
+
+    Ok(trimmed
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+// ---------------------------------------------------------------------------
+// 構文木
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Variable {
+        name: String,
+        filters: Vec<Filter>,
+        line: usize,
+    },
+    If {
+        name: String,
+        negated: bool,
+        then_branch: Vec<Node>,
+        else_branch: Vec<Node>,
+    },
+    For {
+        item: String,
+        list: String,
+        body: Vec<Node>,
+        line: usize,
+    },
+    Include {
+        name: String,
+        line: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Upper,
+    Lower,
+    Trim,
+    Indent(usize),
+}
+
+impl Filter {
+    fn parse(spec: &str, line: usize) -> Result<Self> {
+        let spec = spec.trim();
+        let (name, argument) = match spec.split_once('(') {
+            Some((name, rest)) => {
+                let argument = rest.strip_suffix(')').ok_or_else(|| {
+                    syntax_error(line, format!("Unclosed filter argument in '{}'", spec))
+                })?;
+                (name.trim(), Some(argument.trim()))
+            }
+            None => (spec, None),
+        };
+
+        match (name, argument) {
+            ("upper", None) => Ok(Filter::Upper),
+            ("lower", None) => Ok(Filter::Lower),
+            ("trim", None) => Ok(Filter::Trim),
+            ("indent", None) => Ok(Filter::Indent(4)),
+            ("indent", Some(width)) => width.parse().map(Filter::Indent).map_err(|_| {
+                syntax_error(line, format!("indent expects a number, got '{}'", width))
+            }),
+            _ => Err(syntax_error(line, format!("Unknown filter '{}'", spec))),
+        }
+    }
+
+    fn apply(&self, value: String) -> String {
+        match self {
+            Filter::Upper => value.to_uppercase(),
+            Filter::Lower => value.to_lowercase(),
+            Filter::Trim => value.trim().to_string(),
+            Filter::Indent(width) => {
+                let padding = " ".repeat(*width);
+                value
+                    .split_inclusive('\n')
+                    .map(|line| {
+                        if line.trim().is_empty() {
+                            line.to_string()
+                        } else {
+                            format!("{}{}", padding, line)
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 字句解析
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Output(String, usize),
+    Tag(String, usize),
+}
+
+fn tokenize(content: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = content;
+    let mut line = 1;
+    // 現在の行にここまで空白以外の出力が無いか
+    let mut line_clean = true;
+
+    while let Some(start) = rest.find('{') {
+        let opener = &rest[start..];
+        let closer = if opener.starts_with("{{") {
+            "}}"
+        } else if opener.starts_with("{%") {
+            "%}"
+        } else if opener.starts_with("{#") {
+            "#}"
+        } else {
+            text.push_str(&rest[..start + 1]);
+            line += rest[..start + 1].matches('\n').count();
+            rest = &rest[start + 1..];
+            continue;
+        };
+
+        text.push_str(&rest[..start]);
+        line += rest[..start].matches('\n').count();
+        let tag_line = line;
+
+        let inner_start = start + 2;
+        let end = rest[inner_start..]
+            .find(closer)
+            .ok_or_else(|| syntax_error(tag_line, format!("Unclosed '{}'", &opener[..2])))?;
+        let inner = &rest[inner_start..inner_start + end];
+        line += inner.matches('\n').count();
+        rest = &rest[inner_start + end + 2..];
+
+        if closer == "}}" {
+            tokens.push(Token::Text(std::mem::take(&mut text)));
+            tokens.push(Token::Output(inner.trim().to_string(), tag_line));
+            line_clean = false;
+            continue;
+        }
+
+        // ブロックタグやコメントだけの行は、行ごと取り除く
+        let prefix_start = text.rfind('\n').map(|i| i + 1);
+        let prefix_clean = prefix_start.is_some() || line_clean;
+        let prefix_blank = text[prefix_start.unwrap_or(0)..].trim().is_empty();
+        let suffix_end = rest.find('\n').map(|i| i + 1).unwrap_or(rest.len());
+        let suffix_blank = rest[..suffix_end].trim().is_empty();
+
+        if prefix_clean && prefix_blank && suffix_blank {
+            text.truncate(prefix_start.unwrap_or(0));
+            line += rest[..suffix_end].matches('\n').count();
+            rest = &rest[suffix_end..];
+            line_clean = true;
+        } else {
+            line_clean = false;
+        }
+
+        if closer == "%}" {
+            tokens.push(Token::Text(std::mem::take(&mut text)));
+            tokens.push(Token::Tag(inner.trim().to_string(), tag_line));
+        }
+    }
+
+    text.push_str(rest);
+    tokens.push(Token::Text(text));
+    Ok(tokens)
+}
+
+// ---------------------------------------------------------------------------
+// 構文解析
+
+/// ブロックの終端を表すタグ（行番号付き）
+enum Terminator {
+    Else(usize),
+    EndIf(usize),
+    EndFor(usize),
+}
+
+impl Terminator {
+    fn line(&self) -> usize {
+        match self {
+            Terminator::Else(line) | Terminator::EndIf(line) | Terminator::EndFor(line) => *line,
+        }
+    }
+}
+
+fn parse(content: &str) -> Result<Vec<Node>> {
+    let tokens = tokenize(content)?;
+    let mut iter = tokens.into_iter();
+    let (nodes, terminator) = parse_block(&mut iter)?;
+
+    match terminator {
+        None => Ok(nodes),
+        Some(Terminator::Else(line)) => Err(syntax_error(line, "'else' without 'if'")),
+        Some(Terminator::EndIf(line)) => Err(syntax_error(line, "'endif' without 'if'")),
+        Some(Terminator::EndFor(line)) => Err(syntax_error(line, "'endfor' without 'for'")),
+    }
+}
+
+fn parse_block(tokens: &mut std::vec::IntoIter<Token>) -> Result<(Vec<Node>, Option<Terminator>)> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => {
+                if !text.is_empty() {
+                    nodes.push(Node::Text(text));
+                }
+            }
+            Token::Output(expression, line) => nodes.push(parse_output(&expression, line)?),
+            Token::Tag(tag, line) => {
+                let mut words = tag.split_whitespace();
+                let keyword = words.next().unwrap_or("");
+                let arguments: Vec<&str> = words.collect();
+
+                match keyword {
+                    "if" => nodes.push(parse_if(&arguments, line, tokens)?),
+                    "for" => nodes.push(parse_for(&arguments, line, tokens)?),
+                    "include" => nodes.push(parse_include(&tag, line)?),
+                    "else" => return Ok((nodes, Some(Terminator::Else(line)))),
+                    "endif" => return Ok((nodes, Some(Terminator::EndIf(line)))),
+                    "endfor" => return Ok((nodes, Some(Terminator::EndFor(line)))),
+                    _ => return Err(syntax_error(line, format!("Unknown tag '{}'", tag))),
+                }
+            }
+        }
+    }
+
+    Ok((nodes, None))
+}
+
+fn parse_output(expression: &str, line: usize) -> Result<Node> {
+    let mut parts = expression.split('|');
+    let name = parts.next().unwrap_or("").trim();
+    validate_variable_name(name, line)?;
+
+    let filters = parts
+        .map(|spec| Filter::parse(spec, line))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Node::Variable {
+        name: name.to_string(),
+        filters,
+        line,
+    })
+}
+
+fn parse_if(
+    arguments: &[&str],
+    line: usize,
+    tokens: &mut std::vec::IntoIter<Token>,
+) -> Result<Node> {
+    let (negated, name) = match arguments {
+        [name] => (false, *name),
+        ["not", name] => (true, *name),
+        _ => {
+            return Err(syntax_error(
+                line,
+                "Expected '{% if var %}' or '{% if not var %}'",
+            ))
+        }
+    };
+    validate_variable_name(name, line)?;
+
+    let (then_branch, terminator) = parse_block(tokens)?;
+    let else_branch = match terminator {
+        Some(Terminator::EndIf(_)) => Vec::new(),
+        Some(Terminator::Else(_)) => match parse_block(tokens)? {
+            (else_branch, Some(Terminator::EndIf(_))) => else_branch,
+            (_, Some(other)) => return Err(syntax_error(other.line(), "Expected 'endif'")),
+            (_, None) => return Err(syntax_error(line, "'if' is not closed with 'endif'")),
+        },
+        Some(Terminator::EndFor(inner)) => {
+            return Err(syntax_error(inner, "Expected 'endif', found 'endfor'"))
+        }
+        None => return Err(syntax_error(line, "'if' is not closed with 'endif'")),
+    };
+
+    Ok(Node::If {
+        name: name.to_string(),
+        negated,
+        then_branch,
+        else_branch,
+    })
+}
+
+fn parse_for(
+    arguments: &[&str],
+    line: usize,
+    tokens: &mut std::vec::IntoIter<Token>,
+) -> Result<Node> {
+    let [item, "in", list] = arguments else {
+        return Err(syntax_error(line, "Expected '{% for item in list %}'"));
+    };
+    validate_variable_name(item, line)?;
+    validate_variable_name(list, line)?;
+
+    match parse_block(tokens)? {
+        (body, Some(Terminator::EndFor(_))) => Ok(Node::For {
+            item: item.to_string(),
+            list: list.to_string(),
+            body,
+            line,
+        }),
+        (_, Some(other)) => Err(syntax_error(other.line(), "Expected 'endfor'")),
+        (_, None) => Err(syntax_error(line, "'for' is not closed with 'endfor'")),
+    }
+}
+
+fn parse_include(tag: &str, line: usize) -> Result<Node> {
+    let argument = tag["include".len()..].trim();
+    let name = argument
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .or_else(|| {
+            argument
+                .strip_prefix('\'')
+                .and_then(|rest| rest.strip_suffix('\''))
+        })
+        .ok_or_else(|| syntax_error(line, "Expected '{% include \"name\" %}'"))?;
+
+    Ok(Node::Include {
+        name: name.to_string(),
+        line,
+    })
+}
+
+fn validate_variable_name(name: &str, line: usize) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(syntax_error(
+            line,
+            format!("Invalid variable name '{}'", name),
+        ))
+    }
+}
+
+fn syntax_error(line: usize, message: impl std::fmt::Display) -> AskError {
+    AskError::TemplateError(format!("{} at line {}", message, line))
+}
+
+fn collect_variables(nodes: &[Node], locals: &mut Vec<String>, names: &mut Vec<String>) {
+    let add = |name: &str, locals: &Vec<String>, names: &mut Vec<String>| {
+        if !locals.iter().any(|local| local == name) && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    };
+
+    for node in nodes {
+        match node {
+            Node::Text(_) | Node::Include { .. } => {}
+            Node::Variable { name, .. } => add(name, locals, names),
+            Node::If {
+                name,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                add(name, locals, names);
+                collect_variables(then_branch, locals, names);
+                collect_variables(else_branch, locals, names);
+            }
+            Node::For {
+                item, list, body, ..
+            } => {
+                add(list, locals, names);
+                locals.push(item.clone());
+                collect_variables(body, locals, names);
+                locals.pop();
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 展開
+
+struct Renderer<'a> {
+    loader: Option<&'a dyn TemplateLoader>,
+    variables: &'a HashMap<String, String>,
+}
+
+/// 展開中の状態
+struct State {
+    /// ループ変数（内側のものほど後ろ）
+    locals: Vec<(String, String)>,
+    /// インクルード中のテンプレート名
+    include_stack: Vec<String>,
+    unresolved: BTreeSet<String>,
+}
+
+impl<'a> Renderer<'a> {
+    fn render_root(&self, name: Option<&str>, content: &str) -> Result<String> {
+        let nodes = parse(content)?;
+        let mut state = State {
+            locals: Vec::new(),
+            include_stack: name.map(|n| vec![n.to_string()]).unwrap_or_default(),
+            unresolved: BTreeSet::new(),
+        };
+
+        let mut output = String::with_capacity(content.len());
+        self.render_nodes(&nodes, &mut state, &mut output)?;
+
+        if !state.unresolved.is_empty() {
+            return Err(AskError::TemplateError(format!(
+                "Unresolved template variables: {}",
+                state.unresolved.into_iter().collect::<Vec<_>>().join(", ")
+            )));
+        }
+
+        Ok(output)
+    }
+
+    fn lookup<'s>(&'s self, name: &str, state: &'s State) -> Option<&'s str> {
+        state
+            .locals
+            .iter()
+            .rev()
+            .find(|(local, _)| local == name)
+            .map(|(_, value)| value.as_str())
+            .or_else(|| self.variables.get(name).map(String::as_str))
+    }
+
+    fn render_nodes(&self, nodes: &[Node], state: &mut State, output: &mut String) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+
+                Node::Variable { name, filters, .. } => match self.lookup(name, state) {
+                    Some(value) => {
+                        let value = filters
+                            .iter()
+                            .fold(value.to_string(), |value, filter| filter.apply(value));
+                        output.push_str(&value);
+                    }
+                    None => {
+                        state.unresolved.insert(name.clone());
+                    }
+                },
+
+                Node::If {
+                    name,
+                    negated,
+                    then_branch,
+                    else_branch,
+                } => {
+                    // 未定義の変数は偽として扱う
+                    let truthy = self.lookup(name, state).map(is_truthy).unwrap_or(false);
+                    let branch = if truthy != *negated {
+                        then_branch
+                    } else {
+                        else_branch
+                    };
+                    self.render_nodes(branch, state, output)?;
+                }
+
+                Node::For {
+                    item,
+                    list,
+                    body,
+                    line,
+                } => {
+                    let Some(value) = self.lookup(list, state) else {
+                        state.unresolved.insert(list.clone());
+                        continue;
+                    };
+                    let items = split_list(value).map_err(|e| match e {
+                        AskError::TemplateError(message) => syntax_error(
+                            *line,
+                            format!("Cannot iterate over '{}': {}", list, message),
+                        ),
+                        other => other,
+                    })?;
+
+                    for value in items {
+                        state.locals.push((item.clone(), value));
+                        let result = self.render_nodes(body, state, output);
+                        state.locals.pop();
+                        result?;
+                    }
+                }
+
+                Node::Include { name, line } => self.render_include(name, *line, state, output)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_include(
+        &self,
+        name: &str,
+        line: usize,
+        state: &mut State,
+        output: &mut String,
+    ) -> Result<()> {
+        let loader = self
+            .loader
+            .ok_or_else(|| syntax_error(line, format!("Cannot include '{}' here", name)))?;
+
+        if state.include_stack.iter().any(|included| included == name) {
+            let mut chain = state.include_stack.clone();
+            chain.push(name.to_string());
+            return Err(AskError::TemplateError(format!(
+                "Include cycle detected: {}",
+                chain.join(" -> ")
+            )));
+        }
+        if state.include_stack.len() >= MAX_INCLUDE_DEPTH {
+            return Err(syntax_error(line, "Includes are nested too deeply"));
+        }
+
+        let body = loader.load_body(name).map_err(|e| match e {
+            AskError::TemplateError(message) => {
+                syntax_error(line, format!("Failed to include '{}': {}", name, message))
+            }
+            other => other,
+        })?;
+        let nodes = parse(&body).map_err(|e| match e {
+            AskError::TemplateError(message) => {
+                AskError::TemplateError(format!("In included template '{}': {}", name, message))
+            }
+            other => other,
+        })?;
+
+        state.include_stack.push(name.to_string());
+        let result = self.render_nodes(&nodes, state, output);
+        state.include_stack.pop();
+        result
+    }
+}
+
+fn is_truthy(value: &str) -> bool {
+    !matches!(
+        value.trim().to_lowercase().as_str(),
+        "" | "false" | "0" | "no" | "off" | "[]"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    struct MapLoader(HashMap<&'static str, &'static str>);
+
+    impl TemplateLoader for MapLoader {
+        fn load_body(&self, name: &str) -> Result<String> {
+            self.0
+                .get(name)
+                .map(|body| body.to_string())
+                .ok_or_else(|| AskError::TemplateError(format!("Template not found: {}", name)))
+        }
+    }
+
+    #[test]
+    fn test_render_substitutes_variables() {
+        let result = render(
+            "Review this {{ lang }} code:\n{{code}}",
+            &vars(&[("lang", "Rust"), ("code", "fn main() {}")]),
+        )
+        .unwrap();
+        assert_eq!(result, "Review this Rust code:\nfn main() {}");
+    }
+
+    #[test]
+    fn test_render_reports_all_unresolved_variables() {
+        let err = render("{{a}} {{b}} {{a}} {{c}}", &vars(&[("b", "x")])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Template error: Unresolved template variables: a, c"
+        );
+    }
+
+    #[test]
+    fn test_render_rejects_unclosed_braces() {
+        let err = render("line one\n{{ name", &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_variables_in_order() {
+        assert_eq!(
+            variables("{{b}} {{a}} {{b}}").unwrap(),
+            vec!["b".to_string(), "a".to_string()]
+        );
+        assert_eq!(
+            variables("{% if strict %}{% for f in files %}{{f}}{{ext}}{% endfor %}{% endif %}")
+                .unwrap(),
+            vec!["strict".to_string(), "files".to_string(), "ext".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let result = render(
+            "{{ name | trim | upper }}|{{ code | indent(2) }}",
+            &vars(&[("name", "  ada "), ("code", "a\nb")]),
+        )
+        .unwrap();
+        assert_eq!(result, "ADA|  a\n  b");
+
+        assert!(render("{{ name | shout }}", &vars(&[("name", "x")])).is_err());
+    }
+
+    #[test]
+    fn test_if_else_blocks() {
+        let template = "Review the code.\n{% if strict %}\nBe strict.\n{% else %}\nBe kind.\n{% endif %}\nThanks";
+        assert_eq!(
+            render(template, &vars(&[("strict", "true")])).unwrap(),
+            "Review the code.\nBe strict.\nThanks"
+        );
+        assert_eq!(
+            render(template, &vars(&[("strict", "false")])).unwrap(),
+            "Review the code.\nBe kind.\nThanks"
+        );
+        // 未定義の変数は偽として扱う
+        assert_eq!(
+            render(template, &HashMap::new()).unwrap(),
+            "Review the code.\nBe kind.\nThanks"
+        );
+        assert_eq!(
+            render("{% if not quiet %}loud{% endif %}", &HashMap::new()).unwrap(),
+            "loud"
+        );
+    }
+
+    #[test]
+    fn test_for_loops() {
+        let template = "{% for file in files %}\n- {{ file }}\n{% endfor %}\n";
+        assert_eq!(
+            render(template, &vars(&[("files", "a.rs, b.rs,")])).unwrap(),
+            "- a.rs\n- b.rs\n"
+        );
+        assert_eq!(
+            render(template, &vars(&[("files", r#"["x, y", 2]"#)])).unwrap(),
+            "- x, y\n- 2\n"
+        );
+        assert!(render(template, &vars(&[("files", "[broken")])).is_err());
+        assert!(render(template, &HashMap::new())
+            .unwrap_err()
+            .to_string()
+            .contains("files"));
+    }
+
+    #[test]
+    fn test_unbalanced_blocks() {
+        assert!(render("{% if a %}x", &HashMap::new()).is_err());
+        assert!(render("{% endif %}", &HashMap::new()).is_err());
+        assert!(render("{% for a in b %}{% endif %}", &HashMap::new()).is_err());
+        assert!(render("{% unknown %}", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_comments_are_removed() {
+        assert_eq!(render("a\n{# note #}\nb", &HashMap::new()).unwrap(), "a\nb");
+    }
+
+    #[test]
+    fn test_include() {
+        let loader = MapLoader(HashMap::from([
+            ("header", "You are reviewing {{ lang }}.\n"),
+            ("footer", "{% include \"signature\" %}"),
+            ("signature", "-- ask"),
+        ]));
+
+        let result = render_with_loader(
+            "main",
+            "{% include \"header\" %}\n{{ code }}\n{% include 'footer' %}",
+            &vars(&[("lang", "Rust"), ("code", "fn main() {}")]),
+            &loader,
+        )
+        .unwrap();
+        assert_eq!(result, "You are reviewing Rust.\nfn main() {}\n-- ask");
+
+        let err = render_with_loader("main", "{% include \"missing\" %}", &vars(&[]), &loader)
+            .unwrap_err();
+        assert!(err.to_string().contains("missing"));
+
+        assert!(render("{% include \"header\" %}", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let loader = MapLoader(HashMap::from([
+            ("a", "{% include \"b\" %}"),
+            ("b", "{% include \"a\" %}"),
+        ]));
+
+        let err =
+            render_with_loader("a", "{% include \"b\" %}", &HashMap::new(), &loader).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Template error: Include cycle detected: a -> b -> a"
+        );
+    }
+}
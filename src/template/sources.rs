@@ -0,0 +1,437 @@
+use crate::error::{AskError, Result};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// 1つの変数に読み込める最大サイズ（バイト）
+pub const MAX_VARIABLE_BYTES: usize = 512 * 1024;
+
+/// 変数の値の取得元
+///
+/// - `@path/to/file` ファイルの内容（言語が分かる場合はコードブロックで囲む）
+/// - `-` 標準入力
+/// - `$(command)` コマンドの標準出力（`--allow-exec` が必要）
+/// - `@@text` 先頭の `@` をエスケープした文字列
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableSource {
+    Literal(String),
+    File(String),
+    Stdin,
+    Command(String),
+}
+
+/// 変数の値を解決するときの設定
+#[derive(Debug, Clone, Default)]
+pub struct SourceOptions {
+    /// `$(command)` の実行を許可する
+    pub allow_exec: bool,
+    /// パイプされた標準入力の内容
+    pub stdin: Option<String>,
+}
+
+impl VariableSource {
+    /// `--var` の値から取得元を判別する
+    pub fn parse(value: &str) -> Self {
+        if let Some(literal) = value.strip_prefix("@@") {
+            return VariableSource::Literal(format!("@{}", literal));
+        }
+        if let Some(path) = value.strip_prefix('@') {
+            return VariableSource::File(path.to_string());
+        }
+        if value == "-" {
+            return VariableSource::Stdin;
+        }
+        if let Some(command) = value
+            .strip_prefix("$(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            return VariableSource::Command(command.to_string());
+        }
+        VariableSource::Literal(value.to_string())
+    }
+}
+
+/// 変数の取得元を解決し、値を読み込む
+///
+/// ファイルから読み込んだ変数 `name` には、`name_path` と `name_lang` も追加する
+/// （すでに指定されている場合は上書きしない）。
+pub fn resolve_sources(
+    variables: HashMap<String, String>,
+    options: &SourceOptions,
+) -> Result<HashMap<String, String>> {
+    let stdin_readers: Vec<&String> = variables
+        .iter()
+        .filter(|(_, value)| VariableSource::parse(value) == VariableSource::Stdin)
+        .map(|(name, _)| name)
+        .collect();
+    if stdin_readers.len() > 1 {
+        return Err(AskError::TemplateError(
+            "Only one variable can read from stdin ('-')".to_string(),
+        ));
+    }
+
+    let mut resolved = HashMap::new();
+    let mut companions = HashMap::new();
+
+    for (name, value) in &variables {
+        let value = match VariableSource::parse(value) {
+            VariableSource::Literal(value) => value,
+            VariableSource::Stdin => {
+                let input = options.stdin.as_ref().ok_or_else(|| {
+                    AskError::TemplateError(format!(
+                        "Variable '{}' reads from stdin, but no input was piped",
+                        name
+                    ))
+                })?;
+                check_size(name, input.len())?;
+                input.clone()
+            }
+            VariableSource::File(path) => {
+                let (content, language) = read_file(name, &path)?;
+                companions.insert(format!("{}_path", name), path.clone());
+                if let Some(language) = language {
+                    companions.insert(format!("{}_lang", name), language.to_string());
+                }
+                match language {
+                    Some(language) => fence_code(&content, language),
+                    None => content,
+                }
+            }
+            VariableSource::Command(command) => {
+                if !options.allow_exec {
+                    return Err(AskError::TemplateError(format!(
+                        "Variable '{}' runs a command; pass --allow-exec to allow this",
+                        name
+                    )));
+                }
+                run_command(name, &command)?
+            }
+        };
+        resolved.insert(name.clone(), value);
+    }
+
+    for (name, value) in companions {
+        if !variables.contains_key(&name) {
+            resolved.insert(name, value);
+        }
+    }
+
+    Ok(resolved)
+}
+
+fn check_size(name: &str, size: usize) -> Result<()> {
+    if size > MAX_VARIABLE_BYTES {
+        return Err(AskError::TemplateError(format!(
+            "Variable '{}' is too large ({} bytes, limit is {} bytes)",
+            name, size, MAX_VARIABLE_BYTES
+        )));
+    }
+    Ok(())
+}
+
+fn read_file(name: &str, path: &str) -> Result<(String, Option<&'static str>)> {
+    let metadata = std::fs::metadata(path).map_err(|e| {
+        AskError::TemplateError(format!(
+            "Cannot read '{}' for variable '{}': {}",
+            path, name, e
+        ))
+    })?;
+    check_size(name, metadata.len() as usize)?;
+
+    // 読み込む間に大きくなったファイルやデバイスファイルに備えて、読み込む量も制限する
+    let bytes = read_limited(name, std::fs::File::open(path)?)?;
+    let content = String::from_utf8(bytes).map_err(|_| {
+        AskError::TemplateError(format!(
+            "File '{}' for variable '{}' is not valid UTF-8 text",
+            path, name
+        ))
+    })?;
+
+    Ok((content, detect_language(Path::new(path))))
+}
+
+/// 最大サイズを1バイト超えるところまで読み、超えていればエラーにする
+fn read_limited(name: &str, reader: impl Read) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader
+        .take(MAX_VARIABLE_BYTES as u64 + 1)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() > MAX_VARIABLE_BYTES {
+        return Err(AskError::TemplateError(format!(
+            "Variable '{}' is too large (more than {} bytes)",
+            name, MAX_VARIABLE_BYTES
+        )));
+    }
+    Ok(bytes)
+}
+
+fn run_command(name: &str, command: &str) -> Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    let mut child = shell
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 標準エラー出力は、パイプが詰まってコマンドが止まらないよう別のスレッドで読む
+    let stderr = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut head = Vec::new();
+            let _ = (&mut stderr).take(4096).read_to_end(&mut head);
+            let _ = std::io::copy(&mut stderr, &mut std::io::sink());
+            head
+        })
+    });
+
+    let stdout = match child.stdout.take().map(|stdout| read_limited(name, stdout)) {
+        Some(Ok(stdout)) => stdout,
+        Some(Err(e)) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+        None => Vec::new(),
+    };
+    let status = child.wait()?;
+    let stderr = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(AskError::TemplateError(format!(
+            "Command for variable '{}' failed ({}): {}",
+            name,
+            status,
+            stderr.trim()
+        )));
+    }
+
+    let stdout = String::from_utf8(stdout).map_err(|_| {
+        AskError::TemplateError(format!(
+            "Output of the command for variable '{}' is not valid UTF-8 text",
+            name
+        ))
+    })?;
+
+    // シェルのコマンド置換と同様に末尾の改行を取り除く
+    Ok(stdout.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// 内容をコードブロックで囲む（内容にバッククォートの連続があれば、それより長いフェンスを使う）
+pub fn fence_code(content: &str, language: &str) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+
+    let newline = if content.ends_with('\n') { "" } else { "\n" };
+    format!("{}{}\n{}{}{}", fence, language, content, newline, fence)
+}
+
+/// ファイル名からコードブロックの言語を推測する
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    let file_name = path.file_name()?.to_str()?;
+    match file_name {
+        "Dockerfile" => return Some("dockerfile"),
+        "Makefile" | "makefile" | "GNUmakefile" => return Some("makefile"),
+        _ => {}
+    }
+
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "dart" => "dart",
+        "lua" => "lua",
+        "pl" => "perl",
+        "r" => "r",
+        "ex" | "exs" => "elixir",
+        "hs" => "haskell",
+        "sh" | "bash" => "bash",
+        "zsh" => "zsh",
+        "fish" => "fish",
+        "ps1" => "powershell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "proto" => "protobuf",
+        "tf" => "hcl",
+        "diff" | "patch" => "diff",
+        _ => return None,
+    };
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sources() {
+        assert_eq!(
+            VariableSource::parse("@src/main.rs"),
+            VariableSource::File("src/main.rs".to_string())
+        );
+        assert_eq!(VariableSource::parse("-"), VariableSource::Stdin);
+        assert_eq!(
+            VariableSource::parse("$(git diff --staged)"),
+            VariableSource::Command("git diff --staged".to_string())
+        );
+        assert_eq!(
+            VariableSource::parse("@@handle"),
+            VariableSource::Literal("@handle".to_string())
+        );
+        assert_eq!(
+            VariableSource::parse("plain text"),
+            VariableSource::Literal("plain text".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language(Path::new("src/main.rs")), Some("rust"));
+        assert_eq!(detect_language(Path::new("app.TSX")), Some("tsx"));
+        assert_eq!(
+            detect_language(Path::new("docker/Dockerfile")),
+            Some("dockerfile")
+        );
+        assert_eq!(detect_language(Path::new("notes.txt")), None);
+        assert_eq!(detect_language(Path::new("README")), None);
+    }
+
+    #[test]
+    fn test_fence_code() {
+        assert_eq!(
+            fence_code("fn main() {}", "rust"),
+            "```rust\nfn main() {}\n```"
+        );
+        assert_eq!(
+            fence_code("```\nnested\n```\n", "markdown"),
+            "````markdown\n```\nnested\n```\n````"
+        );
+    }
+
+    #[test]
+    fn test_stdin_source() {
+        let variables = HashMap::from([("input".to_string(), "-".to_string())]);
+
+        let options = SourceOptions {
+            allow_exec: false,
+            stdin: Some("piped text".to_string()),
+        };
+        assert_eq!(
+            resolve_sources(variables.clone(), &options).unwrap()["input"],
+            "piped text"
+        );
+
+        assert!(resolve_sources(variables, &SourceOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_file_source() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let code = dir.path().join("main.rs");
+        std::fs::write(&code, "fn main() {}\n").unwrap();
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, "plain notes").unwrap();
+        let code_path = code.to_string_lossy().into_owned();
+
+        let variables = HashMap::from([
+            ("code".to_string(), format!("@{}", code_path)),
+            ("notes".to_string(), format!("@{}", notes.display())),
+            ("notes_lang".to_string(), "text".to_string()),
+        ]);
+        let resolved = resolve_sources(variables, &SourceOptions::default()).unwrap();
+
+        // 言語が分かるファイルはコードブロックで囲み、パスと言語も変数にする
+        assert_eq!(resolved["code"], "```rust\nfn main() {}\n```");
+        assert_eq!(resolved["code_path"], code_path);
+        assert_eq!(resolved["code_lang"], "rust");
+
+        // 言語が分からないファイルはそのまま、指定済みの変数は上書きしない
+        assert_eq!(resolved["notes"], "plain notes");
+        assert_eq!(resolved["notes_lang"], "text");
+        assert!(resolved.contains_key("notes_path"));
+
+        let missing = HashMap::from([("code".to_string(), "@/no/such/file.rs".to_string())]);
+        assert!(resolve_sources(missing, &SourceOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let large = dir.path().join("large.txt");
+        std::fs::write(&large, "x".repeat(MAX_VARIABLE_BYTES + 1)).unwrap();
+        let variables = HashMap::from([("big".to_string(), format!("@{}", large.display()))]);
+        let err = resolve_sources(variables, &SourceOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("too large"));
+
+        let variables = HashMap::from([("big".to_string(), "-".to_string())]);
+        let options = SourceOptions {
+            allow_exec: false,
+            stdin: Some("x".repeat(MAX_VARIABLE_BYTES + 1)),
+        };
+        assert!(resolve_sources(variables, &options).is_err());
+
+        // 終わらない出力も、制限を超えたところで止める
+        #[cfg(unix)]
+        {
+            let variables = HashMap::from([("big".to_string(), "$(yes)".to_string())]);
+            let options = SourceOptions {
+                allow_exec: true,
+                stdin: None,
+            };
+            let err = resolve_sources(variables, &options).unwrap_err();
+            assert!(err.to_string().contains("too large"));
+        }
+    }
+
+    #[test]
+    fn test_command_requires_allow_exec() {
+        let variables = HashMap::from([("out".to_string(), "$(echo hello)".to_string())]);
+
+        let err = resolve_sources(variables.clone(), &SourceOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("--allow-exec"));
+
+        #[cfg(unix)]
+        {
+            let options = SourceOptions {
+                allow_exec: true,
+                stdin: None,
+            };
+            assert_eq!(
+                resolve_sources(variables, &options).unwrap()["out"],
+                "hello"
+            );
+        }
+    }
+}
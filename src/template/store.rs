@@ -0,0 +1,306 @@
+use crate::config::TemplateConfig;
+use crate::error::{AskError, Result};
+use crate::template::metadata::{parse_front_matter, TemplateMetadata};
+use crate::template::render::{render_with_loader, TemplateLoader};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// テンプレートファイルの拡張子
+pub const TEMPLATE_EXTENSION: &str = "md";
+
+/// 保存されているテンプレート
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    /// フロントマターを含むファイルの内容
+    pub content: String,
+    pub metadata: TemplateMetadata,
+    /// フロントマターを除いた本文
+    pub body: String,
+}
+
+impl Template {
+    /// ファイルの内容からテンプレートを作成する
+    pub fn parse(name: &str, content: &str) -> Result<Self> {
+        let (metadata, body) = parse_front_matter(content)
+            .map_err(|e| AskError::TemplateError(format!("{}: {}", name, inner_message(e))))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            content: content.to_string(),
+            metadata,
+            body,
+        })
+    }
+
+    /// 変数を検証してデフォルト値を補い、本文を展開する
+    ///
+    /// `{% include %}` は `loader`（通常はテンプレートストア）から読み込む。
+    pub fn render(
+        &self,
+        provided: &HashMap<String, String>,
+        loader: &dyn TemplateLoader,
+    ) -> Result<String> {
+        let variables = self.metadata.resolve_variables(provided)?;
+        render_with_loader(&self.name, &self.body, &variables, loader)
+    }
+
+    /// 一覧表示用の短い説明
+    pub fn summary(&self) -> &str {
+        self.metadata.description.as_deref().unwrap_or_else(|| {
+            self.body
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or("")
+        })
+    }
+}
+
+/// テンプレートの内容を検証する（フロントマターと本文の構文）
+pub fn validate(name: &str, content: &str) -> Result<Template> {
+    let template = Template::parse(name, content)?;
+    crate::template::render::variables(&template.body)
+        .map_err(|e| AskError::TemplateError(format!("{}: {}", name, inner_message(e))))?;
+    Ok(template)
+}
+
+fn inner_message(error: AskError) -> String {
+    match error {
+        AskError::TemplateError(message) => message,
+        other => other.to_string(),
+    }
+}
+
+/// プロジェクトローカルのテンプレートディレクトリ（カレントディレクトリから親へ探す）
+pub const PROJECT_TEMPLATE_DIR: &str = ".ask/templates";
+
+/// 一覧表示用のテンプレートの場所
+#[derive(Debug, Clone)]
+pub struct TemplateEntry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// テンプレートをディレクトリ内のファイルとして管理するストア
+///
+/// 複数の検索パスを持ち、同じ名前のテンプレートは先にあるディレクトリのものが優先される。
+/// 新しいテンプレートはデフォルトのディレクトリ（検索パスの最後）に保存する。
+pub struct TemplateStore {
+    search_paths: Vec<PathBuf>,
+    create_dir: bool,
+}
+
+impl TemplateStore {
+    /// 指定したディレクトリだけを使うストアを作成する
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            search_paths: vec![dir.into()],
+            create_dir: true,
+        }
+    }
+
+    /// 検索パス（優先度の高い順）とデフォルトのディレクトリからストアを作成する
+    pub fn with_search_paths<P: Into<PathBuf>>(search_paths: Vec<PathBuf>, default_dir: P) -> Self {
+        let default_dir = default_dir.into();
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for path in search_paths {
+            // 重複したディレクトリは最も優先度の高い位置だけ残す
+            if path != default_dir && !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths.push(default_dir);
+
+        Self {
+            search_paths: paths,
+            create_dir: true,
+        }
+    }
+
+    /// 設定からストアを作成する
+    ///
+    /// 検索の優先順位は、カレントディレクトリに近い `.ask/templates`、設定の
+    /// `search_paths`、`default_template_dir` の順。
+    /// `auto_load` が無効な場合、デフォルトのディレクトリは自動作成しない。
+    pub fn from_config(config: &TemplateConfig) -> Self {
+        let mut search_paths = std::env::current_dir()
+            .map(|cwd| discover_project_dirs(&cwd))
+            .unwrap_or_default();
+        search_paths.extend(config.search_paths.iter().map(PathBuf::from));
+
+        let mut store = Self::with_search_paths(search_paths, &config.default_template_dir);
+        store.create_dir = config.auto_load;
+        store
+    }
+
+    /// 新しいテンプレートを保存するデフォルトのディレクトリ
+    pub fn dir(&self) -> &Path {
+        self.search_paths
+            .last()
+            .expect("template store always has a default directory")
+    }
+
+    /// 検索パス（優先度の高い順）
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// デフォルトのディレクトリに保存する場合のテンプレートファイルのパス
+    pub fn path_for(&self, name: &str) -> Result<PathBuf> {
+        template_path(self.dir(), name)
+    }
+
+    /// テンプレートが保存されている場所を検索パスの順に探す
+    pub fn locate(&self, name: &str) -> Result<Option<PathBuf>> {
+        validate_name(name)?;
+        for dir in &self.search_paths {
+            let path = template_path(dir, name)?;
+            if path.is_file() {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    /// テンプレートの一覧を名前順に取得する（優先されるものだけ）
+    pub fn list_entries(&self) -> Result<Vec<TemplateEntry>> {
+        let mut entries: Vec<TemplateEntry> = Vec::new();
+
+        for dir in &self.search_paths {
+            for name in list_dir(dir)? {
+                if !entries.iter().any(|entry| entry.name == name) {
+                    let path = template_path(dir, &name)?;
+                    entries.push(TemplateEntry { name, path });
+                }
+            }
+        }
+
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    /// テンプレート名の一覧を名前順に取得する
+    pub fn list(&self) -> Result<Vec<String>> {
+        Ok(self
+            .list_entries()?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect())
+    }
+
+    /// テンプレートが存在するか
+    pub fn exists(&self, name: &str) -> Result<bool> {
+        Ok(self.locate(name)?.is_some())
+    }
+
+    /// テンプレートを読み込む
+    pub fn load(&self, name: &str) -> Result<Template> {
+        let path = self
+            .locate(name)?
+            .ok_or_else(|| AskError::TemplateError(format!("Template not found: {}", name)))?;
+
+        Template::parse(name, &std::fs::read_to_string(path)?)
+    }
+
+    /// テンプレートを保存する
+    ///
+    /// 既存のテンプレートはその場所で上書きし、新しいテンプレートはデフォルトの
+    /// ディレクトリに保存する。
+    pub fn save(&self, name: &str, content: &str) -> Result<PathBuf> {
+        if let Some(path) = self.locate(name)? {
+            std::fs::write(&path, content)?;
+            return Ok(path);
+        }
+
+        if !self.dir().exists() && !self.create_dir {
+            return Err(AskError::TemplateError(format!(
+                "Template directory does not exist: {}",
+                self.dir().display()
+            )));
+        }
+
+        save_in(self.dir(), name, content)
+    }
+
+    /// テンプレートを削除する
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self
+            .locate(name)?
+            .ok_or_else(|| AskError::TemplateError(format!("Template not found: {}", name)))?;
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
+
+/// 指定したディレクトリにテンプレートを保存する（ディレクトリが無ければ作成する）
+pub fn save_in(dir: &Path, name: &str, content: &str) -> Result<PathBuf> {
+    let path = template_path(dir, name)?;
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// 指定したディレクトリにテンプレートがあるか（ほかの検索パスは見ない）
+pub fn exists_in(dir: &Path, name: &str) -> Result<bool> {
+    Ok(template_path(dir, name)?.is_file())
+}
+
+/// `start` から親ディレクトリへ向かって `.ask/templates` を探す（近い順）
+pub fn discover_project_dirs(start: &Path) -> Vec<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_TEMPLATE_DIR))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+fn template_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+    Ok(dir.join(format!("{}.{}", name, TEMPLATE_EXTENSION)))
+}
+
+fn list_dir(dir: &Path) -> Result<Vec<String>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(TEMPLATE_EXTENSION) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            if validate_name(name).is_ok() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+impl TemplateLoader for TemplateStore {
+    fn load_body(&self, name: &str) -> Result<String> {
+        Ok(self.load(name)?.body)
+    }
+}
+
+/// テンプレート名を検証する（パス区切りなどを含む名前は受け付けない）
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AskError::TemplateError(format!(
+            "Invalid template name '{}': use letters, digits, '-', '_' or '.'",
+            name
+        )))
+    }
+}
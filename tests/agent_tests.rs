@@ -0,0 +1,235 @@
+use ask::agent::{local_tools, AutoApprover, Sandbox, ToolContext};
+use ask::client::{ToolOutput, ToolSet};
+use ask::config::AgentConfig;
+use serde_json::json;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+fn workspace() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+    std::fs::write(
+        dir.path().join("src/main.rs"),
+        "fn main() {\n    println!(\"hello\");\n}\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("README.md"), "# Demo\nSay hello\n").unwrap();
+    std::fs::write(dir.path().join(".git/config"), "hello from git\n").unwrap();
+    dir
+}
+
+fn tools(dir: &TempDir, approve: bool) -> ToolSet {
+    local_tools(Arc::new(ToolContext {
+        sandbox: Sandbox::new(dir.path()).unwrap(),
+        config: AgentConfig::default(),
+        approver: Arc::new(AutoApprover(approve)),
+        command_approver: Arc::new(AutoApprover(approve)),
+    }))
+}
+
+async fn call(tools: &ToolSet, name: &str, input: serde_json::Value) -> ToolOutput {
+    tools.dispatch("toolu_test", name, &input).await.output
+}
+
+#[tokio::test]
+async fn test_read_and_list() {
+    let dir = workspace();
+    let tools = tools(&dir, false);
+
+    let output = call(
+        &tools,
+        "read_file",
+        json!({"path": "src/main.rs", "offset": 2, "limit": 1}),
+    )
+    .await;
+    assert_eq!(output, ToolOutput::text("    println!(\"hello\");"));
+
+    let output = call(&tools, "list_directory", json!({})).await;
+    assert_eq!(output, ToolOutput::text(".git/\nREADME.md\nsrc/"));
+
+    let output = call(&tools, "read_file", json!({"path": "../outside.txt"})).await;
+    assert!(output.is_error);
+    assert!(output.content.contains("outside the sandbox"));
+}
+
+#[tokio::test]
+async fn test_grep_skips_hidden_directories() {
+    let dir = workspace();
+    let tools = tools(&dir, false);
+
+    let output = call(
+        &tools,
+        "grep",
+        json!({"pattern": "HELLO", "case_insensitive": true}),
+    )
+    .await;
+    assert_eq!(
+        output,
+        ToolOutput::text("README.md:2: Say hello\nsrc/main.rs:2:     println!(\"hello\");")
+    );
+
+    let output = call(&tools, "grep", json!({"pattern": "("})).await;
+    assert!(output.is_error);
+}
+
+#[tokio::test]
+async fn test_write_requires_approval() {
+    let dir = workspace();
+
+    let output = call(
+        &tools(&dir, false),
+        "write_file",
+        json!({"path": "notes/todo.md", "content": "- test\n"}),
+    )
+    .await;
+    assert!(output.is_error);
+    assert!(output.content.contains("declined"));
+    assert!(!dir.path().join("notes").exists());
+
+    let output = call(
+        &tools(&dir, true),
+        "write_file",
+        json!({"path": "notes/todo.md", "content": "- test\n"}),
+    )
+    .await;
+    assert_eq!(output, ToolOutput::text("Wrote 7 bytes to notes/todo.md"));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("notes/todo.md")).unwrap(),
+        "- test\n"
+    );
+}
+
+#[tokio::test]
+async fn test_run_command_allowlist() {
+    let dir = workspace();
+
+    let output = call(
+        &tools(&dir, true),
+        "run_command",
+        json!({"command": "ls src"}),
+    )
+    .await;
+    assert!(!output.is_error, "{}", output.content);
+    assert!(output.content.starts_with("exit code: 0"));
+    assert!(output.content.contains("main.rs"));
+
+    let output = call(
+        &tools(&dir, true),
+        "run_command",
+        json!({"command": "rm -rf src"}),
+    )
+    .await;
+    assert!(output.is_error);
+    assert!(output.content.contains("not in the allowed commands"));
+    assert!(dir.path().join("src/main.rs").exists());
+
+    let output = call(&tools(&dir, false), "run_command", json!({"command": "ls"})).await;
+    assert!(output.is_error);
+    assert!(output.content.contains("declined"));
+
+    let output = call(
+        &tools(&dir, true),
+        "run_command",
+        json!({"command": "ls; rm -rf src"}),
+    )
+    .await;
+    assert!(output.is_error);
+    assert!(dir.path().join("src/main.rs").exists());
+}
+
+#[tokio::test]
+async fn test_run_command_stays_in_sandbox() {
+    let dir = workspace();
+    let tools = tools(&dir, true);
+
+    for command in [
+        "cat /etc/passwd",
+        "cat ../../.ssh/id_rsa",
+        "head --lines=1 ../secret",
+        "grep -f/etc/passwd x",
+        "cat ~/.ssh/id_rsa",
+    ] {
+        let output = call(&tools, "run_command", json!({ "command": command })).await;
+        assert!(output.is_error, "{} should be rejected", command);
+        assert!(
+            output.content.contains("outside the sandbox"),
+            "{}",
+            output.content
+        );
+    }
+
+    // 外を指すシンボリックリンクも経由できない
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("/etc/passwd", dir.path().join("passwd")).unwrap();
+        let output = call(&tools, "run_command", json!({"command": "cat passwd"})).await;
+        assert!(output.is_error);
+    }
+
+    let output = call(
+        &tools,
+        "run_command",
+        json!({"command": "cat ./src/main.rs"}),
+    )
+    .await;
+    assert!(!output.is_error, "{}", output.content);
+    assert!(output.content.contains("println!"));
+}
+
+#[tokio::test]
+async fn test_run_command_rejects_escaping_options() {
+    let dir = workspace();
+    let mut config = AgentConfig::default();
+    config
+        .allowed_commands
+        .extend(["find".to_string(), "git".to_string()]);
+    let tools = local_tools(Arc::new(ToolContext {
+        sandbox: Sandbox::new(dir.path()).unwrap(),
+        config,
+        approver: Arc::new(AutoApprover(true)),
+        command_approver: Arc::new(AutoApprover(true)),
+    }));
+
+    for command in [
+        "find . -exec rm {} ;",
+        "find . -exec rm {} \\;",
+        "find . -delete",
+        "git -C / log",
+        "git --git-dir=/tmp/repo log",
+    ] {
+        let output = call(&tools, "run_command", json!({ "command": command })).await;
+        assert!(output.is_error, "{} should be rejected", command);
+    }
+    assert!(dir.path().join("src/main.rs").exists());
+
+    assert!(!AgentConfig::default()
+        .allowed_commands
+        .iter()
+        .any(|program| ["find", "git", "cargo"].contains(&program.as_str())));
+}
+
+#[tokio::test]
+async fn test_output_is_capped() {
+    let dir = workspace();
+    std::fs::write(dir.path().join("big.txt"), "x".repeat(10_000)).unwrap();
+    let config = AgentConfig {
+        max_output_bytes: 100,
+        ..AgentConfig::default()
+    };
+    let tools = local_tools(Arc::new(ToolContext {
+        sandbox: Sandbox::new(dir.path()).unwrap(),
+        config,
+        approver: Arc::new(AutoApprover(true)),
+        command_approver: Arc::new(AutoApprover(true)),
+    }));
+
+    let output = call(&tools, "read_file", json!({"path": "big.txt"})).await;
+    assert!(output.content.starts_with(&"x".repeat(100)));
+    assert!(output.content.contains("truncated after 100 bytes"));
+
+    let output = call(&tools, "run_command", json!({"command": "cat big.txt"})).await;
+    assert!(!output.is_error, "{}", output.content);
+    assert!(output.content.contains("truncated"));
+    assert!(output.content.len() < 400);
+}
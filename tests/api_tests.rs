@@ -37,7 +37,14 @@ fn test_chat_request_serialization() {
         model: "claude-3-5-sonnet-20241022".to_string(),
         max_tokens: 1000,
         messages,
+        system: None,
         temperature: Some(0.7),
+        top_p: None,
+        top_k: None,
+        stop_sequences: None,
+        metadata: None,
+        tools: None,
+        tool_choice: None,
         stream: Some(true),
     };
 
@@ -47,6 +54,48 @@ fn test_chat_request_serialization() {
     assert!(json.contains("Hi there!"));
 }
 
+#[test]
+fn test_chat_request_from_config() {
+    use ask::client::models::ChatRequest;
+    use ask::config::ApiConfig;
+
+    let json = serde_json::to_value(ChatRequest::from_config(
+        &ApiConfig::default(),
+        vec![Message::user("Hello".to_string())],
+        None,
+    ))
+    .unwrap();
+    for field in [
+        "top_p",
+        "top_k",
+        "stop_sequences",
+        "metadata",
+        "system",
+        "stream",
+    ] {
+        assert!(json.get(field).is_none(), "{} should be omitted", field);
+    }
+
+    let config = ApiConfig {
+        top_p: Some(0.9),
+        top_k: Some(40),
+        stop_sequences: vec!["###".to_string()],
+        user_id: Some("user-1234".to_string()),
+        ..ApiConfig::default()
+    };
+    let json = serde_json::to_value(ChatRequest::from_config(
+        &config,
+        vec![Message::user("Hello".to_string())],
+        Some("Be brief."),
+    ))
+    .unwrap();
+    assert_eq!(json["top_p"], json!(0.9f32));
+    assert_eq!(json["top_k"], 40);
+    assert_eq!(json["stop_sequences"], json!(["###"]));
+    assert_eq!(json["metadata"]["user_id"], "user-1234");
+    assert_eq!(json["system"], "Be brief.");
+}
+
 #[test]
 fn test_error_response_deserialization() {
     use ask::client::models::ErrorResponse;
@@ -78,10 +127,42 @@ fn test_stream_event_parsing() {
         }
     });
 
+    let event: StreamEvent = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(
+        event,
+        StreamEvent::TextDelta {
+            index: 0,
+            text: "Hello".to_string()
+        }
+    );
+    assert_eq!(event.text_delta(), Some("Hello"));
+    // API のイベントと同じ形で書き出す
+    assert_eq!(serde_json::to_value(&event).unwrap(), json);
+}
+
+#[test]
+fn test_stream_message_delta_parsing() {
+    use ask::client::models::{StreamEvent, Usage};
+
+    let json = json!({
+        "type": "message_delta",
+        "delta": {"stop_reason": "max_tokens", "stop_sequence": null},
+        "usage": {"output_tokens": 128}
+    });
+
     let event: StreamEvent = serde_json::from_value(json).unwrap();
-    assert_eq!(event.r#type, "content_block_delta");
-    assert!(event.delta.is_some());
-    assert_eq!(event.delta.unwrap().text, "Hello");
+    assert_eq!(
+        event,
+        StreamEvent::MessageDelta {
+            stop_reason: Some("max_tokens".to_string()),
+            stop_sequence: None,
+            usage: Some(Usage {
+                input_tokens: 0,
+                output_tokens: 128
+            }),
+        }
+    );
+    assert_eq!(event.text_delta(), None);
 }
 
 #[test]
@@ -115,3 +196,227 @@ fn test_ask_error_from_conversions() {
     let ask_error: AskError = json_error.into();
     assert!(matches!(ask_error, AskError::SerializationError(_)));
 }
+
+#[test]
+fn test_chat_response_text_joins_text_blocks() {
+    use ask::client::models::ChatResponse;
+
+    let json = json!({
+        "id": "msg_123",
+        "type": "message",
+        "role": "assistant",
+        "content": [
+            {"type": "text", "text": "Hello, "},
+            {"type": "text", "text": "world!"}
+        ],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 10, "output_tokens": 5}
+    });
+
+    let response: ChatResponse = serde_json::from_value(json).unwrap();
+    assert_eq!(response.text(), "Hello, world!");
+}
+
+#[test]
+fn test_message_request_builder() {
+    use ask::config::ApiConfig;
+
+    let client = ClaudeClient::new("test-api-key".to_string()).unwrap();
+    let builder = client
+        .messages()
+        .model("claude-3-5-haiku-20241022")
+        .system("You are terse.")
+        .max_tokens(256)
+        .temperature(0.2)
+        .top_k(10)
+        .stop_sequence("###")
+        .stop_sequence("END")
+        .user_id("user-1")
+        .user("Hello");
+    let request = builder.build();
+
+    assert_eq!(request.model, "claude-3-5-haiku-20241022");
+    assert_eq!(request.system.as_deref(), Some("You are terse."));
+    assert_eq!(request.max_tokens, 256);
+    assert_eq!(request.temperature, Some(0.2));
+    assert_eq!(request.top_k, Some(10));
+    assert_eq!(
+        request.stop_sequences,
+        Some(vec!["###".to_string(), "END".to_string()])
+    );
+    assert_eq!(
+        request.metadata.as_ref().unwrap().user_id.as_deref(),
+        Some("user-1")
+    );
+    assert_eq!(request.messages.len(), 1);
+    assert!(request.stream.is_none());
+
+    // config() は設定の値を反映し、メッセージとシステムプロンプトは残す
+    let config = ApiConfig {
+        model: "configured-model".to_string(),
+        top_p: Some(0.5),
+        ..ApiConfig::default()
+    };
+    let builder = client
+        .messages()
+        .system("Keep me")
+        .user("Hi")
+        .config(&config);
+    let request = builder.build();
+    assert_eq!(request.model, "configured-model");
+    assert_eq!(request.top_p, Some(0.5));
+    assert_eq!(request.system.as_deref(), Some("Keep me"));
+    assert_eq!(request.messages.len(), 1);
+}
+
+#[tokio::test]
+async fn test_message_request_requires_messages() {
+    let client = ClaudeClient::new("test-api-key".to_string()).unwrap();
+    let err = client.messages().send().await.unwrap_err();
+    assert!(matches!(err, AskError::InvalidInput(_)));
+}
+
+#[test]
+fn test_message_content_serialization() {
+    use ask::client::models::ContentBlock;
+
+    // テキストだけのメッセージは従来どおり文字列として送る
+    let text = serde_json::to_value(Message::user("Hello")).unwrap();
+    assert_eq!(text, json!({"role": "user", "content": "Hello"}));
+
+    let blocks = serde_json::to_value(Message::user(vec![
+        ContentBlock::image_url("https://example.com/cat.png"),
+        ContentBlock::text("Describe this"),
+    ]))
+    .unwrap();
+    assert_eq!(
+        blocks,
+        json!({
+            "role": "user",
+            "content": [
+                {"type": "image", "source": {"type": "url", "url": "https://example.com/cat.png"}},
+                {"type": "text", "text": "Describe this"}
+            ]
+        })
+    );
+
+    let parsed: Message = serde_json::from_value(blocks).unwrap();
+    assert!(parsed.content.has_attachments());
+    assert_eq!(parsed.text(), "Describe this");
+}
+
+#[test]
+fn test_unknown_content_blocks_round_trip() {
+    use ask::client::models::{ChatResponse, ContentBlock};
+
+    let thinking = serde_json::json!({"type": "thinking", "thinking": "Hmm", "signature": "sig"});
+    let response: ChatResponse = serde_json::from_value(serde_json::json!({
+        "id": "msg_1",
+        "type": "message",
+        "role": "assistant",
+        "content": [thinking, {"type": "text", "text": "Hi"}],
+        "model": "claude-test",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 1, "output_tokens": 1}
+    }))
+    .unwrap();
+
+    // 扱わないブロックも、次のリクエストでそのまま送り返せる
+    assert_eq!(response.content[0], ContentBlock::Unknown(thinking.clone()));
+    assert_eq!(response.text(), "Hi");
+    assert_eq!(
+        serde_json::to_value(&response.content[0]).unwrap(),
+        thinking
+    );
+}
+
+#[test]
+fn test_user_message_with_attachments() {
+    use ask::client::models::{ContentBlock, MediaSource, MessageContent};
+    use ask::client::user_message_with_attachments;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let image = dir.path().join("pixel.png");
+    std::fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "meeting notes").unwrap();
+
+    let message = user_message_with_attachments(
+        "Summarize",
+        &[
+            image.to_string_lossy().into_owned(),
+            notes.to_string_lossy().into_owned(),
+        ],
+    )
+    .unwrap();
+
+    let MessageContent::Blocks(blocks) = message.content else {
+        panic!("expected content blocks");
+    };
+    assert_eq!(blocks.len(), 3);
+    assert!(matches!(
+        blocks[0],
+        ContentBlock::Image {
+            source: MediaSource::Base64 { ref media_type, .. }
+        } if media_type == "image/png"
+    ));
+    assert!(matches!(
+        blocks[1],
+        ContentBlock::Document {
+            source: MediaSource::Text { ref data, .. },
+            title: Some(ref title),
+        } if data == "meeting notes" && title == "notes.txt"
+    ));
+    assert_eq!(blocks[2], ContentBlock::text("Summarize"));
+
+    // 添付がなければ文字列のまま
+    let plain = user_message_with_attachments("Hello", &[]).unwrap();
+    assert_eq!(plain.content, "Hello");
+
+    let unknown = dir.path().join("data.bin");
+    std::fs::write(&unknown, [0u8, 1, 2]).unwrap();
+    assert!(matches!(
+        user_message_with_attachments("?", &[unknown.to_string_lossy().into_owned()]),
+        Err(AskError::AttachmentError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_api_errors_keep_type_and_request_id() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/messages")
+        .with_status(404)
+        .with_header("content-type", "application/json")
+        .with_header("request-id", "req_011CabcXYZ")
+        .with_body(
+            json!({
+                "type": "error",
+                "error": {"type": "not_found_error", "message": "model: claude-unknown"}
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let client = ClaudeClient::new("test-api-key".to_string())
+        .unwrap()
+        .with_base_url(server.url());
+    let err = client
+        .messages()
+        .model("claude-unknown")
+        .user("Hi")
+        .send()
+        .await
+        .unwrap_err();
+
+    mock.assert_async().await;
+    assert!(
+        matches!(err, AskError::NotFound { ref message, .. } if message == "model: claude-unknown")
+    );
+    assert_eq!(err.request_id(), Some("req_011CabcXYZ"));
+    assert_eq!(err.to_string(), "Not found: model: claude-unknown");
+}
@@ -25,6 +25,56 @@ fn test_api_config_default() {
     assert_eq!(api_config.temperature, 0.7);
 }
 
+#[test]
+fn test_api_config_system_prompt() {
+    use ask::config::ApiConfig;
+
+    assert!(ApiConfig::default().system.is_none());
+
+    // system を含まない既存の設定ファイルも読み込める
+    let api_config: ApiConfig = toml::from_str(
+        "model = \"m\"\ntimeout = 30\nmax_tokens = 100\nstream = true\ntemperature = 0.5\n",
+    )
+    .unwrap();
+    assert!(api_config.system.is_none());
+    assert!(!toml::to_string(&api_config).unwrap().contains("system"));
+
+    let api_config = ApiConfig {
+        system: Some("You are terse.".to_string()),
+        ..ApiConfig::default()
+    };
+    let restored: ApiConfig = toml::from_str(&toml::to_string(&api_config).unwrap()).unwrap();
+    assert_eq!(restored.system.as_deref(), Some("You are terse."));
+}
+
+#[test]
+fn test_api_config_retry_policy() {
+    use ask::client::RetryPolicy;
+    use ask::config::ApiConfig;
+
+    // retry を含まない既存の設定ファイルは既定の方針になる
+    let api_config: ApiConfig = toml::from_str(
+        "model = \"m\"\ntimeout = 30\nmax_tokens = 100\nstream = true\ntemperature = 0.5\n",
+    )
+    .unwrap();
+    assert_eq!(api_config.retry, RetryPolicy::default());
+
+    // 一部の項目だけを指定できる
+    let api_config: ApiConfig = toml::from_str(
+        "model = \"m\"\ntimeout = 30\nmax_tokens = 100\nstream = true\ntemperature = 0.5\n\
+         [retry]\nmax_attempts = 5\n",
+    )
+    .unwrap();
+    assert_eq!(api_config.retry.max_attempts, 5);
+    assert_eq!(
+        api_config.retry.base_delay_ms,
+        RetryPolicy::default().base_delay_ms
+    );
+
+    let restored: ApiConfig = toml::from_str(&toml::to_string(&api_config).unwrap()).unwrap();
+    assert_eq!(restored.retry, api_config.retry);
+}
+
 #[test]
 fn test_output_config_default() {
     use ask::config::OutputConfig;
@@ -0,0 +1,204 @@
+use ask::conversation::{export_conversation, generate_title, ConversationStore, ExportFormat};
+use ask::{Conversation, Message};
+use tempfile::TempDir;
+
+fn sample_conversation(title: &str) -> Conversation {
+    let mut conversation = Conversation::new(title.to_string());
+    conversation.add_message(Message::user("Hello".to_string()));
+    conversation.add_message(Message::assistant("Hi there!".to_string()));
+    conversation
+}
+
+#[test]
+fn test_save_and_load_conversation() {
+    let store = ConversationStore::open_in_memory().unwrap();
+    let conversation = sample_conversation("Greeting");
+
+    store.save(&conversation).unwrap();
+
+    let loaded = store.load(&conversation.id).unwrap().unwrap();
+    assert_eq!(loaded.id, conversation.id);
+    assert_eq!(loaded.title, "Greeting");
+    assert_eq!(loaded.messages.len(), 2);
+    assert_eq!(loaded.messages[0].role, "user");
+    assert_eq!(loaded.messages[1].content, "Hi there!");
+    assert_eq!(loaded.created_at, conversation.created_at);
+}
+
+#[test]
+fn test_save_overwrites_messages() {
+    let store = ConversationStore::open_in_memory().unwrap();
+    let mut conversation = sample_conversation("Greeting");
+    store.save(&conversation).unwrap();
+
+    conversation.add_message(Message::user("How are you?".to_string()));
+    store.save(&conversation).unwrap();
+
+    let loaded = store.load(&conversation.id).unwrap().unwrap();
+    assert_eq!(loaded.messages.len(), 3);
+    assert_eq!(store.list(10).unwrap().len(), 1);
+}
+
+#[test]
+fn test_load_by_prefix() {
+    let store = ConversationStore::open_in_memory().unwrap();
+    let conversation = sample_conversation("Prefix");
+    store.save(&conversation).unwrap();
+
+    let loaded = store.load(&conversation.id[..8]).unwrap().unwrap();
+    assert_eq!(loaded.id, conversation.id);
+
+    assert!(store.load("does-not-exist").unwrap().is_none());
+}
+
+#[test]
+fn test_list_orders_by_updated_at() {
+    let store = ConversationStore::open_in_memory().unwrap();
+    let first = sample_conversation("First");
+    store.save(&first).unwrap();
+    let second = sample_conversation("Second");
+    store.save(&second).unwrap();
+
+    let summaries = store.list(10).unwrap();
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(summaries[0].title, "Second");
+    assert_eq!(summaries[0].message_count, 2);
+
+    assert_eq!(store.list(1).unwrap().len(), 1);
+}
+
+#[test]
+fn test_delete_and_clear() {
+    let store = ConversationStore::open_in_memory().unwrap();
+    let first = sample_conversation("First");
+    let second = sample_conversation("Second");
+    store.save(&first).unwrap();
+    store.save(&second).unwrap();
+
+    assert!(store.delete(&first.id).unwrap());
+    assert!(!store.delete(&first.id).unwrap());
+    assert!(store.load(&first.id).unwrap().is_none());
+
+    assert_eq!(store.clear().unwrap(), 1);
+    assert!(store.list(10).unwrap().is_empty());
+}
+
+#[test]
+fn test_prune_keeps_newest() {
+    let store = ConversationStore::open_in_memory().unwrap();
+    for i in 0..5 {
+        store
+            .save(&sample_conversation(&format!("C{}", i)))
+            .unwrap();
+    }
+
+    assert_eq!(store.prune(2).unwrap(), 3);
+    let titles: Vec<String> = store
+        .list(10)
+        .unwrap()
+        .into_iter()
+        .map(|c| c.title)
+        .collect();
+    assert_eq!(titles, vec!["C4", "C3"]);
+}
+
+#[test]
+fn test_store_persists_to_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("nested").join("history.db");
+    let conversation = sample_conversation("On disk");
+
+    ConversationStore::open(&path)
+        .unwrap()
+        .save(&conversation)
+        .unwrap();
+
+    let reopened = ConversationStore::open(&path).unwrap();
+    assert!(reopened.load(&conversation.id).unwrap().is_some());
+}
+
+#[test]
+fn test_generate_title() {
+    assert_eq!(
+        generate_title("\n  What is Rust?\nMore text"),
+        "What is Rust?"
+    );
+    assert_eq!(generate_title("   "), "Untitled");
+
+    let long = "a".repeat(80);
+    let title = generate_title(&long);
+    assert_eq!(title.chars().count(), 53);
+    assert!(title.ends_with("..."));
+}
+
+#[test]
+fn test_system_prompt_roundtrip() {
+    let store = ConversationStore::open_in_memory().unwrap();
+    let mut conversation = sample_conversation("With system");
+    conversation.system = Some("You are terse.".to_string());
+    store.save(&conversation).unwrap();
+
+    let loaded = store.load(&conversation.id).unwrap().unwrap();
+    assert_eq!(loaded.system.as_deref(), Some("You are terse."));
+}
+
+#[test]
+fn test_export_formats() {
+    let mut conversation = sample_conversation("Export me");
+    conversation.system = Some("Be brief.".to_string());
+
+    let markdown = export_conversation(&conversation, ExportFormat::Markdown).unwrap();
+    assert!(markdown.starts_with("# Export me"));
+    assert!(markdown.contains("## System\n\nBe brief."));
+    assert!(markdown.contains("## Claude\n\nHi there!"));
+
+    let text = export_conversation(&conversation, ExportFormat::Text).unwrap();
+    assert!(text.contains("You:\nHello"));
+
+    let json = export_conversation(&conversation, ExportFormat::Json).unwrap();
+    let parsed: ask::Conversation = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.id, conversation.id);
+    assert_eq!(parsed.messages.len(), 2);
+}
+
+#[test]
+fn test_export_format_from_extension() {
+    assert_eq!(
+        ExportFormat::from_extension("MD"),
+        Some(ExportFormat::Markdown)
+    );
+    assert_eq!(
+        ExportFormat::from_extension("json"),
+        Some(ExportFormat::Json)
+    );
+    assert_eq!(
+        ExportFormat::from_extension("txt"),
+        Some(ExportFormat::Text)
+    );
+    assert_eq!(ExportFormat::from_extension("pdf"), None);
+}
+
+#[test]
+fn test_save_and_load_message_with_attachments() {
+    use ask::client::models::{ContentBlock, MessageContent};
+
+    let store = ConversationStore::open_in_memory().unwrap();
+    let mut conversation = Conversation::new("Screenshot".to_string());
+    conversation.add_message(Message::user(vec![
+        ContentBlock::image_base64("image/png", "iVBORw0KGgo="),
+        ContentBlock::text("What is in this picture?"),
+    ]));
+    conversation.add_message(Message::assistant("A cat.".to_string()));
+    store.save(&conversation).unwrap();
+
+    let loaded = store.load(&conversation.id).unwrap().unwrap();
+    assert_eq!(loaded.messages[0].content, conversation.messages[0].content);
+    assert_eq!(loaded.messages[0].text(), "What is in this picture?");
+    assert!(matches!(
+        loaded.messages[1].content,
+        MessageContent::Text(_)
+    ));
+
+    let markdown = export_conversation(&loaded, ExportFormat::Markdown).unwrap();
+    assert!(markdown.contains("[image: image/png]\nWhat is in this picture?"));
+}
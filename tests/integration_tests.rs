@@ -115,3 +115,21 @@ fn test_output_format_display() {
     assert_eq!(OutputFormat::Json.to_string(), "json");
     assert_eq!(OutputFormat::Markdown.to_string(), "markdown");
 }
+
+#[test]
+fn test_extract_code_language_argument() {
+    use ask::clap::Parser;
+    use ask::Cli;
+
+    let cli = Cli::try_parse_from(["ask", "--extract-code", "rust", "Write hello"]).unwrap();
+    assert_eq!(cli.extract_code.as_deref(), Some("rust"));
+    assert_eq!(cli.message.as_deref(), Some("Write hello"));
+
+    let cli = Cli::try_parse_from(["ask", "Write hello", "--extract-code"]).unwrap();
+    assert_eq!(cli.extract_code.as_deref(), Some(""));
+    assert_eq!(cli.message.as_deref(), Some("Write hello"));
+
+    let cli = Cli::try_parse_from(["ask", "--extract-code", "--", "Write hello"]).unwrap();
+    assert_eq!(cli.extract_code.as_deref(), Some(""));
+    assert_eq!(cli.message.as_deref(), Some("Write hello"));
+}
@@ -0,0 +1,184 @@
+use ask::client::models::{ChatResponse, StreamEvent};
+use ask::config::OutputFormat;
+use ask::output::{renderer_for, RenderContext};
+use serde_json::json;
+
+fn context() -> RenderContext {
+    RenderContext {
+        model: "claude-3-5-sonnet-20241022".to_string(),
+        title: "Greeting".to_string(),
+        conversation_id: Some("conv-123".to_string()),
+        created_at: chrono::Utc::now(),
+    }
+}
+
+fn response() -> ChatResponse {
+    serde_json::from_value(json!({
+        "id": "msg_123",
+        "type": "message",
+        "role": "assistant",
+        "content": [{"type": "text", "text": "Hello!"}],
+        "model": "claude-3-5-sonnet-20241022",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 10, "output_tokens": 3}
+    }))
+    .unwrap()
+}
+
+fn delta(text: &str) -> StreamEvent {
+    serde_json::from_value(json!({
+        "type": "content_block_delta",
+        "index": 0,
+        "delta": {"type": "text_delta", "text": text}
+    }))
+    .unwrap()
+}
+
+fn render(format: OutputFormat, streamed: bool) -> String {
+    let mut renderer = renderer_for(&format, false);
+    let context = context();
+    let mut out = Vec::new();
+
+    renderer.begin(&mut out, &context).unwrap();
+    if streamed {
+        renderer.event(&mut out, &delta("Hel")).unwrap();
+        renderer.event(&mut out, &delta("lo!")).unwrap();
+    }
+    renderer
+        .finish(&mut out, &response(), &context, streamed)
+        .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_plain_output() {
+    assert_eq!(render(OutputFormat::Plain, false), "Hello!\n");
+    assert_eq!(render(OutputFormat::Plain, true), "Hello!\n");
+}
+
+#[test]
+fn test_json_output() {
+    let output: serde_json::Value =
+        serde_json::from_str(&render(OutputFormat::Json, false)).unwrap();
+    assert_eq!(output["model"], "claude-3-5-sonnet-20241022");
+    assert_eq!(output["stop_reason"], "end_turn");
+    assert_eq!(output["usage"]["output_tokens"], 3);
+    assert_eq!(output["content"][0]["text"], "Hello!");
+    assert_eq!(output["conversation_id"], "conv-123");
+}
+
+#[test]
+fn test_json_streaming_output_is_ndjson() {
+    let output = render(OutputFormat::Json, true);
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["type"], "content_block_delta");
+    assert_eq!(lines[1]["delta"]["text"], "lo!");
+    assert_eq!(lines[2]["type"], "result");
+    assert_eq!(lines[2]["stop_reason"], "end_turn");
+    assert_eq!(lines[2]["conversation_id"], "conv-123");
+}
+
+#[test]
+fn test_markdown_output_has_metadata_header() {
+    for streamed in [false, true] {
+        let output = render(OutputFormat::Markdown, streamed);
+        assert!(output.starts_with("# Greeting\n\n"));
+        assert!(output.contains("- **Model**: `claude-3-5-sonnet-20241022`\n"));
+        assert!(output.contains("- **Conversation**: `conv-123`\n"));
+        assert!(output.ends_with("---\n\nHello!\n"));
+    }
+}
+
+#[test]
+fn test_extract_code_blocks() {
+    use ask::output::extract_code_blocks;
+
+    let reply = "Here you go:\n\n```rust\nfn main() {}\n```\n\nAnd a script:\n\n~~~python:tools/run.py\nprint('hi')\n~~~\n\n```\nplain\n";
+    let blocks = extract_code_blocks(reply);
+
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks[0].language.as_deref(), Some("rust"));
+    assert_eq!(blocks[0].content, "fn main() {}\n");
+    assert_eq!(blocks[1].filename.as_deref(), Some("tools/run.py"));
+    assert_eq!(blocks[1].content, "print('hi')\n");
+    // 閉じていないブロックも取り出す
+    assert_eq!(blocks[2].language, None);
+    assert_eq!(blocks[2].content, "plain\n");
+}
+
+#[test]
+fn test_code_extraction_prints_and_writes_blocks() {
+    use ask::output::CodeExtraction;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let reply = "```rust\nfn a() {}\n```\ntext\n```python\nprint(1)\n```\n```rust main.rs\nfn main() {}\n```\n```rust main.rs\nfn other() {}\n```";
+
+    let mut extraction = CodeExtraction::new(Some("rs"), true, Some(dir.path().to_path_buf()));
+    let mut out = Vec::new();
+    // ストリーミングと同じく少しずつ渡す
+    for chunk in reply.as_bytes().chunks(5) {
+        extraction
+            .push(&mut out, std::str::from_utf8(chunk).unwrap())
+            .unwrap();
+    }
+    extraction.finish(&mut out).unwrap();
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "fn a() {}\n\nfn main() {}\n\nfn other() {}\n"
+    );
+    assert_eq!(extraction.matched(), 3);
+
+    let names: Vec<String> = extraction
+        .written()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names, vec!["code_1.rs", "main.rs", "main-2.rs"]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("main-2.rs")).unwrap(),
+        "fn other() {}\n"
+    );
+}
+
+#[test]
+fn test_code_extraction_does_not_overwrite_files() {
+    use ask::output::CodeExtraction;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("main.rs"), "// keep\n").unwrap();
+
+    let mut extraction = CodeExtraction::new(None, false, Some(dir.path().to_path_buf()));
+    let mut out = Vec::new();
+    extraction
+        .push(&mut out, "```rust main.rs\nfn main() {}\n```\n")
+        .unwrap();
+    extraction.finish(&mut out).unwrap();
+
+    assert_eq!(extraction.written(), [dir.path().join("main-2.rs")]);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("main.rs")).unwrap(),
+        "// keep\n"
+    );
+}
+
+#[test]
+fn test_json_output_includes_stop_sequence() {
+    let mut response = response();
+    response.stop_reason = Some("stop_sequence".to_string());
+    response.stop_sequence = Some("###".to_string());
+
+    let mut out = Vec::new();
+    renderer_for(&OutputFormat::Json, false)
+        .finish(&mut out, &response, &context(), false)
+        .unwrap();
+    let output: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(output["stop_reason"], "stop_sequence");
+    assert_eq!(output["stop_sequence"], "###");
+}
@@ -0,0 +1,229 @@
+use ask::client::{RetryPolicy, StreamEvent};
+use ask::{AskError, ClaudeClient};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_stream::StreamExt;
+
+fn response_body(text: &str) -> String {
+    json!({
+        "id": "msg_1",
+        "type": "message",
+        "role": "assistant",
+        "content": [{"type": "text", "text": text}],
+        "model": "claude-test",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 5, "output_tokens": 2}
+    })
+    .to_string()
+}
+
+fn stream_body(events: &[serde_json::Value]) -> String {
+    events
+        .iter()
+        .map(|event| {
+            format!(
+                "event: {}\ndata: {}\n\n",
+                event["type"].as_str().unwrap(),
+                event
+            )
+        })
+        .collect()
+}
+
+fn message_start() -> serde_json::Value {
+    json!({
+        "type": "message_start",
+        "message": {
+            "id": "msg_1", "type": "message", "role": "assistant", "content": [],
+            "model": "claude-test", "stop_reason": null, "stop_sequence": null,
+            "usage": {"input_tokens": 5, "output_tokens": 1}
+        }
+    })
+}
+
+/// 待ち時間なしで再試行するクライアント
+fn client(server: &mockito::Server, max_attempts: u32) -> ClaudeClient {
+    ClaudeClient::new("test-api-key".to_string())
+        .unwrap()
+        .with_base_url(server.url())
+        .with_retry_policy(RetryPolicy {
+            max_attempts,
+            base_delay_ms: 0,
+            jitter: 0.0,
+        })
+}
+
+#[tokio::test]
+async fn test_retries_overloaded_then_succeeds() {
+    let mut server = mockito::Server::new_async().await;
+    let overloaded = server
+        .mock("POST", "/messages")
+        .with_status(529)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#)
+        .expect(1)
+        .create_async()
+        .await;
+    let ok = server
+        .mock("POST", "/messages")
+        .with_header("content-type", "application/json")
+        .with_body(response_body("Hello"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let notices = Arc::new(Mutex::new(Vec::new()));
+    let recorded = notices.clone();
+    let client = client(&server, 3).on_retry(move |notice| {
+        recorded
+            .lock()
+            .unwrap()
+            .push((notice.attempt, notice.reason.to_string()))
+    });
+    let response = client.messages().user("Hi").send().await.unwrap();
+
+    overloaded.assert_async().await;
+    ok.assert_async().await;
+    assert_eq!(response.text(), "Hello");
+    let notices = notices.lock().unwrap();
+    assert_eq!(notices.len(), 1);
+    assert_eq!(notices[0].0, 2);
+    assert!(notices[0].1.contains("529"));
+}
+
+#[tokio::test]
+async fn test_honours_retry_after_header() {
+    let mut server = mockito::Server::new_async().await;
+    let limited = server
+        .mock("POST", "/messages")
+        .with_status(429)
+        .with_header("retry-after", "1")
+        .expect(1)
+        .create_async()
+        .await;
+    let ok = server
+        .mock("POST", "/messages")
+        .with_header("content-type", "application/json")
+        .with_body(response_body("Done"))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let remaining = Arc::new(Mutex::new(Vec::new()));
+    let recorded = remaining.clone();
+    let client =
+        client(&server, 2).on_retry(move |notice| recorded.lock().unwrap().push(notice.remaining));
+    let response = client.messages().user("Hi").send().await.unwrap();
+
+    limited.assert_async().await;
+    ok.assert_async().await;
+    assert_eq!(response.text(), "Done");
+    assert_eq!(
+        *remaining.lock().unwrap(),
+        [Duration::from_secs(1), Duration::ZERO]
+    );
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_attempts() {
+    let mut server = mockito::Server::new_async().await;
+    let limited = server
+        .mock("POST", "/messages")
+        .with_status(429)
+        .expect(3)
+        .create_async()
+        .await;
+
+    let result = client(&server, 3).messages().user("Hi").send().await;
+
+    limited.assert_async().await;
+    assert!(matches!(result, Err(AskError::RateLimitExceeded)));
+}
+
+#[tokio::test]
+async fn test_does_not_retry_client_errors() {
+    let mut server = mockito::Server::new_async().await;
+    let bad_request = server
+        .mock("POST", "/messages")
+        .with_status(400)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"type":"error","error":{"type":"invalid_request_error","message":"bad"}}"#)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let result = client(&server, 3).messages().user("Hi").send().await;
+
+    bad_request.assert_async().await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_retries_stream_that_fails_before_content() {
+    let mut server = mockito::Server::new_async().await;
+    let failed = server
+        .mock("POST", "/messages")
+        .with_header("content-type", "text/event-stream")
+        .with_body(stream_body(&[
+            message_start(),
+            json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}),
+        ]))
+        .expect(1)
+        .create_async()
+        .await;
+    let ok = server
+        .mock("POST", "/messages")
+        .with_header("content-type", "text/event-stream")
+        .with_body(stream_body(&[
+            message_start(),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hello"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "message_stop"}),
+        ]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let stream = client(&server, 2)
+        .messages()
+        .user("Hi")
+        .stream_text()
+        .await
+        .unwrap();
+    let texts: Vec<String> = Box::pin(stream).map(|text| text.unwrap()).collect().await;
+
+    failed.assert_async().await;
+    ok.assert_async().await;
+    assert_eq!(texts, ["Hello"]);
+}
+
+#[tokio::test]
+async fn test_stream_error_after_content_is_not_retried() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/messages")
+        .with_header("content-type", "text/event-stream")
+        .with_body(stream_body(&[
+            message_start(),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hel"}}),
+            json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}),
+        ]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let stream = client(&server, 3)
+        .messages()
+        .user("Hi")
+        .stream()
+        .await
+        .unwrap();
+    let events: Vec<StreamEvent> = Box::pin(stream).map(|event| event.unwrap()).collect().await;
+
+    mock.assert_async().await;
+    assert_eq!(events[1].text_delta(), Some("Hel"));
+    assert!(events.last().unwrap().to_error().is_some());
+}
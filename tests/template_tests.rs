@@ -0,0 +1,357 @@
+use ask::template::{parse_variables, render, TemplateStore};
+use ask::AskError;
+use tempfile::TempDir;
+
+#[test]
+fn test_template_store_roundtrip() {
+    let dir = TempDir::new().unwrap();
+    let store = TemplateStore::new(dir.path().join("templates"));
+
+    assert!(store.list().unwrap().is_empty());
+
+    store
+        .save("code-review", "Please review this code: {{code}}")
+        .unwrap();
+    store.save("summary", "Summarize: {{text}}").unwrap();
+
+    assert_eq!(store.list().unwrap(), vec!["code-review", "summary"]);
+    assert!(store.exists("summary").unwrap());
+
+    let template = store.load("code-review").unwrap();
+    assert_eq!(template.content, "Please review this code: {{code}}");
+
+    store.delete("summary").unwrap();
+    assert_eq!(store.list().unwrap(), vec!["code-review"]);
+    assert!(matches!(
+        store.load("summary"),
+        Err(AskError::TemplateError(_))
+    ));
+}
+
+#[test]
+fn test_template_store_rejects_path_names() {
+    let dir = TempDir::new().unwrap();
+    let store = TemplateStore::new(dir.path());
+
+    assert!(store.save("../escape", "x").is_err());
+    assert!(store.save("nested/name", "x").is_err());
+    assert!(store.save(".hidden", "x").is_err());
+    assert!(store.save("", "x").is_err());
+}
+
+#[test]
+fn test_parse_variables() {
+    let vars = parse_variables(&[
+        "code=fn main() { let a = 1; }".to_string(),
+        "lang=Rust".to_string(),
+    ])
+    .unwrap();
+
+    assert_eq!(vars["code"], "fn main() { let a = 1; }");
+    assert_eq!(vars["lang"], "Rust");
+
+    assert!(parse_variables(&["novalue".to_string()]).is_err());
+    assert!(parse_variables(&["=value".to_string()]).is_err());
+}
+
+#[test]
+fn test_render_stored_template() {
+    let dir = TempDir::new().unwrap();
+    let store = TemplateStore::new(dir.path());
+    store
+        .save("greet", "Hello {{name}}, welcome to {{place}}!")
+        .unwrap();
+
+    let template = store.load("greet").unwrap();
+    let vars = parse_variables(&["name=Ada".to_string()]).unwrap();
+
+    let err = render(&template.content, &vars).unwrap_err();
+    assert!(err.to_string().contains("place"));
+
+    let vars = parse_variables(&["name=Ada".to_string(), "place=Rust".to_string()]).unwrap();
+    assert_eq!(
+        render(&template.content, &vars).unwrap(),
+        "Hello Ada, welcome to Rust!"
+    );
+}
+
+#[test]
+fn test_template_front_matter_overrides_api_config() {
+    use ask::config::ApiConfig;
+
+    let dir = TempDir::new().unwrap();
+    let store = TemplateStore::new(dir.path());
+    store
+        .save(
+            "review",
+            "+++\ndescription = \"Code review\"\nmodel = \"claude-3-5-haiku-20241022\"\nmax_tokens = 1000\nsystem = \"You are a strict reviewer.\"\n\n[variables.lang]\ndefault = \"Rust\"\n+++\nReview this {{lang}} code: {{code}}",
+        )
+        .unwrap();
+
+    let template = store.load("review").unwrap();
+    assert_eq!(template.summary(), "Code review");
+    assert_eq!(
+        template.metadata.system.as_deref(),
+        Some("You are a strict reviewer.")
+    );
+
+    let mut api = ApiConfig::default();
+    template.metadata.apply_to(&mut api);
+    assert_eq!(api.model, "claude-3-5-haiku-20241022");
+    assert_eq!(api.max_tokens, 1000);
+    assert_eq!(api.temperature, ApiConfig::default().temperature);
+
+    let vars = parse_variables(&["code=fn main() {}".to_string()]).unwrap();
+    assert_eq!(
+        template.render(&vars, &store).unwrap(),
+        "Review this Rust code: fn main() {}"
+    );
+}
+
+#[test]
+fn test_template_includes_from_store() {
+    let dir = TempDir::new().unwrap();
+    let store = TemplateStore::new(dir.path());
+    store
+        .save(
+            "review-rules",
+            "+++\ndescription = \"Shared rules\"\n+++\n{% for rule in rules %}\n- {{ rule | trim }}\n{% endfor %}\n",
+        )
+        .unwrap();
+    store
+        .save(
+            "review",
+            "Review this {{ lang | upper }} code.\n{% include \"review-rules\" %}\n{% if strict %}\nBe strict.\n{% endif %}\n{{ code | indent(2) }}",
+        )
+        .unwrap();
+    store.save("loop-a", "{% include \"loop-b\" %}").unwrap();
+    store.save("loop-b", "{% include \"loop-a\" %}").unwrap();
+
+    let template = store.load("review").unwrap();
+    let vars = parse_variables(&[
+        "lang=rust".to_string(),
+        "rules=no unwrap, document panics".to_string(),
+        "code=fn a() {}\nfn b() {}".to_string(),
+    ])
+    .unwrap();
+    assert_eq!(
+        template.render(&vars, &store).unwrap(),
+        "Review this RUST code.\n- no unwrap\n- document panics\n  fn a() {}\n  fn b() {}"
+    );
+
+    let err = store
+        .load("loop-a")
+        .unwrap()
+        .render(&Default::default(), &store)
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Include cycle detected: loop-a -> loop-b -> loop-a"));
+}
+
+#[test]
+fn test_validate_template_content() {
+    use ask::template::validate;
+
+    let template = validate(
+        "ok",
+        "+++\nmodel = \"m\"\n+++\n{% if a %}{{ b }}{% endif %}",
+    )
+    .unwrap();
+    assert_eq!(template.metadata.model.as_deref(), Some("m"));
+
+    let err = validate("broken", "+++\nmodel =\n+++\nbody").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("broken: Invalid TOML front-matter"));
+
+    let err = validate("broken", "{% for x in %}").unwrap_err();
+    assert!(err.to_string().contains("broken:"));
+}
+
+#[test]
+fn test_template_search_path_precedence() {
+    use ask::template::discover_project_dirs;
+
+    let project = TempDir::new().unwrap();
+    let shared = TempDir::new().unwrap();
+    let personal = TempDir::new().unwrap();
+
+    let project_dir = project.path().join(".ask/templates");
+    let nested = project.path().join("src/module");
+    std::fs::create_dir_all(&project_dir).unwrap();
+    std::fs::create_dir_all(&nested).unwrap();
+    assert_eq!(discover_project_dirs(&nested), vec![project_dir.clone()]);
+
+    TemplateStore::new(&project_dir)
+        .save("review", "project review")
+        .unwrap();
+    TemplateStore::new(shared.path())
+        .save("review", "shared review")
+        .unwrap();
+    TemplateStore::new(shared.path())
+        .save("commit", "shared commit")
+        .unwrap();
+
+    let store = TemplateStore::with_search_paths(
+        vec![project_dir.clone(), shared.path().to_path_buf()],
+        personal.path(),
+    );
+    assert_eq!(store.dir(), personal.path());
+    assert_eq!(store.list().unwrap(), vec!["commit", "review"]);
+    assert_eq!(store.load("review").unwrap().content, "project review");
+
+    // 既存のテンプレートは見つかった場所で更新し、新しいものはデフォルトのディレクトリに保存する
+    store.save("commit", "updated commit").unwrap();
+    assert_eq!(
+        std::fs::read_to_string(shared.path().join("commit.md")).unwrap(),
+        "updated commit"
+    );
+    let path = store.save("new", "new template").unwrap();
+    assert_eq!(path, personal.path().join("new.md"));
+}
+
+#[test]
+fn test_template_pack_roundtrip() {
+    use ask::template::{PackFormat, TemplatePack};
+
+    let dir = TempDir::new().unwrap();
+    let store = TemplateStore::new(dir.path());
+    store
+        .save(
+            "review",
+            "+++\ndescription = \"Review\"\n+++\nReview {{ code }}",
+        )
+        .unwrap();
+    store.save("commit", "Write a commit message").unwrap();
+
+    let pack = TemplatePack::from_store(&store, &[]).unwrap();
+    assert_eq!(pack.templates.len(), 2);
+
+    for format in [PackFormat::Json, PackFormat::Tar] {
+        let bytes = pack.to_bytes(format).unwrap();
+        let restored = TemplatePack::from_bytes(&bytes, format).unwrap();
+        let names: Vec<&str> = restored.templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["commit", "review"]);
+        assert_eq!(
+            restored.templates[1].content,
+            "+++\ndescription = \"Review\"\n+++\nReview {{ code }}"
+        );
+    }
+
+    let selected = TemplatePack::from_store(&store, &["review".to_string()]).unwrap();
+    assert_eq!(selected.templates.len(), 1);
+    assert!(TemplatePack::from_store(&store, &["missing".to_string()]).is_err());
+}
+
+#[test]
+fn test_template_pack_import_conflicts() {
+    use ask::template::{
+        import_pack, ConflictStrategy, ImportOutcome, PackedTemplate, TemplatePack,
+    };
+
+    let pack = TemplatePack {
+        version: 1,
+        templates: vec![
+            PackedTemplate {
+                name: "review".to_string(),
+                content: "imported review".to_string(),
+            },
+            PackedTemplate {
+                name: "commit".to_string(),
+                content: "imported commit".to_string(),
+            },
+        ],
+    };
+
+    let setup = || {
+        let dir = TempDir::new().unwrap();
+        let store = TemplateStore::new(dir.path());
+        store.save("review", "local review").unwrap();
+        store.save("review-1", "local review 1").unwrap();
+        (dir, store)
+    };
+
+    let (_dir, store) = setup();
+    let outcomes = import_pack(&pack, &store, ConflictStrategy::Skip).unwrap();
+    assert!(matches!(&outcomes[0], ImportOutcome::Skipped { name } if name == "review"));
+    assert!(matches!(&outcomes[1], ImportOutcome::Created { name, .. } if name == "commit"));
+    assert_eq!(store.load("review").unwrap().content, "local review");
+
+    let (_dir, store) = setup();
+    import_pack(&pack, &store, ConflictStrategy::Overwrite).unwrap();
+    assert_eq!(store.load("review").unwrap().content, "imported review");
+
+    let (_dir, store) = setup();
+    let outcomes = import_pack(&pack, &store, ConflictStrategy::Rename).unwrap();
+    assert!(matches!(&outcomes[0], ImportOutcome::Renamed { to, .. } if to == "review-2"));
+    assert_eq!(store.load("review").unwrap().content, "local review");
+    assert_eq!(store.load("review-2").unwrap().content, "imported review");
+}
+
+#[test]
+fn test_template_pack_import_leaves_other_search_paths_alone() {
+    use ask::template::{
+        import_pack, ConflictStrategy, ImportOutcome, PackedTemplate, TemplatePack,
+    };
+
+    let pack = TemplatePack {
+        version: 1,
+        templates: vec![PackedTemplate {
+            name: "review".to_string(),
+            content: "imported review".to_string(),
+        }],
+    };
+
+    let dir = TempDir::new().unwrap();
+    let shared = dir.path().join("shared");
+    let default_dir = dir.path().join("default");
+    TemplateStore::new(&shared)
+        .save("review", "shared review")
+        .unwrap();
+    let store = TemplateStore::with_search_paths(vec![shared.clone()], &default_dir);
+
+    // 共有ディレクトリのテンプレートは衝突とみなさず、上書きもしない
+    let outcomes = import_pack(&pack, &store, ConflictStrategy::Overwrite).unwrap();
+    assert!(
+        matches!(&outcomes[0], ImportOutcome::Created { path, .. } if path.starts_with(&default_dir))
+    );
+    assert_eq!(
+        std::fs::read_to_string(shared.join("review.md")).unwrap(),
+        "shared review"
+    );
+
+    // 取り込み先にあるものだけが衝突になる
+    let outcomes = import_pack(&pack, &store, ConflictStrategy::Rename).unwrap();
+    assert!(matches!(&outcomes[0], ImportOutcome::Renamed { to, .. } if to == "review-1"));
+    assert!(default_dir.join("review-1.md").is_file());
+    assert!(!shared.join("review-1.md").exists());
+}
+
+#[test]
+fn test_template_pack_rejects_invalid_templates() {
+    use ask::template::{import_pack, ConflictStrategy, PackFormat, TemplatePack};
+
+    let json = r#"{"version":1,"templates":[
+        {"name":"good","content":"fine"},
+        {"name":"bad","content":"{% if x %}unclosed"}
+    ]}"#;
+    let err = TemplatePack::from_bytes(json.as_bytes(), PackFormat::Json).unwrap_err();
+    assert!(err.to_string().contains("bad:"));
+
+    let json = r#"{"version":1,"templates":[{"name":"../evil","content":"x"}]}"#;
+    assert!(TemplatePack::from_bytes(json.as_bytes(), PackFormat::Json).is_err());
+
+    // 不正なテンプレートを含むパックは一部だけ取り込まれることもない
+    let pack: TemplatePack = serde_json::from_str(
+        r#"{"version":1,"templates":[
+            {"name":"good","content":"fine"},
+            {"name":"bad","content":"{% for %}"}
+        ]}"#,
+    )
+    .unwrap();
+    let dir = TempDir::new().unwrap();
+    let store = TemplateStore::new(dir.path());
+    assert!(import_pack(&pack, &store, ConflictStrategy::Skip).is_err());
+    assert!(!store.exists("good").unwrap());
+}
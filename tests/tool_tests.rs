@@ -0,0 +1,184 @@
+use ask::client::models::{ContentBlock, MessageContent, ToolChoice};
+use ask::client::{Tool, ToolLoopEvent, ToolOutput, ToolSet};
+use ask::{ClaudeClient, Message};
+use futures::future::BoxFuture;
+use mockito::Matcher;
+use serde_json::{json, Value};
+
+struct Add;
+
+impl Tool for Add {
+    fn name(&self) -> &str {
+        "add"
+    }
+
+    fn description(&self) -> &str {
+        "Adds two numbers"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {"a": {"type": "number"}, "b": {"type": "number"}},
+            "required": ["a", "b"]
+        })
+    }
+
+    fn call(&self, input: Value) -> BoxFuture<'_, ask::Result<ToolOutput>> {
+        Box::pin(async move {
+            let sum = input["a"].as_f64().unwrap_or(0.0) + input["b"].as_f64().unwrap_or(0.0);
+            Ok(ToolOutput::text(sum.to_string()))
+        })
+    }
+}
+
+fn tool_use_response() -> Value {
+    json!({
+        "id": "msg_1",
+        "type": "message",
+        "role": "assistant",
+        "content": [
+            {"type": "text", "text": "Let me add those."},
+            {"type": "tool_use", "id": "toolu_1", "name": "add", "input": {"a": 2, "b": 3}}
+        ],
+        "model": "claude-test",
+        "stop_reason": "tool_use",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 20, "output_tokens": 10}
+    })
+}
+
+fn final_response() -> Value {
+    json!({
+        "id": "msg_2",
+        "type": "message",
+        "role": "assistant",
+        "content": [{"type": "text", "text": "2 + 3 = 5"}],
+        "model": "claude-test",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 40, "output_tokens": 5}
+    })
+}
+
+#[test]
+fn test_tool_request_serialization() {
+    let tools = ToolSet::new().with(Add);
+    let client = ClaudeClient::new("test-api-key".to_string()).unwrap();
+    let request = client
+        .messages()
+        .tools(&tools)
+        .tool_choice(ToolChoice::Tool {
+            name: "add".to_string(),
+        })
+        .user("What is 2+3?");
+
+    let value = serde_json::to_value(request.build()).unwrap();
+    assert_eq!(value["tools"][0]["name"], "add");
+    assert_eq!(
+        value["tools"][0]["input_schema"]["required"],
+        json!(["a", "b"])
+    );
+    assert_eq!(value["tool_choice"], json!({"type": "tool", "name": "add"}));
+
+    let result = serde_json::to_value(ContentBlock::tool_result("toolu_1", "5", false)).unwrap();
+    assert_eq!(
+        result,
+        json!({"type": "tool_result", "tool_use_id": "toolu_1", "content": "5"})
+    );
+}
+
+#[tokio::test]
+async fn test_tool_loop_runs_until_end_turn() {
+    let mut server = mockito::Server::new_async().await;
+    let first = server
+        .mock("POST", "/messages")
+        .match_body(Matcher::Regex(
+            r#""content":"What is 2\+3\?"\}\]"#.to_string(),
+        ))
+        .with_header("content-type", "application/json")
+        .with_body(tool_use_response().to_string())
+        .expect(1)
+        .create_async()
+        .await;
+    let second = server
+        .mock("POST", "/messages")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex(
+                r#""type":"tool_result","tool_use_id":"toolu_1","content":"5""#.to_string(),
+            ),
+            Matcher::Regex(r#""tools":\[\{"name":"add""#.to_string()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(final_response().to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = ClaudeClient::new("test-api-key".to_string())
+        .unwrap()
+        .with_base_url(server.url());
+    let tools = ToolSet::new().with(Add);
+    let mut events = Vec::new();
+    let run = client
+        .messages()
+        .model("claude-test")
+        .user("What is 2+3?")
+        .run_tools_with(&tools, 5, |event| {
+            events.push(match event {
+                ToolLoopEvent::Response(response) => format!("response {}", response.id),
+                ToolLoopEvent::ToolCall(call) => format!("call {}", call.name),
+            })
+        })
+        .await
+        .unwrap();
+
+    first.assert_async().await;
+    second.assert_async().await;
+    assert_eq!(events, ["response msg_1", "call add", "response msg_2"]);
+    assert_eq!(run.text(), "2 + 3 = 5");
+    assert_eq!(run.iterations, 2);
+    assert!(!run.limit_reached);
+    assert_eq!(run.usage.input_tokens, 60);
+    assert_eq!(run.calls.len(), 1);
+    assert_eq!(run.calls[0].output, ToolOutput::text("5"));
+
+    // アシスタントの応答・ツールの結果・最終的な応答の3つが追加される
+    assert_eq!(run.messages.len(), 3);
+    assert!(matches!(
+        run.messages[1],
+        Message {
+            ref role,
+            content: MessageContent::Blocks(ref blocks),
+        } if role == "user" && blocks == &[ContentBlock::tool_result("toolu_1", "5", false)]
+    ));
+}
+
+#[tokio::test]
+async fn test_tool_loop_stops_at_iteration_cap() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/messages")
+        .with_header("content-type", "application/json")
+        .with_body(tool_use_response().to_string())
+        .expect(2)
+        .create_async()
+        .await;
+
+    let client = ClaudeClient::new("test-api-key".to_string())
+        .unwrap()
+        .with_base_url(server.url());
+    let tools = ToolSet::new().with(Add);
+    let run = client
+        .messages()
+        .user("Keep adding")
+        .run_tools_with(&tools, 2, |_| {})
+        .await
+        .unwrap();
+
+    mock.assert_async().await;
+    assert!(run.limit_reached);
+    assert_eq!(run.iterations, 2);
+    assert_eq!(run.calls.len(), 2);
+    assert_eq!(run.messages.last().unwrap().role, "user");
+}